@@ -0,0 +1,263 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Write};
+use std::path::Path;
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::utils::{format_timestamp, parse_timestamp};
+use crate::vs_handler::vsnode::VSFramerate;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum BookmarkFormat {
+    /// One frame number per line
+    #[default]
+    FrameList,
+    /// x264/x265 `--qpfile`, bookmarks are forced keyframes
+    QpFile,
+    OgmChapters,
+    MatroskaChapters,
+}
+
+/// Bookmarked frames, persisted per script
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Bookmarks {
+    /// Frames by canonical script path
+    scripts: HashMap<String, BTreeSet<u32>>,
+
+    #[serde(skip)]
+    script: String,
+}
+
+impl Bookmarks {
+    /// Bookmarks apply to this script, until changed
+    pub fn set_script(&mut self, script_file: &Path) {
+        let path = std::fs::canonicalize(script_file).unwrap_or_else(|_| script_file.to_owned());
+        self.script = path.to_string_lossy().into_owned();
+    }
+
+    /// Canonical path of the current script
+    pub fn script(&self) -> &str {
+        &self.script
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = u32> + '_ {
+        self.scripts
+            .get(&self.script)
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scripts.get(&self.script).map_or(0, BTreeSet::len)
+    }
+
+    pub fn contains(&self, frame_no: u32) -> bool {
+        self.scripts
+            .get(&self.script)
+            .is_some_and(|frames| frames.contains(&frame_no))
+    }
+
+    pub fn toggle(&mut self, frame_no: u32) {
+        let frames = self.scripts.entry(self.script.clone()).or_default();
+
+        if !frames.remove(&frame_no) {
+            frames.insert(frame_no);
+        }
+
+        // Only scripts with bookmarks are persisted
+        if frames.is_empty() {
+            self.scripts.remove(&self.script);
+        }
+    }
+
+    pub fn replace(&mut self, frames: BTreeSet<u32>) {
+        if frames.is_empty() {
+            self.scripts.remove(&self.script);
+        } else {
+            self.scripts.insert(self.script.clone(), frames);
+        }
+    }
+
+    pub fn next(&self, frame_no: u32) -> Option<u32> {
+        self.frames().find(|f| *f > frame_no)
+    }
+
+    pub fn previous(&self, frame_no: u32) -> Option<u32> {
+        self.frames().filter(|f| *f < frame_no).last()
+    }
+
+    /// Chapters require a constant framerate
+    pub fn export(&self, format: BookmarkFormat, framerate: Option<VSFramerate>) -> Result<String> {
+        let mut out = String::new();
+
+        match format {
+            BookmarkFormat::FrameList => {
+                for frame_no in self.frames() {
+                    writeln!(out, "{frame_no}")?;
+                }
+            }
+            BookmarkFormat::QpFile => {
+                for frame_no in self.frames() {
+                    writeln!(out, "{frame_no} K")?;
+                }
+            }
+            BookmarkFormat::OgmChapters => {
+                let framerate = chapters_framerate(framerate)?;
+
+                for (i, frame_no) in self.frames().enumerate() {
+                    let time = format_timestamp(framerate.frame_to_nanos(frame_no), 3);
+                    let num = i + 1;
+
+                    writeln!(out, "CHAPTER{num:02}={time}")?;
+                    writeln!(out, "CHAPTER{num:02}NAME=Chapter {num:02}")?;
+                }
+            }
+            BookmarkFormat::MatroskaChapters => {
+                let framerate = chapters_framerate(framerate)?;
+
+                writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+                writeln!(out, "<Chapters>")?;
+                writeln!(out, "  <EditionEntry>")?;
+
+                for (i, frame_no) in self.frames().enumerate() {
+                    let time = format_timestamp(framerate.frame_to_nanos(frame_no), 9);
+
+                    writeln!(out, "    <ChapterAtom>")?;
+                    writeln!(out, "      <ChapterTimeStart>{time}</ChapterTimeStart>")?;
+                    writeln!(out, "      <ChapterDisplay>")?;
+                    writeln!(
+                        out,
+                        "        <ChapterString>Chapter {:02}</ChapterString>",
+                        i + 1
+                    )?;
+                    writeln!(out, "      </ChapterDisplay>")?;
+                    writeln!(out, "    </ChapterAtom>")?;
+                }
+
+                writeln!(out, "  </EditionEntry>")?;
+                writeln!(out, "</Chapters>")?;
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Parses any of the export formats, detected from the contents
+    pub fn import(contents: &str, framerate: Option<VSFramerate>) -> Result<BTreeSet<u32>> {
+        if contents.contains("<ChapterTimeStart>") {
+            let framerate = chapters_framerate(framerate)?;
+
+            contents
+                .split("<ChapterTimeStart>")
+                .skip(1)
+                .map(|part| {
+                    let time = part.split("</").next().unwrap_or_default();
+                    parse_chapter_time(time, framerate)
+                })
+                .collect()
+        } else if contents.trim_start().starts_with("CHAPTER") {
+            let framerate = chapters_framerate(framerate)?;
+
+            contents
+                .lines()
+                .filter_map(|line| line.trim().split_once('='))
+                .filter(|(key, _)| key.starts_with("CHAPTER") && !key.ends_with("NAME"))
+                .map(|(_, time)| parse_chapter_time(time, framerate))
+                .collect()
+        } else {
+            // Frame lists and qpfiles start with the frame number
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| {
+                    let frame = line.split_whitespace().next().unwrap_or_default();
+
+                    frame
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid frame number `{frame}`"))
+                })
+                .collect()
+        }
+    }
+
+    /// Shows a save dialog, from the command loop
+    pub fn export_dialog(contents: String, format: BookmarkFormat) -> Result<()> {
+        let (name, ext) = format.default_file();
+
+        let file = rfd::FileDialog::new()
+            .set_title("Export bookmarks")
+            .add_filter(format.to_string(), &[ext])
+            .set_file_name(format!("{name}.{ext}"))
+            .save_file();
+
+        if let Some(file) = file {
+            std::fs::write(file, contents)?;
+        }
+
+        Ok(())
+    }
+
+    /// Shows an open dialog, from the command loop.
+    /// Returns `None` when cancelled.
+    pub fn import_dialog(framerate: Option<VSFramerate>) -> Result<Option<BTreeSet<u32>>> {
+        let file = rfd::FileDialog::new()
+            .set_title("Import bookmarks")
+            .add_filter("Frames, qpfile or chapters", &["txt", "qp", "xml"])
+            .pick_file();
+
+        let Some(file) = file else {
+            return Ok(None);
+        };
+
+        let contents = std::fs::read_to_string(file)?;
+
+        Self::import(&contents, framerate).map(Some)
+    }
+}
+
+impl BookmarkFormat {
+    pub const ALL: [Self; 4] = [
+        Self::FrameList,
+        Self::QpFile,
+        Self::OgmChapters,
+        Self::MatroskaChapters,
+    ];
+
+    fn default_file(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::FrameList => ("bookmarks", "txt"),
+            Self::QpFile => ("qpfile", "txt"),
+            Self::OgmChapters => ("chapters", "txt"),
+            Self::MatroskaChapters => ("chapters", "xml"),
+        }
+    }
+}
+
+fn chapters_framerate(framerate: Option<VSFramerate>) -> Result<VSFramerate> {
+    match framerate {
+        Some(fr) if fr.fr_num > 0 && fr.fr_denom > 0 => Ok(fr),
+        _ => bail!("Chapters require a clip with a constant framerate"),
+    }
+}
+
+fn parse_chapter_time(time: &str, framerate: VSFramerate) -> Result<u32> {
+    parse_timestamp(time)
+        .map(|nanos| framerate.nanos_to_frame(nanos))
+        .ok_or_else(|| anyhow!("Invalid chapter time `{}`", time.trim()))
+}
+
+impl Display for BookmarkFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let val = match self {
+            BookmarkFormat::FrameList => "Frame list",
+            BookmarkFormat::QpFile => "qpfile",
+            BookmarkFormat::OgmChapters => "OGM chapters",
+            BookmarkFormat::MatroskaChapters => "Matroska chapters",
+        };
+
+        f.write_str(val)
+    }
+}
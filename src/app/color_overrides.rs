@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use super::{PreviewOutput, VSColorOverrides};
+
+/// Color conversion overrides of the outputs, persisted per script
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SavedColorOverrides {
+    /// Overrides by output index, by canonical script path
+    scripts: HashMap<String, HashMap<i32, VSColorOverrides>>,
+}
+
+impl SavedColorOverrides {
+    pub fn get(&self, script: &str, output: i32) -> VSColorOverrides {
+        self.scripts
+            .get(script)
+            .and_then(|outputs| outputs.get(&output))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Stores the overrides of the outputs, other outputs of the script are kept
+    pub fn update(&mut self, script: &str, outputs: &HashMap<i32, PreviewOutput>) {
+        let saved = self.scripts.entry(script.to_owned()).or_default();
+
+        for (key, output) in outputs {
+            if output.color_overrides == VSColorOverrides::default() {
+                saved.remove(key);
+            } else {
+                saved.insert(*key, output.color_overrides);
+            }
+        }
+
+        // Only scripts with overrides are persisted
+        if saved.is_empty() {
+            self.scripts.remove(script);
+        }
+    }
+}
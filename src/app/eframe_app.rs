@@ -8,6 +8,9 @@ use super::*;
 pub struct SavedState {
     preview_state: PreviewState,
     transforms: PreviewTransforms,
+    bookmarks: Bookmarks,
+    pinned_props: Vec<String>,
+    color_overrides: SavedColorOverrides,
 }
 
 impl VSPreviewer {
@@ -19,8 +22,14 @@ impl VSPreviewer {
 
             self.state = saved_state.preview_state;
             self.transforms = Arc::new(Mutex::new(saved_state.transforms));
+            self.bookmarks = saved_state.bookmarks;
+            self.pinned_props = saved_state.pinned_props;
+            self.saved_color_overrides = saved_state.color_overrides;
         }
 
+        let script_file = self.script.lock().get_script_file();
+        self.bookmarks.set_script(&script_file);
+
         // Set the global theme, default to dark mode
         let mut global_visuals = egui::style::Visuals::dark();
         global_visuals.window_shadow = Shadow {
@@ -44,6 +53,7 @@ impl VSPreviewer {
         self.state.zoom_multiplier = self.state.zoom_multiplier.clamp(1.0, 2.0);
 
         self.init_transforms();
+        self.start_ipc_server(&cc.egui_ctx);
 
         // Request initial outputs
         self.reload(cc.egui_ctx.clone());
@@ -67,6 +77,9 @@ impl eframe::App for VSPreviewer {
             .inner_margin(MarginF32::same(self.state.canvas_margin))
             .stroke(Stroke::NONE);
 
+        // Docked scopes take space from the canvas
+        UiScopes::ui(self, ctx);
+
         egui::CentralPanel::default()
             .frame(panel_frame)
             .show(ctx, |ui| {
@@ -94,9 +107,15 @@ impl eframe::App for VSPreviewer {
     }
 
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.saved_color_overrides
+            .update(self.bookmarks.script(), &self.outputs);
+
         let saved_state = SavedState {
             preview_state: self.state,
             transforms: self.transforms.lock().clone(),
+            bookmarks: self.bookmarks.clone(),
+            pinned_props: self.pinned_props.clone(),
+            color_overrides: self.saved_color_overrides.clone(),
         };
 
         eframe::set_value(storage, eframe::APP_KEY, &saved_state);
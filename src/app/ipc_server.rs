@@ -0,0 +1,567 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow, bail};
+use eframe::egui::Context;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_stream::StreamExt;
+
+use super::VSPreviewer;
+use crate::utils::parse_timestamp;
+use crate::vs_handler::VSNode;
+use crate::vs_handler::vsprops::{VSPropData, VSPropValue, VSPropsMap};
+
+/// Events not read by slow clients are dropped past this
+const EVENT_CAPACITY: usize = 64;
+/// Clients sending longer requests are disconnected
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// Where the control socket listens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpcAddress {
+    /// Only loopback addresses are accepted
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+/// Command of a request line, the `id` is read separately
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+enum IpcCommand {
+    Seek {
+        frame: Option<u32>,
+        time: Option<IpcSeekTime>,
+    },
+    Output {
+        index: i32,
+    },
+    Reload,
+    Screenshot,
+    State,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum IpcSeekTime {
+    Seconds(f64),
+    /// `[[HH:]MM:]SS[.fraction]`
+    Timecode(String),
+}
+
+/// Answer to a request line, the result fields are flattened in the object
+#[derive(Debug, serde::Serialize)]
+struct IpcResponse {
+    id: Option<Value>,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(flatten)]
+    result: Option<IpcResult>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(untagged)]
+enum IpcResult {
+    Seek { frame: u32 },
+    Output { output: i32 },
+    Screenshot { path: String },
+    State(Box<IpcState>),
+}
+
+#[derive(Debug, serde::Serialize)]
+struct IpcState {
+    frame: u32,
+    displayed_frame: Option<u32>,
+    output: i32,
+    outputs: Vec<i32>,
+    node: IpcNodeInfo,
+    props: Option<Value>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct IpcNodeInfo {
+    num_frames: u32,
+    width: Option<u32>,
+    height: Option<u32>,
+    fps_num: Option<u32>,
+    fps_den: Option<u32>,
+    format: String,
+    alpha: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum IpcEvent {
+    Frame { output: i32, frame: u32 },
+}
+
+/// Line of a client, answered with a single line
+pub struct IpcRequest {
+    pub line: String,
+    reply: oneshot::Sender<String>,
+}
+
+/// Local control socket speaking line-delimited JSON.
+/// Requests are handled by the previewer on each update, events are sent to every client.
+pub struct IpcServer {
+    address: IpcAddress,
+    requests: mpsc::UnboundedReceiver<IpcRequest>,
+    events: broadcast::Sender<String>,
+
+    /// Last output and frame notified
+    last_frame: Option<(i32, u32)>,
+}
+
+impl IpcServer {
+    /// Binds the socket and starts accepting clients on the runtime
+    pub fn new(address: IpcAddress, ctx: Context) -> Result<Self> {
+        let (request_sender, requests) = mpsc::unbounded_channel();
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+
+        match &address {
+            IpcAddress::Tcp(addr) => {
+                let listener = std::net::TcpListener::bind(addr)?;
+                listener.set_nonblocking(true)?;
+
+                let listener = tokio::net::TcpListener::from_std(listener)?;
+                let incoming = tokio_stream::wrappers::TcpListenerStream::new(listener);
+
+                tokio::spawn(accept_clients(
+                    incoming,
+                    request_sender,
+                    events.clone(),
+                    ctx,
+                ));
+            }
+            #[cfg(unix)]
+            IpcAddress::Unix(path) => {
+                use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+                use std::os::unix::net::{UnixListener, UnixStream};
+
+                if std::fs::metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+                    if UnixStream::connect(path).is_ok() {
+                        bail!(
+                            "IPC address in use, another instance listens on {}",
+                            path.display()
+                        );
+                    }
+
+                    // Left over by a previous instance that didn't exit cleanly
+                    std::fs::remove_file(path)?;
+                }
+
+                let listener = UnixListener::bind(path)?;
+                // Only the owner can connect
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+                listener.set_nonblocking(true)?;
+
+                let listener = tokio::net::UnixListener::from_std(listener)?;
+                let incoming = tokio_stream::wrappers::UnixListenerStream::new(listener);
+
+                tokio::spawn(accept_clients(
+                    incoming,
+                    request_sender,
+                    events.clone(),
+                    ctx,
+                ));
+            }
+            #[cfg(not(unix))]
+            IpcAddress::Unix(_) => bail!("Unix sockets are not supported on this platform"),
+        }
+
+        Ok(Self {
+            address,
+            requests,
+            events,
+            last_frame: None,
+        })
+    }
+
+    /// Requests received since the last update
+    pub fn take_requests(&mut self) -> Vec<IpcRequest> {
+        std::iter::from_fn(|| self.requests.try_recv().ok()).collect()
+    }
+
+    /// Sends a frame event if the displayed frame changed
+    pub fn notify_frame(&mut self, output: i32, frame_no: u32) {
+        if self.last_frame == Some((output, frame_no)) {
+            return;
+        }
+        self.last_frame = Some((output, frame_no));
+
+        let event = IpcEvent::Frame {
+            output,
+            frame: frame_no,
+        };
+
+        // No clients connected
+        if let Ok(event) = serde_json::to_string(&event) {
+            self.events.send(event).ok();
+        }
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        if let IpcAddress::Unix(path) = &self.address {
+            std::fs::remove_file(path).ok();
+        }
+    }
+}
+
+impl IpcRequest {
+    /// Replies with the result, echoing the `id` of the command if any
+    fn reply(self, id: Option<Value>, res: Result<Option<IpcResult>>) {
+        let response = IpcResponse::new(id, res);
+
+        // Client disconnected
+        self.reply.send(response.to_line()).ok();
+    }
+}
+
+impl IpcResponse {
+    fn new(id: Option<Value>, res: Result<Option<IpcResult>>) -> Self {
+        match res {
+            Ok(result) => Self {
+                id,
+                ok: true,
+                error: None,
+                result,
+            },
+            Err(e) => Self {
+                id,
+                ok: false,
+                error: Some(e.to_string()),
+                result: None,
+            },
+        }
+    }
+
+    fn to_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|e| {
+            let error = IpcResponse::new(self.id.clone(), Err(e.into()));
+            serde_json::to_string(&error).unwrap_or_default()
+        })
+    }
+}
+
+impl FromStr for IpcAddress {
+    type Err = anyhow::Error;
+
+    /// A port or `host:port` is TCP, anything else a Unix socket path
+    fn from_str(s: &str) -> Result<Self> {
+        let addr = if let Ok(port) = s.parse::<u16>() {
+            Some(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+        } else if let Ok(addr) = s.parse::<SocketAddr>() {
+            Some(addr)
+        } else {
+            match s.rsplit_once(':') {
+                Some((host, port)) if !host.contains(['/', '\\']) => {
+                    let port = port.parse::<u16>().ok();
+                    match port {
+                        Some(port) if host == "localhost" => {
+                            Some(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+                        }
+                        Some(_) => bail!("The IPC socket can only listen on localhost, got {s}"),
+                        None => None,
+                    }
+                }
+                _ => None,
+            }
+        };
+
+        match addr {
+            Some(addr) if !addr.ip().is_loopback() => {
+                bail!("The IPC socket can only listen on localhost, got {addr}")
+            }
+            Some(addr) => Ok(Self::Tcp(addr)),
+            None => Ok(Self::Unix(PathBuf::from(s))),
+        }
+    }
+}
+
+async fn accept_clients<S, E>(
+    mut incoming: S,
+    requests: mpsc::UnboundedSender<IpcRequest>,
+    events: broadcast::Sender<String>,
+    ctx: Context,
+) where
+    S: tokio_stream::Stream<Item = std::io::Result<E>> + Unpin,
+    E: AsyncRead + AsyncWrite + Send + 'static,
+{
+    while let Some(stream) = incoming.next().await {
+        if let Ok(stream) = stream {
+            let client = handle_client(stream, requests.clone(), events.subscribe(), ctx.clone());
+            tokio::spawn(client);
+        }
+    }
+}
+
+/// Answers the requests of the client in order, and forwards the events in between
+async fn handle_client<E: AsyncRead + AsyncWrite>(
+    stream: E,
+    requests: mpsc::UnboundedSender<IpcRequest>,
+    mut events: broadcast::Receiver<String>,
+    ctx: Context,
+) {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+
+    loop {
+        let out = tokio::select! {
+            line = next_line(&mut reader, &mut buf) => {
+                let line = match line {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break,
+                    Err(e) => {
+                        let error = IpcResponse::new(None, Err(e)).to_line();
+                        writer.write_all(format!("{error}\n").as_bytes()).await.ok();
+                        break;
+                    }
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let (reply, response) = oneshot::channel();
+                if requests.send(IpcRequest { line, reply }).is_err() {
+                    break;
+                }
+
+                // Handled on the next update
+                ctx.request_repaint();
+
+                match response.await {
+                    Ok(response) => response,
+                    Err(_) => break,
+                }
+            }
+            event = events.recv() => match event {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+
+        let written = writer.write_all(out.as_bytes()).await;
+        if written.is_err() || writer.write_all(b"\n").await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Reads a line of at most `MAX_LINE_LEN` bytes, `None` at the end of the stream.
+/// Cancel safe, the partially read line is kept in `buf`.
+async fn next_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+) -> Result<Option<String>> {
+    let limit = (MAX_LINE_LEN + 1).saturating_sub(buf.len()) as u64;
+    (&mut *reader).take(limit).read_until(b'\n', buf).await?;
+
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+        if buf.last() == Some(&b'\r') {
+            buf.pop();
+        }
+    } else if buf.len() > MAX_LINE_LEN {
+        bail!("Requests are limited to {MAX_LINE_LEN} bytes");
+    } else if buf.is_empty() {
+        return Ok(None);
+    }
+
+    let line = String::from_utf8(std::mem::take(buf))?;
+    Ok(Some(line))
+}
+
+impl VSPreviewer {
+    /// Answers the IPC requests, then notifies the displayed frame
+    pub fn check_ipc_requests(&mut self, ctx: &Context) {
+        let Some(server) = self.ipc_server.as_mut() else {
+            return;
+        };
+
+        for request in server.take_requests() {
+            let (id, res) = match serde_json::from_str::<Value>(&request.line) {
+                Ok(value) => {
+                    let id = value.get("id").cloned();
+                    let res = IpcCommand::deserialize(value)
+                        .map_err(|e| anyhow!("Invalid command: {e}"))
+                        .and_then(|command| self.ipc_command(ctx, command));
+
+                    (id, res)
+                }
+                Err(e) => (None, Err(anyhow!("Invalid JSON: {e}"))),
+            };
+
+            request.reply(id, res);
+        }
+
+        let displayed_frame = self
+            .outputs
+            .get(&self.state.cur_output)
+            .and_then(|output| output.rendered_frame.as_ref())
+            .map(|pf| pf.read().frame_no);
+
+        if let (Some(server), Some(frame_no)) = (self.ipc_server.as_mut(), displayed_frame) {
+            server.notify_frame(self.state.cur_output, frame_no);
+        }
+    }
+
+    fn ipc_command(&mut self, ctx: &Context, command: IpcCommand) -> Result<Option<IpcResult>> {
+        if self.outputs.is_empty() && !matches!(command, IpcCommand::Reload) {
+            bail!("The script has no outputs loaded");
+        }
+
+        match command {
+            IpcCommand::Seek { frame, time } => {
+                let output = self
+                    .outputs
+                    .get_mut(&self.state.cur_output)
+                    .ok_or_else(|| anyhow!("seek: Invalid current output key"))?;
+                let frame_no = ipc_seek_target(&output.vsoutput.node_info, frame, time)?;
+
+                if frame_no != self.state.cur_frame_no {
+                    output.last_frame_no = self.state.cur_frame_no;
+                    self.state.cur_frame_no = frame_no;
+
+                    self.rerender = true;
+                }
+
+                Ok(Some(IpcResult::Seek { frame: frame_no }))
+            }
+            IpcCommand::Output { index } => {
+                if !self.outputs.contains_key(&index) {
+                    bail!("Output {index} does not exist");
+                }
+
+                let old_output = self.state.cur_output;
+                if index != old_output {
+                    self.state.cur_output = index;
+                    self.rerender |= self.output_needs_rerender(old_output)?;
+                }
+
+                Ok(Some(IpcResult::Output { output: index }))
+            }
+            IpcCommand::Reload => {
+                self.reload(ctx.clone());
+
+                Ok(None)
+            }
+            IpcCommand::Screenshot => {
+                let path = self.save_screenshot()?;
+
+                Ok(Some(IpcResult::Screenshot {
+                    path: path.to_string_lossy().to_string(),
+                }))
+            }
+            IpcCommand::State => Ok(Some(IpcResult::State(Box::new(self.ipc_state()?)))),
+        }
+    }
+
+    /// Current frame and output, with the node info and props of the displayed frame
+    fn ipc_state(&self) -> Result<IpcState> {
+        let output = self
+            .outputs
+            .get(&self.state.cur_output)
+            .ok_or_else(|| anyhow!("state: Invalid current output key"))?;
+        let node = &output.vsoutput.node_info;
+
+        let mut outputs: Vec<i32> = self.outputs.keys().copied().collect();
+        outputs.sort_unstable();
+
+        let resolution = node.resolution;
+        let node_info = IpcNodeInfo {
+            num_frames: node.num_frames,
+            width: resolution.map(|r| r.width),
+            height: resolution.map(|r| r.height),
+            fps_num: node.framerate.map(|fr| fr.fr_num),
+            fps_den: node.framerate.map(|fr| fr.fr_denom),
+            format: node.format_name.clone(),
+            alpha: output.vsoutput.has_alpha,
+        };
+
+        // The props map is from the source clip, before the RGB conversion
+        let displayed = output.rendered_frame.as_ref().map(|pf| {
+            let pf = pf.read();
+            (pf.frame_no, props_json(&pf.vsframe.props_map))
+        });
+        let (displayed_frame, props) = displayed.unzip();
+
+        Ok(IpcState {
+            frame: self.state.cur_frame_no,
+            displayed_frame,
+            output: self.state.cur_output,
+            outputs,
+            node: node_info,
+            props,
+        })
+    }
+}
+
+/// Frame number from `frame`, or `time` as seconds or a timecode string
+fn ipc_seek_target(node: &VSNode, frame: Option<u32>, time: Option<IpcSeekTime>) -> Result<u32> {
+    let frame_no = if let Some(frame) = frame {
+        frame
+    } else {
+        let nanos = match time {
+            Some(IpcSeekTime::Seconds(secs)) if secs >= 0.0 => (secs * 1e9).round() as u128,
+            Some(IpcSeekTime::Timecode(time)) => {
+                parse_timestamp(&time).ok_or_else(|| anyhow!("Invalid timecode `{time}`"))?
+            }
+            _ => bail!("`seek` requires a `frame` or a `time`"),
+        };
+
+        node.framerate
+            .ok_or_else(|| anyhow!("Seeking by time requires a constant framerate"))?
+            .nanos_to_frame(nanos)
+    };
+
+    if frame_no >= node.num_frames {
+        bail!(
+            "Frame {frame_no} is out of range ({} frames)",
+            node.num_frames
+        );
+    }
+
+    Ok(frame_no)
+}
+
+/// Every prop of the frame, arrays of a single value are unwrapped.
+/// Binary data, nodes, frames and functions are described as strings.
+fn props_json(props: &VSPropsMap) -> Value {
+    let map = props
+        .props
+        .iter()
+        .map(|prop| {
+            let values: Vec<Value> = match &prop.value {
+                VSPropValue::Int(v) => v.iter().map(|v| Value::from(*v)).collect(),
+                VSPropValue::Float(v) => v.iter().map(|v| Value::from(*v)).collect(),
+                VSPropValue::Data(v) => v
+                    .iter()
+                    .map(|data| match data {
+                        VSPropData::Utf8(s) => Value::from(s.as_str()),
+                        VSPropData::Binary { .. } => Value::from(data.to_string()),
+                    })
+                    .collect(),
+                value => value.items().into_iter().map(Value::from).collect(),
+            };
+
+            let value = match <[Value; 1]>::try_from(values) {
+                Ok([value]) => value,
+                Err(values) => Value::Array(values),
+            };
+
+            (prop.key.clone(), value)
+        })
+        .collect();
+
+    Value::Object(map)
+}
@@ -0,0 +1,370 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Result, bail};
+use eframe::egui;
+use parking_lot::Mutex;
+use poll_promise::Promise;
+
+use super::PreviewerResponse;
+use crate::vs_handler::{PreviewedScript, VSPlane, VSPlanes};
+
+/// Frames between repaints while computing
+const REPAINT_INTERVAL: usize = 5;
+
+/// Gaussian window of the reference SSIM implementation, 11x11 with a sigma of 1.5
+const SSIM_RADIUS: usize = 5;
+const SSIM_SIGMA: f32 = 1.5;
+/// Stabilizing constants, for samples in `[0, 1]`
+const SSIM_C1: f32 = 0.01 * 0.01;
+const SSIM_C2: f32 = 0.03 * 0.03;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    /// Of the plane index
+    Psnr(usize),
+    Ssim,
+}
+
+/// Metrics of the distorted frame against the reference
+#[derive(Debug, Clone)]
+pub struct FrameMetrics {
+    pub frame_no: u32,
+    /// Per plane, in dB, infinite for identical planes
+    pub psnr: Vec<f64>,
+    /// Of the luma, or averaged over the RGB planes
+    pub ssim: f64,
+}
+
+/// Metrics over a frame range, filled while computing
+#[derive(Debug, Clone, Default)]
+pub struct MetricsData {
+    pub reference: i32,
+    pub distorted: i32,
+    pub start: u32,
+    /// Inclusive
+    pub end: u32,
+
+    /// From the first frame
+    pub plane_names: Vec<&'static str>,
+    pub frames: Vec<FrameMetrics>,
+}
+
+pub struct FetchMetricsState {
+    pub data: Arc<Mutex<MetricsData>>,
+    pub cancelled: Arc<AtomicBool>,
+}
+
+/// Computation running on the worker
+pub struct MetricsScan {
+    pub cancelled: Arc<AtomicBool>,
+    pub promise: Promise<PreviewerResponse>,
+}
+
+pub struct Metrics {
+    pub show: bool,
+
+    /// Settings of the next computation
+    pub reference: i32,
+    pub distorted: i32,
+    pub start: u32,
+    pub end: u32,
+
+    /// Metric of the graph and worst frames
+    pub metric: MetricKind,
+    pub worst_count: usize,
+
+    /// Last computed metrics, kept when cancelled
+    pub data: Option<Arc<Mutex<MetricsData>>>,
+    pub scan: Option<MetricsScan>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            show: false,
+            reference: 0,
+            distorted: 1,
+            start: 0,
+            end: u32::MAX,
+            metric: MetricKind::Psnr(0),
+            worst_count: 10,
+            data: None,
+            scan: None,
+        }
+    }
+}
+
+impl Metrics {
+    pub fn scanning(&self) -> bool {
+        self.scan.is_some()
+    }
+
+    /// The computation stops after the frames already requested
+    pub fn cancel(&self) {
+        if let Some(scan) = &self.scan {
+            scan.cancelled.store(true, Ordering::Release);
+        }
+    }
+
+    /// Computed on the worker, the frames are added as they are rendered
+    pub fn compute(
+        script: &Mutex<PreviewedScript>,
+        state: &FetchMetricsState,
+        ctx: &egui::Context,
+    ) -> Result<()> {
+        let (reference, distorted, start, end) = {
+            let data = state.data.lock();
+            (data.reference, data.distorted, data.start, data.end)
+        };
+
+        let mut res = Ok(());
+
+        PreviewedScript::scan_frames(
+            script,
+            start..=end,
+            &state.cancelled,
+            |script, frame_no| script.request_source_planes_pair(reference, distorted, frame_no),
+            |frame_no, (ref_planes, dist_planes)| {
+                if res.is_err() {
+                    return;
+                }
+
+                let metrics = match FrameMetrics::compute(frame_no, &ref_planes, &dist_planes) {
+                    Ok(metrics) => metrics,
+                    Err(e) => {
+                        // Following frames are not computed
+                        state.cancelled.store(true, Ordering::Release);
+                        res = Err(e);
+                        return;
+                    }
+                };
+
+                let mut data = state.data.lock();
+                if data.plane_names.is_empty() {
+                    data.plane_names = ref_planes.planes.iter().map(|p| p.name).collect();
+                }
+                data.frames.push(metrics);
+
+                if data.frames.len().is_multiple_of(REPAINT_INTERVAL) {
+                    ctx.request_repaint();
+                }
+            },
+        )?;
+
+        res
+    }
+}
+
+impl MetricsData {
+    pub fn progress(&self) -> f32 {
+        self.frames.len() as f32 / (self.end.saturating_sub(self.start) + 1) as f32
+    }
+
+    pub fn metric_name(&self, kind: MetricKind) -> String {
+        match kind {
+            MetricKind::Psnr(plane) => {
+                let name = self.plane_names.get(plane).copied().unwrap_or("?");
+                format!("PSNR {name}")
+            }
+            MetricKind::Ssim => "SSIM".to_string(),
+        }
+    }
+
+    pub fn kinds(&self) -> Vec<MetricKind> {
+        (0..self.plane_names.len())
+            .map(MetricKind::Psnr)
+            .chain([MetricKind::Ssim])
+            .collect()
+    }
+
+    pub fn values(&self, kind: MetricKind) -> Vec<(u32, Option<f64>)> {
+        self.frames
+            .iter()
+            .map(|frame| (frame.frame_no, frame.get(kind)))
+            .collect()
+    }
+
+    /// Averaged over the frames, infinite PSNR values are skipped
+    pub fn mean(&self, kind: MetricKind) -> Option<f64> {
+        let (count, sum) = self
+            .frames
+            .iter()
+            .filter_map(|frame| frame.get(kind))
+            .filter(|v| v.is_finite())
+            .fold((0, 0.0), |(c, s), v| (c + 1, s + v));
+
+        (count > 0).then(|| sum / count as f64)
+    }
+
+    /// Lowest values first, for both PSNR and SSIM
+    pub fn worst(&self, kind: MetricKind, count: usize) -> Vec<&FrameMetrics> {
+        let mut frames: Vec<&FrameMetrics> = self
+            .frames
+            .iter()
+            .filter(|frame| frame.get(kind).is_some())
+            .collect();
+        frames.sort_by(|a, b| a.get(kind).unwrap().total_cmp(&b.get(kind).unwrap()));
+        frames.truncate(count);
+
+        frames
+    }
+}
+
+impl FrameMetrics {
+    /// Samples normalized from different bit depths don't compare, e.g. 235/255 and 940/1023
+    pub fn compute(frame_no: u32, reference: &VSPlanes, distorted: &VSPlanes) -> Result<Self> {
+        let same_format = reference.color_family == distorted.color_family
+            && reference.sample_type == distorted.sample_type
+            && reference.bits_per_sample == distorted.bits_per_sample;
+        if !same_format {
+            bail!(
+                "Cannot compare {} with {}, the color families and bit depths must match",
+                reference.format_name,
+                distorted.format_name
+            );
+        }
+
+        if reference.planes.len() != distorted.planes.len() {
+            bail!(
+                "Cannot compare {} with {}, the plane counts differ",
+                reference.format_name,
+                distorted.format_name
+            );
+        }
+
+        for (a, b) in reference.planes.iter().zip(&distorted.planes) {
+            if (a.width, a.height) != (b.width, b.height) {
+                bail!(
+                    "Cannot compare the {} planes of frame {frame_no}, {}x{} and {}x{}",
+                    a.name,
+                    a.width,
+                    a.height,
+                    b.width,
+                    b.height
+                );
+            }
+        }
+
+        let planes = reference.planes.iter().zip(&distorted.planes);
+        let psnr = planes.clone().map(|(a, b)| psnr(a, b)).collect();
+
+        let ssim = if reference.is_rgb() {
+            planes.map(|(a, b)| ssim(a, b)).sum::<f64>() / reference.planes.len() as f64
+        } else {
+            ssim(&reference.planes[0], &distorted.planes[0])
+        };
+
+        Ok(Self {
+            frame_no,
+            psnr,
+            ssim,
+        })
+    }
+
+    pub fn get(&self, kind: MetricKind) -> Option<f64> {
+        match kind {
+            MetricKind::Psnr(plane) => self.psnr.get(plane).copied(),
+            MetricKind::Ssim => Some(self.ssim),
+        }
+    }
+}
+
+/// Peak of 1.0, the samples are normalized
+fn psnr(a: &VSPlane, b: &VSPlane) -> f64 {
+    let sum: f64 = a
+        .data
+        .iter()
+        .zip(&b.data)
+        .map(|(x, y)| {
+            let diff = (x - y) as f64;
+            diff * diff
+        })
+        .sum();
+    let mse = sum / a.data.len().max(1) as f64;
+
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        -10.0 * mse.log10()
+    }
+}
+
+/// Mean SSIM over the Gaussian windows
+fn ssim(a: &VSPlane, b: &VSPlane) -> f64 {
+    let (w, h) = (a.width, a.height);
+    let kernel = gaussian_kernel();
+
+    let products = |f: fn(f32, f32) -> f32| -> Vec<f32> {
+        a.data.iter().zip(&b.data).map(|(x, y)| f(*x, *y)).collect()
+    };
+
+    let mu_a = gaussian_blur(&a.data, w, h, &kernel);
+    let mu_b = gaussian_blur(&b.data, w, h, &kernel);
+    let aa = gaussian_blur(&products(|x, _| x * x), w, h, &kernel);
+    let bb = gaussian_blur(&products(|_, y| y * y), w, h, &kernel);
+    let ab = gaussian_blur(&products(|x, y| x * y), w, h, &kernel);
+
+    let sum: f64 = (0..w * h)
+        .map(|i| {
+            let (ma, mb) = (mu_a[i], mu_b[i]);
+            let var_a = aa[i] - ma * ma;
+            let var_b = bb[i] - mb * mb;
+            let cov = ab[i] - ma * mb;
+
+            let num = (2.0 * ma * mb + SSIM_C1) * (2.0 * cov + SSIM_C2);
+            let den = (ma * ma + mb * mb + SSIM_C1) * (var_a + var_b + SSIM_C2);
+
+            (num / den) as f64
+        })
+        .sum();
+
+    sum / (w * h).max(1) as f64
+}
+
+fn gaussian_kernel() -> [f32; 2 * SSIM_RADIUS + 1] {
+    let mut kernel = [0.0; 2 * SSIM_RADIUS + 1];
+
+    for (i, k) in kernel.iter_mut().enumerate() {
+        let x = i as f32 - SSIM_RADIUS as f32;
+        *k = (-(x * x) / (2.0 * SSIM_SIGMA * SSIM_SIGMA)).exp();
+    }
+
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|k| *k /= sum);
+
+    kernel
+}
+
+/// Separable blur, edges are clamped
+fn gaussian_blur(data: &[f32], w: usize, h: usize, kernel: &[f32]) -> Vec<f32> {
+    let radius = kernel.len() / 2;
+    let mut horizontal = vec![0.0; w * h];
+
+    for y in 0..h {
+        let row = &data[y * w..(y + 1) * w];
+
+        for x in 0..w {
+            horizontal[y * w + x] = kernel
+                .iter()
+                .enumerate()
+                .map(|(i, k)| k * row[(x + i).saturating_sub(radius).min(w - 1)])
+                .sum();
+        }
+    }
+
+    let mut out = vec![0.0; w * h];
+
+    for y in 0..h {
+        for (i, k) in kernel.iter().enumerate() {
+            let src_y = (y + i).saturating_sub(radius).min(h - 1);
+            let src = &horizontal[src_y * w..(src_y + 1) * w];
+
+            for (o, s) in out[y * w..(y + 1) * w].iter_mut().zip(src) {
+                *o += k * s;
+            }
+        }
+    }
+
+    out
+}
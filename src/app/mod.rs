@@ -1,32 +1,73 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Instant,
+};
 
 use eframe::{
-    egui::{self, Context},
+    egui::{self, Context, Rect},
     epaint::Vec2,
 };
 use image::DynamicImage;
 use parking_lot::{Mutex, RwLock};
 use poll_promise::{Promise, Sender};
 
+mod bookmarks;
+mod color_overrides;
 mod eframe_app;
+mod ipc_server;
+mod metrics;
+mod pixel_inspector;
+mod playback;
+mod preview_alpha;
+mod preview_compare;
 mod preview_filter_type;
+mod prop_plot;
+mod prop_search;
+mod region_selection;
+mod scopes;
+mod script_watcher;
 mod transforms;
 mod ui;
 mod vs_previewer;
 
 use ui::*;
 
+use bookmarks::BookmarkFormat;
+pub use bookmarks::Bookmarks;
+use color_overrides::SavedColorOverrides;
+pub use ipc_server::IpcAddress;
+use ipc_server::IpcServer;
+pub use metrics::Metrics;
+use metrics::{FetchMetricsState, MetricKind, MetricsData, MetricsScan};
+use pixel_inspector::{InspectedPixel, PixelInspector};
+use playback::{PLAYBACK_SPEEDS, Playback};
+pub use preview_alpha::{CHECKERBOARD_SIZE, PreviewAlphaBackground};
+use preview_compare::{PreviewCompareMode, difference_frame};
 use preview_filter_type::{PreviewFilterType, PreviewTextureFilterType};
+pub use prop_plot::PropPlot;
+use prop_plot::{FetchPropPlotState, PropPlotData, PropPlotFormat, PropPlotScan};
+pub use prop_search::PropSearch;
+use prop_search::{FetchPropSearchState, PropExpr, PropSearchData, PropSearchScan};
+use region_selection::{REGION_ALIGNMENTS, RegionCrop, RegionSelection};
+pub use scopes::Scopes;
+use scopes::{FetchScopesState, ScopeImages, ScopesKey, ScopesOptions};
+use script_watcher::ScriptWatcher;
 pub use vs_previewer::VSPreviewer;
 
-use super::vs_handler::{VSFrame, VSFrameProps, VSOutput, vstransform};
-use vstransform::VSTransformOptions;
+use super::vs_handler::{VSFrame, VSFrameProps, VSOutput, vsnode::VSFramerate, vstransform};
+use vstransform::{VSColorOverrides, VSPlaneView, VSTransformOptions};
 
 use crate::utils::{
-    dimensions_for_window, resize_fast, translate_norm_coeffs, update_input_key_state,
+    composite_alpha, dimensions_for_window, image_size, resize_fast, translate_norm_coeffs,
+    update_input_key_state,
 };
 
 pub use transforms::icc::IccProfile;
+pub use transforms::tonemap::TonemapOptions;
 
 pub const MIN_ZOOM: f32 = 0.125;
 pub const MAX_ZOOM: f32 = 64.0;
@@ -35,12 +76,24 @@ type VSPreviewFrame = Arc<RwLock<PreviewFrame>>;
 type FrameResponse = Option<VSPreviewFrame>;
 type PropsResponse = Option<VSFrameProps>;
 type ReloadResponse = Option<HashMap<i32, VSOutput>>;
+/// Imported frames, `None` when exported or cancelled
+type BookmarksResponse = anyhow::Result<Option<BTreeSet<u32>>>;
+type ScopesResponse = Option<ScopeImages>;
+/// Scan or export finished
+type PropPlotResponse = anyhow::Result<()>;
+type PropSearchResponse = anyhow::Result<()>;
+type MetricsResponse = anyhow::Result<()>;
 
 pub enum PreviewerResponse {
     Reload(ReloadResponse),
     Frame(FrameResponse),
     Props(PropsResponse),
     Misc(ReloadType),
+    Bookmarks(BookmarksResponse),
+    Scopes(ScopesResponse),
+    PropPlot(PropPlotResponse),
+    PropSearch(PropSearchResponse),
+    Metrics(MetricsResponse),
     Close,
 }
 
@@ -80,6 +133,39 @@ pub struct PreviewState {
     pub canvas_margin: f32,
 
     pub icc_enabled: bool,
+    /// Separate from the ICC transform
+    pub tonemap: TonemapOptions,
+
+    /// Background for outputs with an alpha clip
+    pub alpha_background: PreviewAlphaBackground,
+    pub alpha_color: [u8; 3],
+    /// Display the alpha plane instead of the composited image
+    pub alpha_mask_only: bool,
+
+    /// Reload the script when it or its local imports change on disk
+    pub auto_reload: bool,
+    /// Memory limit of the rendered frames cache, in MiB
+    pub frame_cache_size: u32,
+
+    /// Compares the current output with `compare_output`
+    pub compare_mode: PreviewCompareMode,
+    pub compare_output: i32,
+    /// Normalized position of the wipe split
+    pub compare_wipe_pos: f32,
+
+    /// Multiplies the difference between outputs
+    pub diff_amplification: f32,
+    /// Highlight the pixels differing by more than `diff_threshold`
+    pub diff_highlight: bool,
+    pub diff_threshold: f32,
+
+    /// Last format bookmarks were exported to
+    pub bookmark_format: BookmarkFormat,
+
+    /// Shows the values of the hovered pixel
+    pub pixel_inspector: bool,
+
+    pub scopes: ScopesOptions,
 }
 
 #[derive(Default)]
@@ -87,31 +173,54 @@ pub struct PreviewOutput {
     pub vsoutput: VSOutput,
 
     pub rendered_frame: Option<VSPreviewFrame>,
+    /// Frame being rendered, several outputs can be in flight
+    pub frame_request: Option<FrameRequest>,
+    /// Stale render still running in VS, its result is dropped
+    pub superseded_request: Option<FrameRequest>,
     pub original_props: Option<VSFrameProps>,
 
     pub force_reprocess: bool,
     pub last_frame_no: u32,
+
+    /// Kept across reloads, persisted per script
+    pub color_overrides: VSColorOverrides,
 }
 
 pub struct PreviewFrame {
-    pub vsframe: VSFrame,
+    /// Shared with the frame cache
+    pub vsframe: Arc<VSFrame>,
+    pub frame_no: u32,
 
     /// Can't be moved out of `VSFrame` without a copy
     /// As an Option, we can check which image to use
     pub processed_image: Option<DynamicImage>,
+    /// Region of `vsframe.image` painted by the texture, in its pixels
+    pub source_rect: Rect,
     pub texture: Mutex<Option<egui::TextureHandle>>,
 }
 
+/// Frame requested from the worker for an output
+pub struct FrameRequest {
+    pub promise: Promise<PreviewerResponse>,
+    pub frame_no: u32,
+    /// Only reprocessing the rendered frame
+    pub reprocess: bool,
+    pub requested_at: Instant,
+    /// Set when superseded by a request for another frame
+    pub cancelled: Arc<AtomicBool>,
+}
+
 pub struct FetchImageState {
-    frame_mutex: Arc<Mutex<Option<Promise<PreviewerResponse>>>>,
     state: PreviewState,
     pf: Option<VSPreviewFrame>,
     reprocess: bool,
     win_size: Vec2,
+    color_overrides: VSColorOverrides,
+    compare_color_overrides: VSColorOverrides,
+    cancelled: Arc<AtomicBool>,
 }
 
 pub struct FetchPropsState {
-    pub frame_mutex: Arc<Mutex<Option<Promise<PreviewerResponse>>>>,
     pub state: PreviewState,
 }
 
@@ -133,6 +242,13 @@ pub enum VSCommand {
     FrameProps(FetchPropsState),
     ChangeScript,
     ChangeIcc(Arc<Mutex<PreviewTransforms>>),
+    ExportBookmarks(String, BookmarkFormat),
+    ImportBookmarks(Option<VSFramerate>),
+    Scopes(FetchScopesState),
+    PropPlot(FetchPropPlotState),
+    ExportPropPlot(String, PropPlotFormat),
+    PropSearch(FetchPropSearchState),
+    Metrics(FetchMetricsState),
     Reload,
     Exit,
 }
@@ -143,6 +259,104 @@ pub struct VSCommandMsg {
     pub egui_ctx: Context,
 }
 
+impl PreviewOutput {
+    /// Frame duration in seconds, from the framerate or the rendered frame for VFR clips
+    pub fn frame_duration_secs(&self) -> Option<f64> {
+        if let Some(fr) = self.vsoutput.node_info.framerate {
+            Some(fr.fr_denom as f64 / fr.fr_num as f64)
+        } else {
+            self.rendered_frame
+                .as_ref()
+                .and_then(|pf| pf.read().vsframe.props.duration_secs())
+        }
+    }
+
+    /// Cancels the in flight render when it's for another frame, so the new one can be requested.
+    /// Only one superseded render is kept running, VS can't abort it.
+    /// Returns whether a request for `frame_no` can be made.
+    pub fn supersede_request(&mut self, frame_no: u32) -> bool {
+        match &self.frame_request {
+            None => true,
+            Some(req)
+                if !req.reprocess
+                    && req.frame_no != frame_no
+                    && self.superseded_request.is_none() =>
+            {
+                req.cancelled.store(true, Ordering::Release);
+                self.superseded_request = self.frame_request.take();
+
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    /// Frame number of the VS render in progress
+    pub fn rendering_frame(&self) -> Option<(u32, Instant)> {
+        self.frame_request
+            .as_ref()
+            .filter(|req| !req.reprocess)
+            .map(|req| (req.frame_no, req.requested_at))
+    }
+
+    /// Presentation time of the frame in nanoseconds.
+    /// Exact for constant framerates, variable framerates need the frame to be rendered.
+    pub fn frame_timestamp(&self, frame_no: u32) -> Option<u128> {
+        if let Some(fr) = self.vsoutput.node_info.framerate {
+            return Some(fr.frame_to_nanos(frame_no));
+        }
+
+        let pf = self.rendered_frame.as_ref()?.read();
+
+        pf.vsframe
+            .props
+            .absolute_time
+            .filter(|_| pf.frame_no == frame_no)
+            .map(|secs| (secs * 1e9).round() as u128)
+    }
+
+    /// Frame one second before or after, exact for constant framerates
+    pub fn seek_one_second(&self, frame_no: u32, forward: bool) -> u32 {
+        let last_frame = self.vsoutput.node_info.num_frames.saturating_sub(1);
+
+        if let Some(fr) = self.vsoutput.node_info.framerate {
+            let time = fr.frame_to_nanos(frame_no);
+            let target = if forward {
+                time + 1_000_000_000
+            } else {
+                time.saturating_sub(1_000_000_000)
+            };
+
+            return fr.nanos_to_frame(target).min(last_frame);
+        }
+
+        // Variable framerate clips use the rendered frame duration
+        let frames = self
+            .rendered_frame
+            .as_ref()
+            .and_then(|pf| pf.read().vsframe.props.duration_secs())
+            .map_or(1, |secs| (1.0 / secs).round().max(1.0) as u32);
+
+        if forward {
+            frame_no.saturating_add(frames).min(last_frame)
+        } else {
+            frame_no.saturating_sub(frames)
+        }
+    }
+
+    /// Clip size, taken from the rendered frame when the resolution is variable
+    pub fn size(&self) -> Vec2 {
+        if let Some(res) = self.vsoutput.node_info.resolution {
+            Vec2::from([res.width as f32, res.height as f32])
+        } else if let Some(pf) = &self.rendered_frame {
+            let image = &pf.read().vsframe.image;
+            Vec2::from([image.width() as f32, image.height() as f32])
+        } else {
+            Vec2::ZERO
+        }
+    }
+}
+
 impl Default for PreviewState {
     fn default() -> Self {
         Self {
@@ -151,6 +365,11 @@ impl Default for PreviewState {
             scroll_multiplier: 1.0,
             canvas_margin: 0.0,
             fit_to_window: true,
+            auto_reload: true,
+            frame_cache_size: 1024,
+            compare_wipe_pos: 0.5,
+            diff_amplification: 10.0,
+            diff_threshold: 1.0,
             show_gui: Default::default(),
             cur_output: Default::default(),
             cur_frame_no: Default::default(),
@@ -161,7 +380,17 @@ impl Default for PreviewState {
             upscale_to_window: Default::default(),
             upsampling_filter: Default::default(),
             icc_enabled: Default::default(),
+            tonemap: Default::default(),
+            alpha_background: Default::default(),
+            alpha_color: Default::default(),
+            alpha_mask_only: Default::default(),
             texture_filter: Default::default(),
+            compare_mode: Default::default(),
+            compare_output: Default::default(),
+            diff_highlight: Default::default(),
+            bookmark_format: Default::default(),
+            pixel_inspector: Default::default(),
+            scopes: Default::default(),
         }
     }
 }
@@ -0,0 +1,163 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use eframe::egui::{Pos2, Rect};
+use image::DynamicImage;
+use parking_lot::Mutex;
+
+use crate::vs_handler::{PendingFrame, PreviewedScript, VSPixelValues};
+
+/// Pixel of an output frame, in the clip's coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InspectedPixel {
+    pub output: i32,
+    pub frame_no: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Values of the hovered pixel, in the source format and as displayed
+#[derive(Default)]
+pub struct PixelInspector {
+    pub pixel: Option<InspectedPixel>,
+    /// Displayed RGB, before the ICC transform
+    pub displayed_rgb: Option<String>,
+
+    /// Last pixel read from the clip, even if it failed
+    read_pixel: Option<InspectedPixel>,
+    values: Option<VSPixelValues>,
+    request: Option<(InspectedPixel, PendingFrame<VSPixelValues>)>,
+}
+
+impl PixelInspector {
+    /// Maps the pointer from the painted `image_rect` to the frame image.
+    /// `source_rect` is the region of the frame image painted.
+    pub fn source_pixel(
+        pos: Pos2,
+        image_rect: Rect,
+        source_rect: Rect,
+        image: &DynamicImage,
+    ) -> Option<(u32, u32)> {
+        let rel = (pos - image_rect.min) / image_rect.size();
+        let src = source_rect.min + rel * source_rect.size();
+
+        let in_bounds = src.x >= 0.0
+            && src.y >= 0.0
+            && src.x < image.width() as f32
+            && src.y < image.height() as f32;
+
+        in_bounds.then_some((src.x as u32, src.y as u32))
+    }
+
+    /// `image_pos` is the pixel in the frame image, which can differ from the clip
+    pub fn hover(&mut self, pixel: InspectedPixel, image: &DynamicImage, image_pos: (u32, u32)) {
+        if self.pixel != Some(pixel) {
+            self.pixel = Some(pixel);
+            self.displayed_rgb = displayed_rgb(image, image_pos.0, image_pos.1);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pixel = None;
+        self.displayed_rgb = None;
+    }
+
+    /// Native values of the hovered pixel, if already read
+    pub fn hovered_values(&self) -> Option<&VSPixelValues> {
+        self.values
+            .as_ref()
+            .filter(|_| self.pixel.is_some() && self.read_pixel == self.pixel)
+    }
+
+    /// The hovered pixel hasn't been read yet
+    pub fn reading(&self) -> bool {
+        self.pixel.is_some() && self.read_pixel != self.pixel
+    }
+
+    /// Completes the pending read, then requests the hovered pixel if it changed.
+    /// Only one read is in flight, the script is not waited for.
+    /// Returns whether a read is still pending.
+    pub fn update(&mut self, script: &Mutex<PreviewedScript>) -> Result<bool> {
+        if let Some((pixel, pending)) = &self.request {
+            let Some(res) = pending.try_get() else {
+                return Ok(true);
+            };
+
+            self.read_pixel = Some(*pixel);
+            self.request = None;
+
+            // Not retried until another pixel is hovered
+            match res {
+                Ok(values) => self.values = Some(values),
+                Err(e) => {
+                    self.values = None;
+                    return Err(e);
+                }
+            }
+        }
+
+        let Some(pixel) = self.pixel.filter(|pixel| self.read_pixel != Some(*pixel)) else {
+            return Ok(false);
+        };
+
+        // Busy reloading or requesting frames, retried on the next paint
+        let Some(script) = script.try_lock() else {
+            return Ok(true);
+        };
+
+        let pending = script.request_pixel_values(
+            pixel.output,
+            pixel.frame_no,
+            pixel.x as usize,
+            pixel.y as usize,
+        )?;
+        self.request = Some((pixel, pending));
+
+        Ok(true)
+    }
+
+    /// Single line summary to copy
+    pub fn summary(&self) -> Option<String> {
+        let pixel = self.pixel?;
+        let mut out = format!(
+            "Output {}, frame {}, pixel ({}, {})",
+            pixel.output, pixel.frame_no, pixel.x, pixel.y
+        );
+
+        if let Some(values) = self.hovered_values() {
+            write!(out, ", {}:", values.format_name).ok();
+
+            for plane in &values.planes {
+                write!(out, " {} {}", plane.name, plane.value).ok();
+            }
+
+            if let Some(alpha) = values.alpha {
+                write!(out, " A {alpha}").ok();
+            }
+        }
+
+        if let Some(rgb) = &self.displayed_rgb {
+            write!(out, ", displayed RGB {rgb}").ok();
+        }
+
+        Some(out)
+    }
+}
+
+/// Values of the converted image, in its precision
+fn displayed_rgb(image: &DynamicImage, x: u32, y: u32) -> Option<String> {
+    if x >= image.width() || y >= image.height() {
+        return None;
+    }
+
+    let values = match image {
+        DynamicImage::ImageRgb8(img) => img.get_pixel(x, y).0.map(|v| v.to_string()).to_vec(),
+        DynamicImage::ImageRgb16(img) => img.get_pixel(x, y).0.map(|v| v.to_string()).to_vec(),
+        DynamicImage::ImageRgb32F(img) => img.get_pixel(x, y).0.map(|v| format!("{v:.5}")).to_vec(),
+        DynamicImage::ImageLuma8(img) => vec![img.get_pixel(x, y).0[0].to_string()],
+        DynamicImage::ImageLuma16(img) => vec![img.get_pixel(x, y).0[0].to_string()],
+        _ => return None,
+    };
+
+    Some(values.join(" "))
+}
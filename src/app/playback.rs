@@ -0,0 +1,208 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::vs_handler::{PendingFrame, VSFrame};
+
+/// Speed multipliers to cycle through
+pub const PLAYBACK_SPEEDS: [f32; 7] = [0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 4.0];
+
+/// Maximum number of frames requested ahead of the displayed one
+pub const PREFETCH_FRAMES: usize = 16;
+
+/// Used when the clip has no framerate and the frame no duration
+const FALLBACK_FRAME_DURATION: f64 = 1.0 / 24.0;
+
+pub struct Playback {
+    pub playing: bool,
+    pub reverse: bool,
+    pub speed: f32,
+
+    /// Frames skipped because the script couldn't keep up
+    pub dropped_frames: u32,
+
+    /// Time and frame the timing is relative to, reset on seek or speed change
+    base: Option<(Instant, u32)>,
+    /// Last frame set by playback, to detect seeking
+    last_frame: u32,
+
+    /// Converted frames requested ahead, added to the frame cache once rendered
+    prefetching: Vec<PendingFrame<Arc<VSFrame>>>,
+    /// Furthest frame requested ahead
+    prefetched_until: Option<u32>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            playing: false,
+            reverse: false,
+            speed: 1.0,
+            dropped_frames: 0,
+            base: None,
+            last_frame: 0,
+            prefetching: Vec::new(),
+            prefetched_until: None,
+        }
+    }
+}
+
+impl Playback {
+    pub fn toggle(&mut self, reverse: bool) {
+        if self.playing && self.reverse == reverse {
+            self.stop();
+        } else {
+            self.playing = true;
+            self.reverse = reverse;
+            self.dropped_frames = 0;
+            self.reset_timing();
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.reset_timing();
+    }
+
+    /// Steps to the next or previous speed multiplier
+    pub fn change_speed(&mut self, faster: bool) {
+        let idx = PLAYBACK_SPEEDS
+            .iter()
+            .position(|s| *s == self.speed)
+            .unwrap_or(3);
+
+        let new_idx = if faster {
+            (idx + 1).min(PLAYBACK_SPEEDS.len() - 1)
+        } else {
+            idx.saturating_sub(1)
+        };
+
+        self.set_speed(PLAYBACK_SPEEDS[new_idx]);
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+        self.reset_timing();
+    }
+
+    fn reset_timing(&mut self) {
+        self.base = None;
+        self.prefetched_until = None;
+    }
+
+    /// Returns the frame that should be displayed now, counting the skipped frames as dropped.
+    /// `None` when the current frame should stay, playback stops at the clip bounds.
+    pub fn next_frame(
+        &mut self,
+        cur_frame_no: u32,
+        num_frames: u32,
+        frame_duration: Option<f64>,
+    ) -> Option<u32> {
+        if !self.playing {
+            return None;
+        }
+
+        // Already at the clip bound for the direction, nothing left to play
+        let last_frame_no = num_frames.saturating_sub(1);
+        let at_bound = if self.reverse {
+            cur_frame_no == 0
+        } else {
+            cur_frame_no >= last_frame_no
+        };
+        if at_bound {
+            self.stop();
+            return None;
+        }
+
+        let now = Instant::now();
+
+        // Started or seeked away from the played frame
+        let (base_time, base_frame) = match self.base {
+            Some(base) if self.last_frame == cur_frame_no => base,
+            _ => {
+                self.base = Some((now, cur_frame_no));
+                self.last_frame = cur_frame_no;
+                self.prefetched_until = None;
+
+                return None;
+            }
+        };
+
+        let duration = frame_duration.unwrap_or(FALLBACK_FRAME_DURATION) / self.speed as f64;
+        let elapsed = (now - base_time).as_secs_f64();
+        let offset = (elapsed / duration) as u32;
+
+        let target = if self.reverse {
+            base_frame.saturating_sub(offset)
+        } else {
+            base_frame.saturating_add(offset).min(last_frame_no)
+        };
+
+        if target == cur_frame_no {
+            return None;
+        }
+
+        self.dropped_frames += target.abs_diff(cur_frame_no) - 1;
+        self.last_frame = target;
+
+        // Reached the end
+        if target == 0 || target == last_frame_no {
+            self.playing = false;
+        }
+
+        Some(target)
+    }
+
+    /// Time until the next frame is due
+    pub fn time_to_next_frame(&self, frame_duration: Option<f64>) -> Duration {
+        let duration = frame_duration.unwrap_or(FALLBACK_FRAME_DURATION) / self.speed as f64;
+
+        let remaining = self.base.map_or(duration, |(base_time, _)| {
+            duration - base_time.elapsed().as_secs_f64().rem_euclid(duration)
+        });
+
+        Duration::from_secs_f64(remaining)
+    }
+
+    /// Frames to request ahead of the current frame, within the prefetch bounds
+    pub fn frames_to_prefetch(&mut self, cur_frame_no: u32, num_frames: u32) -> Vec<u32> {
+        // Errors are reported when the frame is displayed
+        self.prefetching
+            .retain(|pending| pending.try_get().is_none());
+        let available = PREFETCH_FRAMES.saturating_sub(self.prefetching.len()) as u32;
+
+        if !self.playing || available == 0 {
+            return Vec::new();
+        }
+
+        let limit = PREFETCH_FRAMES as u32;
+        let frames: Vec<u32> = if self.reverse {
+            let start = match self.prefetched_until.filter(|n| *n < cur_frame_no) {
+                Some(0) => return Vec::new(),
+                Some(n) => n - 1,
+                None if cur_frame_no == 0 => return Vec::new(),
+                None => cur_frame_no - 1,
+            };
+            let end = cur_frame_no.saturating_sub(limit);
+
+            (end..=start).rev().take(available as usize).collect()
+        } else {
+            let start = self
+                .prefetched_until
+                .filter(|n| *n > cur_frame_no)
+                .map_or(cur_frame_no + 1, |n| n + 1);
+            let end = (cur_frame_no + limit).min(num_frames.saturating_sub(1));
+
+            (start..=end).take(available as usize).collect()
+        };
+
+        if let Some(last) = frames.last() {
+            self.prefetched_until = Some(*last);
+        }
+
+        frames
+    }
+
+    pub fn add_prefetched(&mut self, pending: Vec<PendingFrame<Arc<VSFrame>>>) {
+        self.prefetching.extend(pending);
+    }
+}
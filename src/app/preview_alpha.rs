@@ -0,0 +1,23 @@
+use std::fmt::Display;
+
+/// Size of the checkerboard squares, in source pixels
+pub const CHECKERBOARD_SIZE: u32 = 16;
+
+/// Background the alpha outputs are composited over
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum PreviewAlphaBackground {
+    #[default]
+    Checkerboard,
+    SolidColor,
+}
+
+impl Display for PreviewAlphaBackground {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let val = match self {
+            PreviewAlphaBackground::Checkerboard => "Checkerboard",
+            PreviewAlphaBackground::SolidColor => "Solid color",
+        };
+
+        f.write_str(val)
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt::Display;
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use image::{DynamicImage, Rgb32FImage};
+
+use super::PreviewState;
+use crate::vs_handler::VSFrame;
+
+/// Color of the pixels above the difference threshold
+const HIGHLIGHT_COLOR: [f32; 3] = [1.0, 0.0, 1.0];
+
+/// Displays a second output next to the current one
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum PreviewCompareMode {
+    #[default]
+    Off,
+    /// Current output on the left, compared output on the right
+    VerticalWipe,
+    /// Current output on top, compared output at the bottom
+    HorizontalWipe,
+    SideBySide,
+    /// Absolute difference between the outputs, painted as the current output
+    Difference,
+}
+
+impl PreviewCompareMode {
+    pub const fn is_active(&self) -> bool {
+        !matches!(self, Self::Off)
+    }
+
+    /// Both outputs are painted next to each other
+    pub const fn paints_both(&self) -> bool {
+        matches!(
+            self,
+            Self::VerticalWipe | Self::HorizontalWipe | Self::SideBySide
+        )
+    }
+}
+
+/// Replaces the frame image by its amplified absolute difference with `other`.
+/// The threshold is in 8 bit code values.
+pub fn difference_frame(
+    vsframe: &mut Arc<VSFrame>,
+    other: &VSFrame,
+    state: &PreviewState,
+) -> Result<()> {
+    let (a, b) = (&vsframe.image, &other.image);
+
+    if a.width() != b.width() || a.height() != b.height() {
+        bail!(
+            "Cannot compute difference of outputs with different resolutions: {}x{} and {}x{}",
+            a.width(),
+            a.height(),
+            b.width(),
+            b.height()
+        );
+    }
+
+    let (a, b) = (a.to_rgb32f(), b.to_rgb32f());
+    let amplification = state.diff_amplification;
+    let threshold = state.diff_threshold / 255.0;
+
+    let mut diff = Rgb32FImage::new(a.width(), a.height());
+    diff.pixels_mut()
+        .zip(a.pixels().zip(b.pixels()))
+        .for_each(|(out, (pa, pb))| {
+            let abs = [0, 1, 2].map(|i| (pa[i] - pb[i]).abs());
+
+            out.0 = if state.diff_highlight && abs.iter().any(|v| *v > threshold) {
+                HIGHLIGHT_COLOR
+            } else {
+                abs.map(|v| (v * amplification).min(1.0))
+            };
+        });
+
+    let image = if state.frame_transform_opts.high_precision {
+        DynamicImage::ImageRgb32F(diff)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgb32F(diff).to_rgb8())
+    };
+
+    let mut diff_frame = vsframe.with_image(image);
+    diff_frame.alpha = None;
+    *vsframe = Arc::new(diff_frame);
+
+    Ok(())
+}
+
+impl Display for PreviewCompareMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let val = match self {
+            PreviewCompareMode::Off => "Off",
+            PreviewCompareMode::VerticalWipe => "Vertical wipe",
+            PreviewCompareMode::HorizontalWipe => "Horizontal wipe",
+            PreviewCompareMode::SideBySide => "Side by side",
+            PreviewCompareMode::Difference => "Difference",
+        };
+
+        f.write_str(val)
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt::{Display, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use eframe::egui;
+use parking_lot::Mutex;
+use poll_promise::Promise;
+
+use super::PreviewerResponse;
+use crate::vs_handler::PreviewedScript;
+use crate::vs_handler::vsprops::VSPropValue;
+
+/// Frames between repaints while scanning
+const REPAINT_INTERVAL: usize = 25;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropPlotFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+/// Exported as JSON
+#[derive(serde::Serialize)]
+struct PropPlotExport<'a> {
+    output: i32,
+    key: &'a str,
+    values: Vec<PropPlotValue>,
+}
+
+#[derive(serde::Serialize)]
+struct PropPlotValue {
+    frame: u32,
+    /// Non-finite values are serialized as `null`
+    value: Option<f64>,
+}
+
+/// Values of a prop over a frame range, filled while scanning
+#[derive(Debug, Clone, Default)]
+pub struct PropPlotData {
+    pub output: i32,
+    pub key: String,
+    pub start: u32,
+    /// Inclusive
+    pub end: u32,
+    pub step: u32,
+
+    /// `None` when the frame doesn't have the prop, or it isn't a single number
+    pub values: Vec<(u32, Option<f64>)>,
+}
+
+pub struct FetchPropPlotState {
+    pub data: Arc<Mutex<PropPlotData>>,
+    pub cancelled: Arc<AtomicBool>,
+}
+
+/// Scan running on the worker
+pub struct PropPlotScan {
+    pub cancelled: Arc<AtomicBool>,
+    pub promise: Promise<PreviewerResponse>,
+}
+
+pub struct PropPlot {
+    pub show: bool,
+
+    /// Settings of the next scan
+    pub key: String,
+    pub start: u32,
+    pub end: u32,
+    pub step: u32,
+
+    /// Last scanned values, kept when cancelled
+    pub data: Option<Arc<Mutex<PropPlotData>>>,
+    pub scan: Option<PropPlotScan>,
+    /// Promise of the export dialog
+    pub export_promise: Option<Promise<PreviewerResponse>>,
+}
+
+impl Default for PropPlot {
+    fn default() -> Self {
+        Self {
+            show: false,
+            key: String::new(),
+            start: 0,
+            end: u32::MAX,
+            step: 1,
+            data: None,
+            scan: None,
+            export_promise: None,
+        }
+    }
+}
+
+impl PropPlot {
+    pub fn scanning(&self) -> bool {
+        self.scan.is_some()
+    }
+
+    /// The scan stops after the frames already requested
+    pub fn cancel(&self) {
+        if let Some(scan) = &self.scan {
+            scan.cancelled.store(true, Ordering::Release);
+        }
+    }
+
+    /// Computed on the worker, the values are added as the frames are rendered
+    pub fn compute(
+        script: &Mutex<PreviewedScript>,
+        state: &FetchPropPlotState,
+        ctx: &egui::Context,
+    ) -> Result<()> {
+        let (output, key, start, end, step) = {
+            let data = state.data.lock();
+            (
+                data.output,
+                data.key.clone(),
+                data.start,
+                data.end,
+                data.step,
+            )
+        };
+
+        PreviewedScript::scan_frames(
+            script,
+            (start..=end).step_by(step.max(1) as usize),
+            &state.cancelled,
+            |script, frame_no| script.request_props_map(output, frame_no),
+            |frame_no, props| {
+                let value = props.get(&key).and_then(VSPropValue::as_f64);
+
+                let mut data = state.data.lock();
+                data.values.push((frame_no, value));
+
+                if data.values.len().is_multiple_of(REPAINT_INTERVAL) {
+                    ctx.request_repaint();
+                }
+            },
+        )
+    }
+
+    /// Shows a save dialog, from the command loop
+    pub fn export_dialog(contents: String, format: PropPlotFormat) -> Result<()> {
+        let ext = format.extension();
+
+        let file = rfd::FileDialog::new()
+            .set_title("Export prop values")
+            .add_filter(format.to_string(), &[ext])
+            .set_file_name(format!("props.{ext}"))
+            .save_file();
+
+        if let Some(file) = file {
+            std::fs::write(file, contents)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PropPlotData {
+    pub fn frame_count(&self) -> usize {
+        (self.end.saturating_sub(self.start) / self.step.max(1)) as usize + 1
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.values.len() as f32 / self.frame_count() as f32
+    }
+
+    pub fn numbers(&self) -> impl Iterator<Item = f64> + '_ {
+        self.values
+            .iter()
+            .filter_map(|(_, v)| *v)
+            .filter(|v| v.is_finite())
+    }
+
+    pub fn min_max(&self) -> Option<(f64, f64)> {
+        self.numbers().fold(None, |acc, v| match acc {
+            None => Some((v, v)),
+            Some((min, max)) => Some((min.min(v), max.max(v))),
+        })
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        let (count, sum) = self.numbers().fold((0, 0.0), |(c, s), v| (c + 1, s + v));
+        (count > 0).then(|| sum / count as f64)
+    }
+
+    pub fn export(&self, format: PropPlotFormat) -> Result<String> {
+        Ok(match format {
+            PropPlotFormat::Csv => {
+                let mut out = format!("frame,{}\n", self.key);
+
+                for (frame_no, value) in &self.values {
+                    match value {
+                        Some(v) => writeln!(out, "{frame_no},{v}").ok(),
+                        None => writeln!(out, "{frame_no},").ok(),
+                    };
+                }
+
+                out
+            }
+            PropPlotFormat::Json => {
+                let values = self
+                    .values
+                    .iter()
+                    .map(|(frame, value)| PropPlotValue {
+                        frame: *frame,
+                        value: *value,
+                    })
+                    .collect();
+
+                serde_json::to_string(&PropPlotExport {
+                    output: self.output,
+                    key: &self.key,
+                    values,
+                })?
+            }
+        })
+    }
+}
+
+impl PropPlotFormat {
+    pub const ALL: [Self; 2] = [Self::Csv, Self::Json];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl Display for PropPlotFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Csv => f.write_str("CSV"),
+            Self::Json => f.write_str("JSON"),
+        }
+    }
+}
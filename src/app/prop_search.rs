@@ -0,0 +1,572 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Result, anyhow, bail};
+use eframe::egui;
+use parking_lot::Mutex;
+use poll_promise::Promise;
+
+use super::PreviewerResponse;
+use crate::vs_handler::PreviewedScript;
+use crate::vs_handler::vsprops::{VSPropData, VSPropValue, VSPropsMap};
+
+/// Frames between repaints while scanning
+const REPAINT_INTERVAL: usize = 50;
+/// Nested parentheses and negations, deeper expressions are rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 64;
+
+/// Predicate over the props of a frame, such as `CAMBI > 5 && PlaneStatsMax < 0.9`
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropExpr {
+    /// Chained terms are kept flat
+    Or(Vec<PropExpr>),
+    And(Vec<PropExpr>),
+    Not(Box<PropExpr>),
+    Compare(Operand, CompareOp, Operand),
+    /// Bare operand, true for non-zero numbers and non-empty strings
+    Truthy(Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    /// Key, with an index for arrays
+    Prop(String, usize),
+    Number(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Value of an operand for a frame
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    String(String),
+    Op(CompareOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+}
+
+/// Frames of the current output matching the expression, filled while scanning
+#[derive(Debug, Clone, Default)]
+pub struct PropSearchData {
+    pub output: i32,
+    pub query: String,
+    pub num_frames: u32,
+    pub scanned: u32,
+
+    pub matches: BTreeSet<u32>,
+}
+
+pub struct FetchPropSearchState {
+    pub expr: PropExpr,
+    pub data: Arc<Mutex<PropSearchData>>,
+    pub cancelled: Arc<AtomicBool>,
+}
+
+/// Scan running on the worker
+pub struct PropSearchScan {
+    pub cancelled: Arc<AtomicBool>,
+    pub promise: Promise<PreviewerResponse>,
+}
+
+#[derive(Default)]
+pub struct PropSearch {
+    /// Expression of the next scan
+    pub query: String,
+
+    /// Last scanned matches, kept when cancelled
+    pub data: Option<Arc<Mutex<PropSearchData>>>,
+    pub scan: Option<PropSearchScan>,
+}
+
+impl PropSearch {
+    pub fn scanning(&self) -> bool {
+        self.scan.is_some()
+    }
+
+    /// The scan stops after the frames already requested
+    pub fn cancel(&self) {
+        if let Some(scan) = &self.scan {
+            scan.cancelled.store(true, Ordering::Release);
+        }
+    }
+
+    /// Matched frames, only for the output that was scanned
+    pub fn matches(&self, output: i32) -> Vec<u32> {
+        self.data
+            .as_ref()
+            .map(|data| data.lock())
+            .filter(|data| data.output == output)
+            .map(|data| data.matches.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Closest match after or before the frame
+    pub fn next_match(&self, output: i32, frame_no: u32, forward: bool) -> Option<u32> {
+        let data = self.data.as_ref()?.lock();
+        if data.output != output {
+            return None;
+        }
+
+        if forward {
+            data.matches.range(frame_no + 1..).next().copied()
+        } else {
+            data.matches.range(..frame_no).next_back().copied()
+        }
+    }
+
+    /// Computed on the worker, the matches are added as the frames are rendered
+    pub fn compute(
+        script: &Mutex<PreviewedScript>,
+        state: &FetchPropSearchState,
+        ctx: &egui::Context,
+    ) -> Result<()> {
+        let (output, num_frames) = {
+            let data = state.data.lock();
+            (data.output, data.num_frames)
+        };
+
+        PreviewedScript::scan_frames(
+            script,
+            0..num_frames,
+            &state.cancelled,
+            |script, frame_no| script.request_props_map(output, frame_no),
+            |frame_no, props| {
+                let matched = state.expr.eval(&props);
+
+                let mut data = state.data.lock();
+                data.scanned += 1;
+                if matched {
+                    data.matches.insert(frame_no);
+                }
+
+                if (data.scanned as usize).is_multiple_of(REPAINT_INTERVAL) {
+                    ctx.request_repaint();
+                }
+            },
+        )
+    }
+}
+
+impl PropSearchData {
+    pub fn progress(&self) -> f32 {
+        self.scanned as f32 / self.num_frames.max(1) as f32
+    }
+}
+
+impl PropExpr {
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            bail!("Empty search expression");
+        }
+
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let expr = parser.or()?;
+
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            bail!("Unexpected {token:?} in search expression");
+        }
+
+        Ok(expr)
+    }
+
+    /// Missing props or mismatched types never match
+    pub fn eval(&self, props: &VSPropsMap) -> bool {
+        match self {
+            Self::Or(terms) => terms.iter().any(|e| e.eval(props)),
+            Self::And(terms) => terms.iter().all(|e| e.eval(props)),
+            Self::Not(e) => !e.eval(props),
+            Self::Compare(a, op, b) => match (a.value(props), b.value(props)) {
+                (Some(Value::Number(a)), Some(Value::Number(b))) => op.compare(a, b),
+                (Some(Value::String(a)), Some(Value::String(b))) => op.compare(a, b),
+                _ => false,
+            },
+            Self::Truthy(operand) => match operand.value(props) {
+                Some(Value::Number(v)) => v != 0.0,
+                Some(Value::String(s)) => !s.is_empty(),
+                None => false,
+            },
+        }
+    }
+}
+
+impl Operand {
+    fn value(&self, props: &VSPropsMap) -> Option<Value> {
+        match self {
+            Self::Number(v) => Some(Value::Number(*v)),
+            Self::String(s) => Some(Value::String(s.clone())),
+            Self::Prop(key, index) => match props.get(key)? {
+                VSPropValue::Int(v) => v.get(*index).map(|v| Value::Number(*v as f64)),
+                VSPropValue::Float(v) => v.get(*index).map(|v| Value::Number(*v)),
+                VSPropValue::Data(v) => match v.get(*index)? {
+                    VSPropData::Utf8(s) => Some(Value::String(s.clone())),
+                    VSPropData::Binary { .. } => None,
+                },
+                _ => None,
+            },
+        }
+    }
+}
+
+impl CompareOp {
+    fn compare<T: PartialOrd>(&self, a: T, b: T) -> bool {
+        match self {
+            Self::Eq => a == b,
+            Self::Ne => a != b,
+            Self::Lt => a < b,
+            Self::Le => a <= b,
+            Self::Gt => a > b,
+            Self::Ge => a >= b,
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while let Some(&c) = chars.get(pos) {
+        let next = chars.get(pos + 1).copied();
+        pos += 1;
+
+        let token = match (c, next) {
+            (c, _) if c.is_whitespace() => continue,
+            ('&', Some('&')) => Token::And,
+            ('|', Some('|')) => Token::Or,
+            ('=', Some('=')) => Token::Op(CompareOp::Eq),
+            ('!', Some('=')) => Token::Op(CompareOp::Ne),
+            ('<', Some('=')) => Token::Op(CompareOp::Le),
+            ('>', Some('=')) => Token::Op(CompareOp::Ge),
+            ('<', _) => Token::Op(CompareOp::Lt),
+            ('>', _) => Token::Op(CompareOp::Gt),
+            ('!', _) => Token::Not,
+            ('(', _) => Token::LParen,
+            (')', _) => Token::RParen,
+            ('[', _) => Token::LBracket,
+            (']', _) => Token::RBracket,
+            ('"' | '\'', _) => {
+                let start = pos;
+                while chars.get(pos).is_some_and(|q| *q != c) {
+                    pos += 1;
+                }
+                if pos >= chars.len() {
+                    bail!("Unterminated string at {}", start - 1);
+                }
+
+                pos += 1;
+                tokens.push(Token::String(chars[start..pos - 1].iter().collect()));
+                continue;
+            }
+            (c, _) if c.is_ascii_digit() || c == '.' || c == '-' => {
+                let start = pos - 1;
+                while chars.get(pos).is_some_and(|c| {
+                    c.is_ascii_digit()
+                        || matches!(c, '.' | 'e' | 'E')
+                        || (matches!(c, '-' | '+') && matches!(chars[pos - 1], 'e' | 'E'))
+                }) {
+                    pos += 1;
+                }
+
+                let number: String = chars[start..pos].iter().collect();
+                let value = number
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid number `{number}` at {start}"))?;
+
+                tokens.push(Token::Number(value));
+                continue;
+            }
+            (c, _) if c.is_alphabetic() || c == '_' => {
+                let start = pos - 1;
+                while chars
+                    .get(pos)
+                    .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '.'))
+                {
+                    pos += 1;
+                }
+
+                tokens.push(Token::Ident(chars[start..pos].iter().collect()));
+                continue;
+            }
+            (c, _) => bail!("Unexpected `{c}` at {}", pos - 1),
+        };
+
+        // Two character operators
+        if matches!(
+            token,
+            Token::And
+                | Token::Or
+                | Token::Op(CompareOp::Eq | CompareOp::Ne | CompareOp::Le | CompareOp::Ge)
+        ) {
+            pos += 1;
+        }
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Of the parentheses and negations being parsed
+    depth: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let matched = self.tokens.get(self.pos) == Some(token);
+        if matched {
+            self.pos += 1;
+        }
+
+        matched
+    }
+
+    fn or(&mut self) -> Result<PropExpr> {
+        let mut terms = vec![self.and()?];
+        while self.eat(&Token::Or) {
+            terms.push(self.and()?);
+        }
+
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            PropExpr::Or(terms)
+        })
+    }
+
+    fn and(&mut self) -> Result<PropExpr> {
+        let mut terms = vec![self.unary()?];
+        while self.eat(&Token::And) {
+            terms.push(self.unary()?);
+        }
+
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            PropExpr::And(terms)
+        })
+    }
+
+    fn unary(&mut self) -> Result<PropExpr> {
+        if self.eat(&Token::Not) {
+            return self.nested(|p| Ok(PropExpr::Not(Box::new(p.unary()?))));
+        }
+
+        if self.eat(&Token::LParen) {
+            return self.nested(|p| {
+                let expr = p.or()?;
+                if !p.eat(&Token::RParen) {
+                    bail!("Missing `)` in search expression");
+                }
+
+                Ok(expr)
+            });
+        }
+
+        let lhs = self.operand()?;
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => {
+                let op = *op;
+                self.pos += 1;
+
+                Ok(PropExpr::Compare(lhs, op, self.operand()?))
+            }
+            _ => Ok(PropExpr::Truthy(lhs)),
+        }
+    }
+
+    fn nested(&mut self, parse: impl FnOnce(&mut Self) -> Result<PropExpr>) -> Result<PropExpr> {
+        if self.depth >= MAX_DEPTH {
+            bail!("Search expression nested deeper than {MAX_DEPTH}");
+        }
+
+        self.depth += 1;
+        let res = parse(self);
+        self.depth -= 1;
+
+        res
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        match self.next() {
+            Some(Token::Number(v)) => Ok(Operand::Number(v)),
+            Some(Token::String(s)) => Ok(Operand::String(s)),
+            Some(Token::Ident(key)) => {
+                if !self.eat(&Token::LBracket) {
+                    return Ok(Operand::Prop(key, 0));
+                }
+
+                let index = match self.next() {
+                    Some(Token::Number(v)) if v >= 0.0 && v.fract() == 0.0 => v as usize,
+                    _ => bail!("Invalid index of `{key}`"),
+                };
+                if !self.eat(&Token::RBracket) {
+                    bail!("Missing `]` after the index of `{key}`");
+                }
+
+                Ok(Operand::Prop(key, index))
+            }
+            Some(token) => bail!("Expected a prop or a value, found {token:?}"),
+            None => bail!("Incomplete search expression"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vs_handler::vsprops::VSProp;
+
+    fn props() -> VSPropsMap {
+        let prop = |key: &str, value| VSProp {
+            key: key.to_string(),
+            value,
+        };
+
+        VSPropsMap {
+            props: vec![
+                prop("CAMBI", VSPropValue::Float(vec![6.5])),
+                prop("_Combed", VSPropValue::Int(vec![0])),
+                prop("Levels", VSPropValue::Int(vec![16, 235])),
+                prop(
+                    "_PictType",
+                    VSPropValue::Data(vec![VSPropData::Utf8("I".to_string())]),
+                ),
+            ],
+        }
+    }
+
+    fn matches(input: &str) -> bool {
+        PropExpr::parse(input).unwrap().eval(&props())
+    }
+
+    fn prop(key: &str) -> PropExpr {
+        PropExpr::Truthy(Operand::Prop(key.to_string(), 0))
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            PropExpr::parse("a || b && c").unwrap(),
+            PropExpr::Or(vec![prop("a"), PropExpr::And(vec![prop("b"), prop("c")])])
+        );
+        assert_eq!(
+            PropExpr::parse("(a || b) && !c").unwrap(),
+            PropExpr::And(vec![
+                PropExpr::Or(vec![prop("a"), prop("b")]),
+                PropExpr::Not(Box::new(prop("c"))),
+            ])
+        );
+
+        assert!(matches("CAMBI > 100 || CAMBI > 5 && _PictType == 'I'"));
+        assert!(!matches("(CAMBI > 100 || CAMBI > 5) && _PictType == 'P'"));
+    }
+
+    #[test]
+    fn not_and_parentheses() {
+        assert!(matches("!_Combed"));
+        assert!(!matches("!!_Combed"));
+        assert!(matches("!(CAMBI < 5)"));
+        assert!(matches("((CAMBI >= 6.5))"));
+    }
+
+    #[test]
+    fn comparisons() {
+        assert!(matches("CAMBI == 6.5"));
+        assert!(matches("CAMBI != 6"));
+        assert!(matches("CAMBI <= 6.5 && CAMBI >= 6.5"));
+        assert!(matches("-1e3 < _Combed"));
+        assert!(matches("Levels[1] == 235"));
+        assert!(!matches("Levels[2] == 235"));
+
+        assert!(matches("_PictType == \"I\""));
+        assert!(matches("_PictType < 'P'"));
+        assert!(!matches("_PictType == 'B'"));
+
+        // Mismatched types never match
+        assert!(!matches("_PictType == 1"));
+        assert!(!matches("CAMBI == '6.5'"));
+    }
+
+    #[test]
+    fn missing_keys() {
+        assert!(!matches("Missing"));
+        assert!(!matches("Missing == 0"));
+        assert!(!matches("Missing != 0"));
+        assert!(matches("!Missing"));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for invalid in [
+            "",
+            "(CAMBI > 5",
+            "CAMBI > 5)",
+            "CAMBI >",
+            "&& CAMBI",
+            "CAMBI > 5 ||",
+            "Levels[",
+            "Levels[-1]",
+            "Levels[0",
+            "_PictType == 'I",
+            "CAMBI # 5",
+            "1.2.3",
+        ] {
+            assert!(PropExpr::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn nesting() {
+        let nested = |depth| format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+
+        assert!(PropExpr::parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(PropExpr::parse(&nested(MAX_DEPTH + 1)).is_err());
+        assert!(PropExpr::parse(&format!("{}a", "!".repeat(MAX_DEPTH + 1))).is_err());
+
+        // Would overflow the stack without the limit
+        assert!(PropExpr::parse(&"(".repeat(100_000)).is_err());
+        assert!(PropExpr::parse(&"!".repeat(100_000)).is_err());
+
+        // Chains stay flat
+        let chain = vec!["a"; 10_000].join(" || ");
+        assert!(PropExpr::parse(&chain).is_ok());
+    }
+}
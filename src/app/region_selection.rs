@@ -0,0 +1,156 @@
+use eframe::egui::{Pos2, Rect, pos2};
+
+/// Alignments that can be chosen for the crop values
+pub const REGION_ALIGNMENTS: [u32; 5] = [1, 2, 4, 8, 16];
+
+/// Rectangle dragged over an output, in the clip's coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelectedRegion {
+    pub output: i32,
+    pub clip_size: [u32; 2],
+    /// Log2 chroma subsampling of the clip, the crop must be aligned to it
+    pub subsampling: (u8, u8),
+
+    start: Pos2,
+    end: Pos2,
+}
+
+/// Crop values of the region, aligned to the subsampling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionCrop {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct RegionSelection {
+    pub region: Option<SelectedRegion>,
+    /// Minimum alignment of the crop values, raised to the subsampling
+    pub alignment: u32,
+
+    dragging: bool,
+}
+
+impl Default for RegionSelection {
+    fn default() -> Self {
+        Self {
+            region: None,
+            alignment: 1,
+            dragging: false,
+        }
+    }
+}
+
+impl RegionSelection {
+    /// Starts a new selection at `pos`, in the clip's coordinates
+    pub fn start(&mut self, output: i32, pos: Pos2, clip_size: [u32; 2], subsampling: (u8, u8)) {
+        let pos = clamp_to_clip(pos, clip_size);
+
+        self.dragging = true;
+        self.region = Some(SelectedRegion {
+            output,
+            clip_size,
+            subsampling,
+            start: pos,
+            end: pos,
+        });
+    }
+
+    /// Output being dragged over
+    pub fn dragging(&self) -> Option<i32> {
+        self.region
+            .as_ref()
+            .filter(|_| self.dragging)
+            .map(|region| region.output)
+    }
+
+    pub fn drag_to(&mut self, pos: Pos2) {
+        if let Some(region) = self.region.as_mut().filter(|_| self.dragging) {
+            region.end = clamp_to_clip(pos, region.clip_size);
+        }
+    }
+
+    /// Empty selections are discarded
+    pub fn finish(&mut self) {
+        self.dragging = false;
+
+        if self.crop().is_none() {
+            self.region = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.dragging = false;
+        self.region = None;
+    }
+
+    /// Aligned crop of the selected region
+    pub fn crop(&self) -> Option<RegionCrop> {
+        self.region.as_ref()?.crop(self.alignment)
+    }
+}
+
+impl SelectedRegion {
+    /// Left and top are rounded to the alignment, as are the right and bottom crops.
+    /// `None` if nothing is left after alignment.
+    pub fn crop(&self, alignment: u32) -> Option<RegionCrop> {
+        let align_w = alignment.max(1 << self.subsampling.0);
+        let align_h = alignment.max(1 << self.subsampling.1);
+
+        let (left, right) = aligned_span(self.start.x, self.end.x, self.clip_size[0], align_w)?;
+        let (top, bottom) = aligned_span(self.start.y, self.end.y, self.clip_size[1], align_h)?;
+
+        Some(RegionCrop {
+            left,
+            top,
+            right,
+            bottom,
+            width: self.clip_size[0] - left - right,
+            height: self.clip_size[1] - top - bottom,
+        })
+    }
+}
+
+impl RegionCrop {
+    /// Region in the clip's coordinates
+    pub fn rect(&self) -> Rect {
+        Rect::from_min_size(
+            pos2(self.left as f32, self.top as f32),
+            [self.width as f32, self.height as f32].into(),
+        )
+    }
+
+    pub fn crop_snippet(&self) -> String {
+        format!(
+            "core.std.Crop(clip, left={}, right={}, top={}, bottom={})",
+            self.left, self.right, self.top, self.bottom
+        )
+    }
+
+    pub fn crop_abs_snippet(&self) -> String {
+        format!(
+            "core.std.CropAbs(clip, width={}, height={}, left={}, top={})",
+            self.width, self.height, self.left, self.top
+        )
+    }
+}
+
+fn clamp_to_clip(pos: Pos2, clip_size: [u32; 2]) -> Pos2 {
+    pos2(
+        pos.x.clamp(0.0, clip_size[0] as f32),
+        pos.y.clamp(0.0, clip_size[1] as f32),
+    )
+}
+
+/// Start offset and end crop of the span between `a` and `b`
+fn aligned_span(a: f32, b: f32, size: u32, alignment: u32) -> Option<(u32, u32)> {
+    let align = |v: f32| (v / alignment as f32).round() as u32 * alignment;
+
+    let start = align(a.min(b)).min(size);
+    let end_crop = align(size as f32 - a.max(b)).min(size - start);
+
+    (start + end_crop < size).then_some((start, end_crop))
+}
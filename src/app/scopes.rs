@@ -0,0 +1,444 @@
+use std::fmt::Display;
+use std::sync::Arc;
+
+use anyhow::Result;
+use eframe::egui::{self, ColorImage, Pos2, TextureHandle, TextureOptions};
+use parking_lot::Mutex;
+use poll_promise::Promise;
+
+use super::{PreviewerResponse, VSPreviewFrame};
+use crate::vs_handler::vsplanes::VSPlane;
+use crate::vs_handler::{PendingFrame, PreviewedScript, VSPlanes};
+
+/// Columns of the waveform and parade
+const WAVEFORM_WIDTH: usize = 512;
+/// Rows of the waveforms, and bins of the histogram
+const SCOPE_LEVELS: usize = 256;
+const VECTORSCOPE_SIZE: usize = 256;
+const HISTOGRAM_HEIGHT: usize = 128;
+
+/// Minimum brightness of the occupied scope cells, so sparse values stay visible
+const MIN_DENSITY: f32 = 0.15;
+
+/// Waveform and vectorscope trace color
+const TRACE_COLOR: [f32; 3] = [0.55, 1.0, 0.6];
+
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+pub enum ScopeSource {
+    /// Converted RGB frame, as displayed
+    #[default]
+    Preview,
+    /// Output clip in its native format
+    Original,
+}
+
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+pub enum VectorscopeTargets {
+    #[default]
+    Bt709,
+    Bt2020,
+}
+
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+#[serde(default)]
+pub struct ScopesOptions {
+    pub show: bool,
+    /// Side panel instead of a floating window
+    pub docked: bool,
+    pub settings: ScopeSettings,
+}
+
+/// What is computed, changing them recomputes the scopes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ScopeSettings {
+    pub waveform: bool,
+    pub parade: bool,
+    pub vectorscope: bool,
+    pub histogram: bool,
+
+    pub source: ScopeSource,
+    /// Matrix of the vectorscope targets, also used to compute YUV from RGB
+    pub targets: VectorscopeTargets,
+}
+
+/// Computed on the worker, for the enabled scopes
+#[derive(Default)]
+pub struct ScopeImages {
+    pub waveform: Option<ColorImage>,
+    pub parade: Option<ColorImage>,
+    pub vectorscope: Option<ColorImage>,
+    pub histogram: Option<ColorImage>,
+
+    pub format_name: String,
+    pub plane_names: Vec<&'static str>,
+    pub limited_range: bool,
+}
+
+#[derive(Default)]
+pub struct ScopeTextures {
+    pub waveform: Option<TextureHandle>,
+    pub parade: Option<TextureHandle>,
+    pub vectorscope: Option<TextureHandle>,
+    pub histogram: Option<TextureHandle>,
+
+    pub format_name: String,
+    pub plane_names: Vec<&'static str>,
+    pub limited_range: bool,
+}
+
+/// Frame and settings the scopes are computed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScopesKey {
+    pub output: i32,
+    pub frame_no: u32,
+    /// New frames are new allocations, even for the same frame number
+    pub frame_ptr: usize,
+    pub settings: ScopeSettings,
+}
+
+pub struct FetchScopesState {
+    pub key: ScopesKey,
+    pub pf: VSPreviewFrame,
+}
+
+/// Scopes of the displayed frame, updated on each render
+#[derive(Default)]
+pub struct Scopes {
+    pub key: Option<ScopesKey>,
+    pub promise: Option<(ScopesKey, Promise<PreviewerResponse>)>,
+    pub textures: ScopeTextures,
+}
+
+impl Default for ScopeSettings {
+    fn default() -> Self {
+        Self {
+            waveform: true,
+            parade: false,
+            vectorscope: true,
+            histogram: true,
+            source: Default::default(),
+            targets: Default::default(),
+        }
+    }
+}
+
+impl Scopes {
+    /// Runs on the worker, the original clip is only locked to request the frame
+    pub fn compute(
+        script: &Arc<Mutex<PreviewedScript>>,
+        fetch_state: FetchScopesState,
+    ) -> Result<ScopeImages> {
+        let FetchScopesState { key, pf } = fetch_state;
+
+        let planes = match key.settings.source {
+            ScopeSource::Preview => VSPlanes::from_image(&pf.read().vsframe.image),
+            ScopeSource::Original => {
+                let pending = script
+                    .lock()
+                    .request_source_planes(key.output, key.frame_no);
+                pending.and_then(PendingFrame::wait)?
+            }
+        };
+
+        Ok(compute_scopes(&planes, &key.settings))
+    }
+
+    pub fn set_images(&mut self, ctx: &egui::Context, images: ScopeImages) {
+        let load = |name: &str, image: Option<ColorImage>| {
+            image.map(|image| ctx.load_texture(name, image, TextureOptions::LINEAR))
+        };
+
+        self.textures = ScopeTextures {
+            waveform: load("scope_waveform", images.waveform),
+            parade: load("scope_parade", images.parade),
+            vectorscope: load("scope_vectorscope", images.vectorscope),
+            histogram: load("scope_histogram", images.histogram),
+            format_name: images.format_name,
+            plane_names: images.plane_names,
+            limited_range: images.limited_range,
+        };
+    }
+}
+
+impl VectorscopeTargets {
+    pub const ALL: [Self; 2] = [Self::Bt709, Self::Bt2020];
+
+    /// Luma coefficients `(Kr, Kb)`
+    const fn coefficients(&self) -> (f32, f32) {
+        match self {
+            Self::Bt709 => (0.2126, 0.0722),
+            Self::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+
+    fn luma(&self, [r, g, b]: [f32; 3]) -> f32 {
+        let (kr, kb) = self.coefficients();
+
+        kr * r + (1.0 - kr - kb) * g + kb * b
+    }
+
+    /// Normalized `(Cb, Cr)`, centered on 0.5
+    fn chroma(&self, rgb: [f32; 3]) -> (f32, f32) {
+        let (kr, kb) = self.coefficients();
+        let y = self.luma(rgb);
+
+        (
+            (rgb[2] - y) / (2.0 * (1.0 - kb)) + 0.5,
+            (rgb[0] - y) / (2.0 * (1.0 - kr)) + 0.5,
+        )
+    }
+
+    /// Positions of the 75% color bars in the vectorscope, normalized.
+    /// Limited range chroma spans 224 of 255 code values.
+    pub fn targets(&self, limited_range: bool) -> [(&'static str, Pos2); 6] {
+        let scale = if limited_range { 224.0 / 255.0 } else { 1.0 };
+
+        [
+            ("R", [0.75, 0.0, 0.0]),
+            ("Yl", [0.75, 0.75, 0.0]),
+            ("G", [0.0, 0.75, 0.0]),
+            ("Cy", [0.0, 0.75, 0.75]),
+            ("B", [0.0, 0.0, 0.75]),
+            ("Mg", [0.75, 0.0, 0.75]),
+        ]
+        .map(|(name, rgb)| {
+            let (cb, cr) = self.chroma(rgb);
+            let pos = Pos2::new(0.5 + (cb - 0.5) * scale, 1.0 - (0.5 + (cr - 0.5) * scale));
+
+            (name, pos)
+        })
+    }
+}
+
+/// Scopes enabled in `settings`, from the normalized planes
+pub fn compute_scopes(planes: &VSPlanes, settings: &ScopeSettings) -> ScopeImages {
+    let is_rgb = planes.planes.len() == 3 && !planes.is_yuv();
+    let targets = settings.targets;
+
+    // Weighted from RGB, with the matrix of the targets
+    let rgb_planes = is_rgb.then(|| {
+        let [r, g, b] = [0, 1, 2].map(|i| &planes.planes[i].data);
+        let rgb = r.iter().zip(g).zip(b).map(|((r, g), b)| [*r, *g, *b]);
+
+        (planes.planes[0].width, planes.planes[0].height, rgb)
+    });
+
+    let waveform = settings.waveform.then(|| {
+        let luma = match &rgb_planes {
+            Some((width, height, rgb)) => VSPlane {
+                name: "Y",
+                width: *width,
+                height: *height,
+                data: rgb.clone().map(|rgb| targets.luma(rgb)).collect(),
+            },
+            None => planes.planes[0].clone(),
+        };
+
+        let mut canvas = ScopeCanvas::new(WAVEFORM_WIDTH, SCOPE_LEVELS);
+        canvas.add_density(
+            &waveform_counts(&luma, WAVEFORM_WIDTH),
+            WAVEFORM_WIDTH,
+            0,
+            TRACE_COLOR,
+        );
+
+        canvas.into_image()
+    });
+
+    let parade = settings.parade.then(|| {
+        let cols = WAVEFORM_WIDTH / planes.planes.len();
+        let mut canvas = ScopeCanvas::new(cols * planes.planes.len(), SCOPE_LEVELS);
+
+        for (i, plane) in planes.planes.iter().enumerate() {
+            let counts = waveform_counts(plane, cols);
+            canvas.add_density(&counts, cols, i * cols, plane_color(plane.name));
+        }
+
+        canvas.into_image()
+    });
+
+    let vectorscope = settings
+        .vectorscope
+        .then(|| {
+            let chroma: Vec<(f32, f32)> = match &rgb_planes {
+                Some((_, _, rgb)) => rgb.clone().map(|rgb| targets.chroma(rgb)).collect(),
+                None if planes.is_yuv() => {
+                    let (u, v) = (&planes.planes[1].data, &planes.planes[2].data);
+                    u.iter().copied().zip(v.iter().copied()).collect()
+                }
+                // Gray has no chroma
+                None => return None,
+            };
+
+            let size = VECTORSCOPE_SIZE;
+            let mut counts = vec![0; size * size];
+            for (cb, cr) in chroma {
+                let x = level(cb, size);
+                let y = size - 1 - level(cr, size);
+                counts[y * size + x] += 1;
+            }
+
+            let mut canvas = ScopeCanvas::new(size, size);
+            canvas.add_density(&counts, size, 0, TRACE_COLOR);
+
+            Some(canvas.into_image())
+        })
+        .flatten();
+
+    let histogram = settings.histogram.then(|| histogram(&planes.planes));
+
+    ScopeImages {
+        waveform,
+        parade,
+        vectorscope,
+        histogram,
+        format_name: planes.format_name.clone(),
+        plane_names: planes.planes.iter().map(|p| p.name).collect(),
+        limited_range: planes.limited_range,
+    }
+}
+
+/// Row of a normalized value, out of `levels`
+fn level(value: f32, levels: usize) -> usize {
+    // NaN is cast to 0
+    (value.clamp(0.0, 1.0) * (levels - 1) as f32).round() as usize
+}
+
+/// Counts the samples of the plane into `cols` columns of `SCOPE_LEVELS` rows, top is 1.0
+fn waveform_counts(plane: &VSPlane, cols: usize) -> Vec<u32> {
+    let mut counts = vec![0; cols * SCOPE_LEVELS];
+
+    for row in plane.data.chunks_exact(plane.width) {
+        for (x, value) in row.iter().enumerate() {
+            let col = x * cols / plane.width;
+            let y = SCOPE_LEVELS - 1 - level(*value, SCOPE_LEVELS);
+
+            counts[y * cols + col] += 1;
+        }
+    }
+
+    counts
+}
+
+fn histogram(planes: &[VSPlane]) -> ColorImage {
+    let bins: Vec<Vec<u32>> = planes
+        .iter()
+        .map(|plane| {
+            let mut bins = vec![0; SCOPE_LEVELS];
+            for value in &plane.data {
+                bins[level(*value, SCOPE_LEVELS)] += 1;
+            }
+
+            bins
+        })
+        .collect();
+
+    let max = bins.iter().flatten().copied().max().unwrap_or(0).max(1) as f32;
+    let mut canvas = ScopeCanvas::new(SCOPE_LEVELS, HISTOGRAM_HEIGHT);
+
+    for (plane, bins) in planes.iter().zip(&bins) {
+        let color = plane_color(plane.name).map(|c| c * 0.7);
+
+        for (x, count) in bins.iter().enumerate() {
+            let height = (*count as f32 / max * HISTOGRAM_HEIGHT as f32).round() as usize;
+
+            for y in HISTOGRAM_HEIGHT - height..HISTOGRAM_HEIGHT {
+                canvas.add(x, y, color, 1.0);
+            }
+        }
+    }
+
+    canvas.into_image()
+}
+
+fn plane_color(name: &str) -> [f32; 3] {
+    match name {
+        "R" => [1.0, 0.25, 0.25],
+        "G" => [0.25, 1.0, 0.25],
+        "B" => [0.35, 0.45, 1.0],
+        "U" => [0.35, 0.6, 1.0],
+        "V" => [1.0, 0.45, 0.35],
+        _ => [0.9, 0.9, 0.9],
+    }
+}
+
+/// Additive colors, converted to an image once complete
+struct ScopeCanvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 3]>,
+}
+
+impl ScopeCanvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0.0; 3]; width * height],
+        }
+    }
+
+    fn add(&mut self, x: usize, y: usize, color: [f32; 3], intensity: f32) {
+        let pixel = &mut self.pixels[y * self.width + x];
+
+        for (p, c) in pixel.iter_mut().zip(color) {
+            *p += c * intensity;
+        }
+    }
+
+    /// Adds the `cols` wide counts at `x_offset`, brighter where denser
+    fn add_density(&mut self, counts: &[u32], cols: usize, x_offset: usize, color: [f32; 3]) {
+        let max = counts.iter().copied().max().unwrap_or(0);
+        if max == 0 {
+            return;
+        }
+
+        let norm = (1.0 + max as f32).ln();
+
+        for (i, count) in counts.iter().enumerate().filter(|(_, c)| **c > 0) {
+            let density = (1.0 + *count as f32).ln() / norm;
+            let intensity = MIN_DENSITY + (1.0 - MIN_DENSITY) * density;
+
+            self.add(i % cols + x_offset, i / cols, color, intensity);
+        }
+    }
+
+    fn into_image(self) -> ColorImage {
+        let rgb: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|p| p.map(|v| (v.min(1.0) * 255.0).round() as u8))
+            .collect();
+
+        ColorImage::from_rgb([self.width, self.height], &rgb)
+    }
+}
+
+impl Display for ScopeSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let val = match self {
+            ScopeSource::Preview => "Preview RGB",
+            ScopeSource::Original => "Original clip",
+        };
+
+        f.write_str(val)
+    }
+}
+
+impl Display for VectorscopeTargets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let val = match self {
+            VectorscopeTargets::Bt709 => "BT.709",
+            VectorscopeTargets::Bt2020 => "BT.2020",
+        };
+
+        f.write_str(val)
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use eframe::egui::Context;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{DebounceEventResult, Debouncer, new_debouncer};
+use parking_lot::Mutex;
+
+/// Rapid saves within this delay only trigger a single reload
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Watches the script file and the local Python modules it imports
+pub struct ScriptWatcher {
+    debouncer: Debouncer<RecommendedWatcher>,
+
+    /// Files that trigger a reload when modified
+    files: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Directories currently watched, files are filtered from their events
+    watched_dirs: HashSet<PathBuf>,
+
+    changed: Arc<AtomicBool>,
+}
+
+impl ScriptWatcher {
+    pub fn new(ctx: Context) -> Result<Self> {
+        let files: Arc<Mutex<HashSet<PathBuf>>> = Default::default();
+        let changed: Arc<AtomicBool> = Default::default();
+
+        let debouncer = {
+            let files = files.clone();
+            let changed = changed.clone();
+
+            new_debouncer(DEBOUNCE_TIMEOUT, move |res: DebounceEventResult| {
+                if let Ok(events) = res {
+                    let files = files.lock();
+
+                    if events.iter().any(|e| files.contains(&e.path)) {
+                        changed.store(true, Ordering::Release);
+                        ctx.request_repaint();
+                    }
+                }
+            })?
+        };
+
+        Ok(Self {
+            debouncer,
+            files,
+            watched_dirs: HashSet::new(),
+            changed,
+        })
+    }
+
+    /// Replaces the watched files with the script and its local imports
+    pub fn watch_script(&mut self, script_file: &Path) -> Result<()> {
+        let files = script_dependencies(script_file);
+
+        // Watch the parent directories, editors often replace the file on save
+        let dirs: HashSet<PathBuf> = files
+            .iter()
+            .filter_map(|f| f.parent().map(Path::to_path_buf))
+            .collect();
+
+        let watcher = self.debouncer.watcher();
+
+        for dir in self.watched_dirs.difference(&dirs) {
+            watcher.unwatch(dir).ok();
+        }
+        for dir in dirs.difference(&self.watched_dirs) {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+
+        self.watched_dirs = dirs;
+        *self.files.lock() = files;
+
+        Ok(())
+    }
+
+    /// Returns whether any watched file changed since the last call
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::AcqRel)
+    }
+}
+
+/// The script file along with every local module it imports, recursively
+fn script_dependencies(script_file: &Path) -> HashSet<PathBuf> {
+    let mut files = HashSet::new();
+
+    let Ok(script_file) = script_file.canonicalize() else {
+        return files;
+    };
+    let search_dir = script_file.parent().map(Path::to_path_buf);
+
+    let mut pending = vec![script_file];
+    while let Some(file) = pending.pop() {
+        if files.contains(&file) {
+            continue;
+        }
+
+        if let Ok(source) = std::fs::read_to_string(&file) {
+            let module_dir = file.parent();
+
+            for (module, relative) in parse_imports(&source) {
+                let base = if relative {
+                    module_dir
+                } else {
+                    search_dir.as_deref()
+                };
+
+                if let Some(path) = base.and_then(|dir| resolve_module(dir, &module)) {
+                    pending.push(path);
+                }
+            }
+        }
+
+        files.insert(file);
+    }
+
+    files
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split_once('#').map_or(line, |(code, _)| code).trim()
+}
+
+/// Import statements, joined with their parenthesized or backslash continued lines
+fn import_statements(source: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut lines = source.lines();
+
+    while let Some(line) = lines.next() {
+        let mut line = strip_comment(line);
+        if !line.starts_with("import ") && !line.starts_with("from ") {
+            continue;
+        }
+
+        let mut statement = String::new();
+        loop {
+            let (code, continued) = match line.strip_suffix('\\') {
+                Some(code) => (code, true),
+                None => (line, false),
+            };
+            statement.push_str(code);
+            statement.push(' ');
+
+            let in_parens = statement.matches('(').count() > statement.matches(')').count();
+            if !continued && !in_parens {
+                break;
+            }
+
+            let Some(next) = lines.next() else {
+                break;
+            };
+            line = strip_comment(next);
+        }
+
+        statements.push(statement);
+    }
+
+    statements
+}
+
+/// Module names from `import` and `from ... import` statements.
+/// Relative imports are flagged to be resolved from the importing module directory.
+fn parse_imports(source: &str) -> Vec<(String, bool)> {
+    let mut modules = Vec::new();
+
+    for statement in import_statements(source) {
+        let line = statement.trim();
+
+        if let Some(rest) = line.strip_prefix("import ") {
+            for name in rest.split(',') {
+                if let Some(module) = name.split_whitespace().next() {
+                    modules.push((module.to_string(), false));
+                }
+            }
+        } else if let Some(rest) = line.strip_prefix("from ")
+            && let Some((module, names)) = rest.split_once(" import ")
+        {
+            let module = module.trim();
+            let relative = module.starts_with('.');
+            let module = module.trim_start_matches('.');
+
+            // `from pkg import mod` can import submodules as well
+            let names = names.trim_matches(|c: char| c == '(' || c == ')' || c.is_whitespace());
+            for name in names.split(',') {
+                if let Some(name) = name.split_whitespace().next()
+                    && name != "*"
+                {
+                    let submodule = if module.is_empty() {
+                        name.to_string()
+                    } else {
+                        format!("{module}.{name}")
+                    };
+                    modules.push((submodule, relative));
+                }
+            }
+
+            if !module.is_empty() {
+                modules.push((module.to_string(), relative));
+            }
+        }
+    }
+
+    modules
+}
+
+fn resolve_module(dir: &Path, module: &str) -> Option<PathBuf> {
+    let mut path = dir.to_path_buf();
+    path.extend(module.split('.'));
+
+    let file = path.with_extension("py");
+    let package = path.join("__init__.py");
+
+    [file, package]
+        .into_iter()
+        .find(|p| p.is_file())
+        .and_then(|p| p.canonicalize().ok())
+}
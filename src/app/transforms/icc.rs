@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 
 use lcms2::{CIExyY, CIExyYTRIPLE, Flags, Intent, PixelFormat, Profile, ToneCurve, Transform};
-use rgb::RGB8;
+use rgb::{RGB, RGB8};
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct IccProfile {
@@ -18,6 +18,10 @@ pub struct IccProfile {
 
     #[serde(skip)]
     pub transform: Option<Transform<RGB8, RGB8>>,
+
+    /// Transform for high bit depth images, normalized to [0, 1]
+    #[serde(skip)]
+    pub transform_float: Option<Transform<RGB<f32>, RGB<f32>>>,
 }
 
 #[derive(Debug, Copy, Clone, serde::Deserialize, serde::Serialize)]
@@ -44,6 +48,7 @@ impl IccProfile {
             input_whitepoint: XyYCoords::d65(),
             input_primaries: XyYTriple::rec709(),
             transform: None,
+            transform_float: None,
         }
     }
 
@@ -79,9 +84,20 @@ impl IccProfile {
         )
         .unwrap();
 
+        let transform_float = Transform::new_flags(
+            &input_profile,
+            PixelFormat::RGB_FLT,
+            &target_profile,
+            PixelFormat::RGB_FLT,
+            intent,
+            Flags::default() | Flags::BLACKPOINT_COMPENSATION,
+        )
+        .unwrap();
+
         self.input_profile = Some(input_profile);
         self.target_profile = Some(target_profile);
         self.transform = Some(transform);
+        self.transform_float = Some(transform_float);
     }
 }
 
@@ -146,6 +162,7 @@ impl Clone for IccProfile {
             input_whitepoint: self.input_whitepoint,
             input_primaries: self.input_primaries,
             transform: None,
+            transform_float: None,
         }
     }
 }
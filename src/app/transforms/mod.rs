@@ -1 +1,2 @@
 pub mod icc;
+pub mod tonemap;
@@ -0,0 +1,191 @@
+use std::fmt::Display;
+use std::sync::Arc;
+
+use image::{DynamicImage, Rgb32FImage};
+
+use crate::vs_handler::{NOMINAL_LUMINANCE, VSFrame, zimg_map::VSTransferCharacteristics};
+
+/// HLG is display referred to a 1000 cd/m² peak
+const HLG_PEAK: f32 = 1000.0;
+
+/// PQ constants
+const PQ_M1: f32 = 2610.0 / 16384.0;
+const PQ_M2: f32 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f32 = 3424.0 / 4096.0;
+const PQ_C2: f32 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum TonemapCurve {
+    #[default]
+    Bt2390,
+    Hable,
+    Reinhard,
+    Clip,
+}
+
+/// Tonemapping of PQ/HLG frames for SDR displays
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TonemapOptions {
+    /// Only applies to PQ and HLG frames
+    pub enabled: bool,
+    pub curve: TonemapCurve,
+    /// Display peak in cd/m²
+    pub target_peak: f32,
+}
+
+impl Default for TonemapOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            curve: Default::default(),
+            target_peak: NOMINAL_LUMINANCE as f32,
+        }
+    }
+}
+
+impl TonemapOptions {
+    /// Tonemaps a frame converted to linear light by VS, encoded back to BT.1886 gamma.
+    /// Frames that weren't linearized are left as is.
+    /// The cached frame is replaced by a tonemapped copy, keeping the linear one cached.
+    pub fn apply(&self, vsframe: &mut Arc<VSFrame>, high_precision: bool) {
+        let Some(conv) = vsframe.conversion.filter(|c| c.linear_hdr) else {
+            return;
+        };
+        let DynamicImage::ImageRgb32F(linear) = &vsframe.image else {
+            return;
+        };
+
+        let src_peak = Self::source_peak(vsframe, conv.transfer);
+        let target_peak = self.target_peak.max(1.0);
+
+        let mut image = Rgb32FImage::new(linear.width(), linear.height());
+        image
+            .pixels_mut()
+            .zip(linear.pixels())
+            .for_each(|(out, px)| {
+                let rgb = px.0.map(|v| v.max(0.0) * NOMINAL_LUMINANCE as f32);
+
+                // Scale by the max component to preserve the hue
+                let max = rgb[0].max(rgb[1]).max(rgb[2]);
+                let scale = if max > 0.0 {
+                    self.map(max, src_peak, target_peak) / max
+                } else {
+                    0.0
+                };
+
+                out.0 = rgb.map(|v| (v * scale / target_peak).clamp(0.0, 1.0).powf(1.0 / 2.4));
+            });
+
+        let image = if high_precision {
+            DynamicImage::ImageRgb32F(image)
+        } else {
+            DynamicImage::ImageRgb8(DynamicImage::ImageRgb32F(image).to_rgb8())
+        };
+        *vsframe = Arc::new(vsframe.with_image(image));
+    }
+
+    /// MaxCLL, then the mastering display peak, then 1000 cd/m²
+    fn source_peak(vsframe: &VSFrame, transfer: VSTransferCharacteristics) -> f32 {
+        if transfer == VSTransferCharacteristics::STD_B67 {
+            return HLG_PEAK;
+        }
+
+        vsframe
+            .props
+            .hdr10_metadata
+            .and_then(|meta| {
+                meta.maxcll
+                    .filter(|cll| *cll > 0.0)
+                    .or(Some(meta.mastering_display.lum_max))
+            })
+            .filter(|peak| *peak > 0.0)
+            .map_or(1000.0, |peak| peak as f32)
+    }
+
+    /// Maps a luminance in cd/m² from the source peak to the target peak
+    fn map(&self, lum: f32, src_peak: f32, target_peak: f32) -> f32 {
+        if src_peak <= target_peak || self.curve == TonemapCurve::Clip {
+            return lum.min(target_peak);
+        }
+
+        match self.curve {
+            TonemapCurve::Bt2390 => bt2390(lum, src_peak, target_peak),
+            TonemapCurve::Hable => {
+                let x = lum / target_peak;
+                let peak = src_peak / target_peak;
+
+                hable(x) / hable(peak) * target_peak
+            }
+            TonemapCurve::Reinhard => {
+                // Extended Reinhard, the source peak maps to the target peak
+                let x = lum / target_peak;
+                let peak = src_peak / target_peak;
+
+                x * (1.0 + x / (peak * peak)) / (1.0 + x) * target_peak
+            }
+            TonemapCurve::Clip => unreachable!(),
+        }
+    }
+}
+
+/// Knee in the PQ domain, as in ITU-R BT.2390 EETF
+fn bt2390(lum: f32, src_peak: f32, target_peak: f32) -> f32 {
+    let src_pq = pq_oetf(src_peak);
+    let e1 = pq_oetf(lum) / src_pq;
+    let max_lum = pq_oetf(target_peak) / src_pq;
+
+    let ks = 1.5 * max_lum - 0.5;
+    let e2 = if e1 < ks {
+        e1
+    } else {
+        let t = (e1 - ks) / (1.0 - ks);
+        let (t2, t3) = (t * t, t * t * t);
+
+        (2.0 * t3 - 3.0 * t2 + 1.0) * ks
+            + (t3 - 2.0 * t2 + t) * (1.0 - ks)
+            + (-2.0 * t3 + 3.0 * t2) * max_lum
+    };
+
+    pq_eotf(e2 * src_pq).min(target_peak)
+}
+
+fn hable(x: f32) -> f32 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+/// cd/m² to PQ
+fn pq_oetf(lum: f32) -> f32 {
+    let y = (lum / 10000.0).clamp(0.0, 1.0).powf(PQ_M1);
+
+    ((PQ_C1 + PQ_C2 * y) / (1.0 + PQ_C3 * y)).powf(PQ_M2)
+}
+
+/// PQ to cd/m²
+fn pq_eotf(pq: f32) -> f32 {
+    let e = pq.clamp(0.0, 1.0).powf(1.0 / PQ_M2);
+    let y = ((e - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * e)).powf(1.0 / PQ_M1);
+
+    y * 10000.0
+}
+
+impl Display for TonemapCurve {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let val = match self {
+            TonemapCurve::Bt2390 => "BT.2390",
+            TonemapCurve::Hable => "Hable",
+            TonemapCurve::Reinhard => "Reinhard",
+            TonemapCurve::Clip => "Clip",
+        };
+
+        f.write_str(val)
+    }
+}
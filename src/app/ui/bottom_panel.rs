@@ -1,7 +1,21 @@
-use super::{VSPreviewer, egui, epaint::Color32, update_input_key_state};
+use super::{
+    PLAYBACK_SPEEDS, UiPropPlot, VSPreviewer, egui,
+    epaint::{Color32, Stroke},
+    update_input_key_state,
+};
 use anyhow::{Result, anyhow};
 use eframe::epaint::MarginF32;
 
+use crate::utils::format_timestamp;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Renders taking longer display their status
+const RENDERING_STATUS_DELAY: Duration = Duration::from_millis(200);
+
+const BOOKMARK_COLOR: Color32 = Color32::from_rgb(255, 200, 0);
+const PROP_MATCH_COLOR: Color32 = Color32::from_rgb(80, 200, 255);
+
 pub struct UiBottomPanel {}
 
 impl UiBottomPanel {
@@ -11,6 +25,17 @@ impl UiBottomPanel {
             .get_mut(&pv.state.cur_output)
             .ok_or_else(|| anyhow!("UiBottomPanel::ui: Invalid current output key"))?;
         let node_info = &output.vsoutput.node_info;
+        let rendering_frame = output.rendering_frame();
+
+        let bookmarks: Vec<u32> = pv.bookmarks.frames().collect();
+        let bookmarked = pv.bookmarks.contains(pv.state.cur_frame_no);
+        let mut toggle_bookmark = false;
+
+        let prop_matches = pv.prop_search.matches(pv.state.cur_output);
+        let mut start_prop_search = false;
+
+        let timestamp = output.frame_timestamp(pv.state.cur_frame_no);
+        let mut seek_target = None;
 
         let transparent_frame = egui::Frame::default()
             .fill(Color32::from_black_alpha(96))
@@ -35,26 +60,194 @@ impl UiBottomPanel {
                         .smart_aim(false)
                         .integer();
 
-                let slider_res = ui.add(frames_slider);
+                let (slider_res, seek_res, search_res) = ui
+                    .horizontal(|ui| {
+                        let playback = &mut pv.playback;
+
+                        let reverse_text = if playback.playing && playback.reverse {
+                            "⏸"
+                        } else {
+                            "⏴"
+                        };
+                        if ui
+                            .button(reverse_text)
+                            .on_hover_text("Play backwards (Shift+Space)")
+                            .clicked()
+                        {
+                            playback.toggle(true);
+                        }
+
+                        let play_text = if playback.playing && !playback.reverse {
+                            "⏸"
+                        } else {
+                            "⏵"
+                        };
+                        if ui.button(play_text).on_hover_text("Play (Space)").clicked() {
+                            playback.toggle(false);
+                        }
+
+                        egui::ComboBox::from_id_salt(egui::Id::new("playback_speed_select"))
+                            .width(60.0)
+                            .selected_text(format!("{}x", playback.speed))
+                            .show_ui(ui, |ui| {
+                                for speed in PLAYBACK_SPEEDS {
+                                    let selected = playback.speed == speed;
+
+                                    if ui.selectable_label(selected, format!("{speed}x")).clicked()
+                                    {
+                                        playback.set_speed(speed);
+                                    }
+                                }
+                            })
+                            .response
+                            .on_hover_text("Playback speed ([ and ])");
+
+                        let bookmark_text = if bookmarked { "★" } else { "☆" };
+                        toggle_bookmark = ui
+                            .button(bookmark_text)
+                            .on_hover_text("Toggle bookmark (B)")
+                            .clicked();
+
+                        let slider_res = ui.add(frames_slider);
+                        let num_frames = node_info.num_frames;
+                        Self::paint_ticks(ui, &slider_res, &prop_matches, num_frames, PROP_MATCH_COLOR);
+                        Self::paint_ticks(ui, &slider_res, &bookmarks, num_frames, BOOKMARK_COLOR);
+
+                        if let Some(nanos) = timestamp {
+                            ui.label(
+                                egui::RichText::new(format_timestamp(nanos, 3))
+                                    .monospace()
+                                    .color(Color32::from_gray(200)),
+                            );
+                        }
+
+                        let seek_edit = egui::TextEdit::singleline(&mut pv.seek_input)
+                            .desired_width(100.0)
+                            .hint_text("Frame or time");
+                        let seek_res = ui
+                            .add(seek_edit)
+                            .on_hover_text("Frame number or HH:MM:SS.mmm timecode, seeks on Enter");
+
+                        if seek_res.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            seek_target = Some(node_info.parse_seek_target(&pv.seek_input));
+                        }
+
+                        let search_edit = egui::TextEdit::singleline(&mut pv.prop_search.query)
+                            .desired_width(180.0)
+                            .hint_text("Props search");
+                        let search_res = ui.add(search_edit).on_hover_text(
+                            "Expression over the frame props, such as `_PictType == \"I\"`, scans on Enter",
+                        );
+
+                        if search_res.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            start_prop_search = true;
+                        }
+
+                        let search = &pv.prop_search;
+                        if let Some(data) = search.data.as_ref().filter(|_| search.scanning()) {
+                            if ui.small_button("✖").on_hover_text("Cancel the search").clicked() {
+                                search.cancel();
+                            }
+
+                            let progress = data.lock().progress();
+                            ui.spinner();
+                            ui.label(
+                                egui::RichText::new(format!("{:.0}%", progress * 100.0))
+                                    .color(Color32::from_gray(200)),
+                            );
+                        }
+
+                        if search.data.is_some() {
+                            ui.label(
+                                egui::RichText::new(format!("{} matches", prop_matches.len()))
+                                    .color(Color32::from_gray(200)),
+                            )
+                            .on_hover_text("Step through the matches with N and Shift+N");
+                        }
+
+                        if playback.dropped_frames > 0 {
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "Dropped {} frames",
+                                    playback.dropped_frames
+                                ))
+                                .color(Color32::from_rgb(255, 165, 0)),
+                            )
+                            .on_hover_text("The script can't render at the playback speed");
+                        }
+
+                        if let Some((frame_no, requested_at)) = rendering_frame {
+                            let elapsed = requested_at.elapsed();
+
+                            // Not shown for quick renders
+                            if elapsed >= RENDERING_STATUS_DELAY {
+                                ui.spinner();
+                                ui.label(
+                                    egui::RichText::new(format!("Rendering frame {frame_no}"))
+                                        .color(Color32::from_gray(200)),
+                                );
+                            } else {
+                                ctx.request_repaint_after(RENDERING_STATUS_DELAY - elapsed);
+                            }
+                        }
+
+                        (slider_res, seek_res, search_res)
+                    })
+                    .inner;
                 let in_use = slider_res.has_focus() || slider_res.drag_started();
-                let lost_focus = update_input_key_state(
+                update_input_key_state(&mut pv.inputs_focused, "frame_slider", in_use, &slider_res);
+                update_input_key_state(
+                    &mut pv.inputs_focused,
+                    "seek_input",
+                    seek_res.has_focus(),
+                    &seek_res,
+                );
+                update_input_key_state(
                     &mut pv.inputs_focused,
-                    "frame_slider",
-                    in_use,
-                    &slider_res,
+                    "prop_search",
+                    search_res.has_focus(),
+                    &search_res,
                 );
 
-                // Released/changed value
-                if lost_focus {
+                let graph_frame = UiPropPlot::graph(
+                    ui,
+                    &pv.prop_plot,
+                    pv.state.cur_output,
+                    pv.state.cur_frame_no,
+                );
+                if let Some(frame_no) = graph_frame.filter(|n| *n < node_info.num_frames) {
+                    slider_frame_no = frame_no;
+                }
+
+                if let Some(Ok(frame_no)) = seek_target {
+                    pv.seek_input.clear();
+                    slider_frame_no = frame_no;
+                }
+
+                // Stale renders are superseded, so the frame follows the slider
+                if slider_frame_no != pv.state.cur_frame_no {
                     output.last_frame_no = pv.state.cur_frame_no;
                     pv.state.cur_frame_no = slider_frame_no;
 
                     pv.rerender = true;
-                } else if slider_frame_no != pv.state.cur_frame_no {
-                    pv.state.cur_frame_no = slider_frame_no;
                 }
 
-                let output_info = format!("Output {} - {}", output.vsoutput.index, node_info);
+                if toggle_bookmark {
+                    pv.bookmarks.toggle(pv.state.cur_frame_no);
+                }
+
+                let mut output_info = format!("Output {} - {}", output.vsoutput.index, node_info);
+                if output.vsoutput.has_alpha {
+                    output_info.push_str(" | Alpha");
+                }
+
+                let viewed_plane = output
+                    .rendered_frame
+                    .as_ref()
+                    .and_then(|pf| pf.read().vsframe.plane);
+                if let Some(plane) = viewed_plane {
+                    write!(output_info, " | {} plane", plane.name).ok();
+                }
 
                 let node_info_label = egui::RichText::new(output_info)
                     .color(Color32::from_gray(200))
@@ -62,6 +255,46 @@ impl UiBottomPanel {
                 ui.label(node_info_label);
             });
 
+        if let Some(res @ Err(_)) = seek_target {
+            pv.add_error("preview", &res);
+        }
+
+        if start_prop_search {
+            pv.start_prop_search(ctx);
+        }
+
         Ok(())
     }
+
+    /// Ticks over the slider rail, one per pixel column
+    fn paint_ticks(
+        ui: &egui::Ui,
+        slider_res: &egui::Response,
+        frames: &[u32],
+        num_frames: u32,
+        color: Color32,
+    ) {
+        if frames.is_empty() {
+            return;
+        }
+
+        // Same as the slider handle inset
+        let rect = slider_res.rect;
+        let handle_radius = rect.height() / 2.5;
+        let left = rect.left() + handle_radius;
+        let width = ui.spacing().slider_width - 2.0 * handle_radius;
+        let last_frame = num_frames.saturating_sub(1).max(1) as f32;
+
+        let stroke = Stroke::new(2.0, color);
+        let mut last_x = None;
+
+        for frame_no in frames {
+            let x = (left + width * (*frame_no as f32 / last_frame)).round();
+
+            if last_x != Some(x) {
+                ui.painter().vline(x, rect.y_range(), stroke);
+                last_x = Some(x);
+            }
+        }
+    }
 }
@@ -1,6 +1,6 @@
 use super::{
-    MAX_ZOOM, MIN_ZOOM, STATE_LABEL_COLOR, VSPreviewer, egui, egui::RichText,
-    update_input_key_state,
+    BookmarkFormat, MAX_ZOOM, MIN_ZOOM, PreviewCompareMode, STATE_LABEL_COLOR, VSPreviewer, egui,
+    egui::RichText, update_input_key_state,
 };
 use anyhow::{Result, anyhow};
 use itertools::Itertools;
@@ -8,7 +8,7 @@ use itertools::Itertools;
 pub struct UiControls {}
 
 impl UiControls {
-    pub fn ui(pv: &mut VSPreviewer, ui: &mut egui::Ui) {
+    pub fn ui(pv: &mut VSPreviewer, ctx: &egui::Context, ui: &mut egui::Ui) {
         egui::Grid::new("controls_grid")
             .num_columns(2)
             .spacing([8.0, 4.0])
@@ -17,6 +17,13 @@ impl UiControls {
                 pv.add_error("preview", &res);
                 ui.end_row();
 
+                res = Self::compare_select_ui(pv, ui);
+                pv.add_error("preview", &res);
+                ui.end_row();
+
+                Self::bookmarks_ui(pv, ctx, ui);
+                ui.end_row();
+
                 res = Self::zoom_slider_ui(pv, ui);
                 pv.add_error("preview", &res);
                 ui.end_row();
@@ -59,6 +66,131 @@ impl UiControls {
         Ok(())
     }
 
+    pub fn bookmarks_ui(pv: &mut VSPreviewer, ctx: &egui::Context, ui: &mut egui::Ui) {
+        ui.label(RichText::new("Bookmarks").color(STATE_LABEL_COLOR));
+        ui.horizontal(|ui| {
+            ui.label(pv.bookmarks.len().to_string());
+
+            let format = &mut pv.state.bookmark_format;
+            egui::ComboBox::from_id_salt(egui::Id::new("bookmark_format_select"))
+                .selected_text(format.to_string())
+                .show_ui(ui, |ui| {
+                    for f in BookmarkFormat::ALL {
+                        ui.selectable_value(format, f, f.to_string());
+                    }
+                });
+
+            let idle = pv.bookmarks_promise.is_none();
+
+            let export_res = ui.add_enabled(idle, egui::Button::new("Export"));
+            if export_res.clicked() {
+                pv.export_bookmarks(ctx);
+            }
+
+            let import_res = ui
+                .add_enabled(idle, egui::Button::new("Import"))
+                .on_hover_text("Replaces the bookmarks, the format is detected");
+            if import_res.clicked() {
+                pv.import_bookmarks(ctx);
+            }
+        });
+    }
+
+    pub fn compare_select_ui(pv: &mut VSPreviewer, ui: &mut egui::Ui) -> Result<()> {
+        let old_mode = pv.state.compare_mode;
+        let old_output = pv.state.compare_output;
+        let new_mode = &mut pv.state.compare_mode;
+
+        ui.label(RichText::new("Compare").color(STATE_LABEL_COLOR));
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt(egui::Id::new("compare_mode_select"))
+                .selected_text(new_mode.to_string())
+                .show_ui(ui, |ui| {
+                    for mode in [
+                        PreviewCompareMode::Off,
+                        PreviewCompareMode::VerticalWipe,
+                        PreviewCompareMode::HorizontalWipe,
+                        PreviewCompareMode::SideBySide,
+                        PreviewCompareMode::Difference,
+                    ] {
+                        ui.selectable_value(new_mode, mode, mode.to_string());
+                    }
+                });
+
+            if new_mode.is_active() {
+                let compare_output = &mut pv.state.compare_output;
+
+                egui::ComboBox::from_id_salt(egui::Id::new("compare_output_select"))
+                    .selected_text(format!("Output {}", compare_output))
+                    .show_ui(ui, |ui| {
+                        for i in pv.outputs.keys().sorted() {
+                            ui.selectable_value(compare_output, *i, format!("Output {}", i));
+                        }
+                    });
+            }
+        });
+
+        let diff_mode = pv.state.compare_mode == PreviewCompareMode::Difference;
+        let old_diff = (
+            pv.state.diff_amplification,
+            pv.state.diff_highlight,
+            pv.state.diff_threshold,
+        );
+
+        if diff_mode {
+            ui.end_row();
+
+            let amp_dragval = egui::DragValue::new(&mut pv.state.diff_amplification)
+                .speed(0.1)
+                .range(1.0..=255.0)
+                .max_decimals(1);
+            ui.label(RichText::new("Amplification").color(STATE_LABEL_COLOR));
+            let res = ui.add(amp_dragval);
+            ui.end_row();
+
+            let in_use = res.has_focus() || res.drag_started();
+            update_input_key_state(&mut pv.inputs_focused, "diff_amp_dragval", in_use, &res);
+
+            ui.checkbox(&mut pv.state.diff_highlight, "Highlight above")
+                .on_hover_text("Difference threshold in 8 bit values");
+
+            let threshold_dragval = egui::DragValue::new(&mut pv.state.diff_threshold)
+                .speed(0.1)
+                .range(0.0..=255.0)
+                .max_decimals(1);
+            let res = ui.add_enabled(pv.state.diff_highlight, threshold_dragval);
+
+            let in_use = res.has_focus() || res.drag_started();
+            update_input_key_state(
+                &mut pv.inputs_focused,
+                "diff_threshold_dragval",
+                in_use,
+                &res,
+            );
+        }
+
+        let new_diff = (
+            pv.state.diff_amplification,
+            pv.state.diff_highlight,
+            pv.state.diff_threshold,
+        );
+
+        if pv.state.compare_mode != old_mode {
+            // Side by side halves the space available to each image
+            pv.reprocess_outputs(true, true);
+            pv.correct_translate_for_current_output(pv.state.translate, false)?;
+
+            // The difference replaces the current output's frame
+            if diff_mode || old_mode == PreviewCompareMode::Difference {
+                pv.rerender = true;
+            }
+        } else if diff_mode && (new_diff != old_diff || pv.state.compare_output != old_output) {
+            pv.rerender = true;
+        }
+
+        Ok(())
+    }
+
     pub fn zoom_slider_ui(pv: &mut VSPreviewer, ui: &mut egui::Ui) -> Result<()> {
         let old_zoom = pv.state.zoom_factor;
         let mut new_zoom = old_zoom;
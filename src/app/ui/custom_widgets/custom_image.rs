@@ -30,28 +30,35 @@ impl CustomImage {
 }
 
 impl CustomImage {
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
     pub fn paint_at(&self, ui: &mut Ui, rect: Rect) {
         if ui.is_rect_visible(rect) {
-            use epaint::*;
-            let Self {
-                texture_id,
-                uv,
-                tint,
-                ..
-            } = self;
-
-            {
-                // TODO: builder pattern for Mesh
-                let mut mesh = Mesh::with_texture(*texture_id);
-                mesh.add_rect_with_uv(rect, *uv, *tint);
-
-                let shape = Shape::mesh(mesh);
-                //shape.translate(*translate);
-
-                ui.painter().add(shape);
-            }
+            self.paint_with(ui.painter(), rect);
         }
     }
+
+    /// Paints with a specific painter, to clip the image
+    pub fn paint_with(&self, painter: &Painter, rect: Rect) {
+        use epaint::*;
+        let Self {
+            texture_id,
+            uv,
+            tint,
+            ..
+        } = self;
+
+        // TODO: builder pattern for Mesh
+        let mut mesh = Mesh::with_texture(*texture_id);
+        mesh.add_rect_with_uv(rect, *uv, *tint);
+
+        let shape = Shape::mesh(mesh);
+        //shape.translate(*translate);
+
+        painter.add(shape);
+    }
 }
 
 impl Widget for CustomImage {
@@ -0,0 +1,140 @@
+use super::egui;
+use eframe::egui::{Align2, Color32, FontId, Sense, Stroke, pos2, vec2};
+
+const GRAPH_COLOR: Color32 = Color32::from_rgb(120, 200, 255);
+const CURSOR_COLOR: Color32 = Color32::from_rgb(255, 200, 0);
+
+/// Values over a frame range, clicking seeks to the closest frame with a value
+pub struct FrameGraph<'a> {
+    pub label: &'a str,
+    pub start: u32,
+    /// Inclusive
+    pub end: u32,
+    /// Sorted by frame, `None` values break the line
+    pub values: &'a [(u32, Option<f64>)],
+    pub cur_frame_no: u32,
+}
+
+impl FrameGraph<'_> {
+    /// Returns the clicked frame
+    pub fn show(&self, ui: &mut egui::Ui, height: f32) -> Option<u32> {
+        let (rect, res) =
+            ui.allocate_exact_size(vec2(ui.available_width(), height), Sense::click());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, Color32::from_black_alpha(96));
+
+        let span = self.end.saturating_sub(self.start).max(1) as f32;
+        let frame_x = |frame_no: u32| {
+            rect.left() + rect.width() * (frame_no.saturating_sub(self.start) as f32 / span)
+        };
+
+        let label_font = FontId::monospace(11.0);
+        let label_color = Color32::from_gray(200);
+        painter.text(
+            rect.right_top() + vec2(-4.0, 2.0),
+            Align2::RIGHT_TOP,
+            self.label,
+            label_font.clone(),
+            label_color,
+        );
+
+        if let Some((min, max)) = self.min_max() {
+            let range = (max - min).max(f64::EPSILON);
+            let value_y = |v: f64| {
+                let norm = ((v - min) / range) as f32;
+                rect.bottom() - 4.0 - (rect.height() - 8.0) * norm
+            };
+
+            // Lines are broken by the frames without a value
+            let mut line = Vec::new();
+            for (frame_no, value) in self.values {
+                match value.filter(|v| v.is_finite()) {
+                    Some(v) => line.push(pos2(frame_x(*frame_no), value_y(v))),
+                    None => paint_line(&painter, std::mem::take(&mut line)),
+                }
+            }
+            paint_line(&painter, line);
+
+            painter.text(
+                rect.left_top() + vec2(4.0, 2.0),
+                Align2::LEFT_TOP,
+                format!("{max:.4}"),
+                label_font.clone(),
+                label_color,
+            );
+            painter.text(
+                rect.left_bottom() + vec2(4.0, -2.0),
+                Align2::LEFT_BOTTOM,
+                format!("{min:.4}"),
+                label_font,
+                label_color,
+            );
+        }
+
+        if (self.start..=self.end).contains(&self.cur_frame_no) {
+            painter.vline(
+                frame_x(self.cur_frame_no),
+                rect.y_range(),
+                Stroke::new(1.0, CURSOR_COLOR),
+            );
+        }
+
+        let hovered = res
+            .hover_pos()
+            .map(|pos| {
+                let norm = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+                self.start + (norm * span).round() as u32
+            })
+            .and_then(|frame_no| self.closest(frame_no));
+
+        let (frame_no, value) = hovered?;
+        painter.vline(
+            frame_x(frame_no),
+            rect.y_range(),
+            Stroke::new(1.0, Color32::from_white_alpha(96)),
+        );
+
+        let value = value.map_or_else(|| "none".to_string(), |v| v.to_string());
+        let clicked = res
+            .on_hover_text(format!("Frame {frame_no}: {value}"))
+            .clicked();
+
+        clicked.then_some(frame_no)
+    }
+
+    fn min_max(&self) -> Option<(f64, f64)> {
+        self.values
+            .iter()
+            .filter_map(|(_, v)| v.filter(|v| v.is_finite()))
+            .fold(None, |acc, v| match acc {
+                None => Some((v, v)),
+                Some((min, max)) => Some((min.min(v), max.max(v))),
+            })
+    }
+
+    /// Value closest to the frame
+    fn closest(&self, frame_no: u32) -> Option<(u32, Option<f64>)> {
+        let idx = self.values.partition_point(|(n, _)| *n < frame_no);
+
+        let after = self.values.get(idx);
+        let before = idx.checked_sub(1).and_then(|i| self.values.get(i));
+
+        match (before, after) {
+            (Some(b), Some(a)) if frame_no - b.0 < a.0 - frame_no => Some(*b),
+            (_, Some(a)) => Some(*a),
+            (b, None) => b.copied(),
+        }
+    }
+}
+
+fn paint_line(painter: &egui::Painter, line: Vec<egui::Pos2>) {
+    match line.len() {
+        0 => (),
+        1 => {
+            painter.circle_filled(line[0], 1.5, GRAPH_COLOR);
+        }
+        _ => {
+            painter.add(egui::Shape::line(line, Stroke::new(1.5, GRAPH_COLOR)));
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use super::{STATE_LABEL_COLOR, VSPreviewer, egui, egui::RichText};
+use super::{
+    PropPlot, STATE_LABEL_COLOR, UiPropPlot, VSPreviewer, egui, egui::RichText,
+    update_input_key_state,
+};
+use crate::vs_handler::vsprops::VSProp;
 use anyhow::{Result, anyhow};
 
 pub struct UiFrameProps {}
@@ -63,6 +67,23 @@ impl UiFrameProps {
                             ui.end_row();
                         }
 
+                        if let Some((num, den)) = props.duration {
+                            let ms =
+                                egui::emath::round_to_decimals(num as f64 / den as f64 * 1000.0, 3);
+
+                            ui.label(RichText::new("Duration").color(STATE_LABEL_COLOR));
+                            ui.label(format!("{num}/{den} ({ms} ms)"));
+                            ui.end_row();
+                        }
+
+                        if let Some(secs) = props.absolute_time {
+                            let nanos = (secs * 1e9).round() as u128;
+
+                            ui.label(RichText::new("Absolute time").color(STATE_LABEL_COLOR));
+                            ui.label(crate::utils::format_timestamp(nanos, 3));
+                            ui.end_row();
+                        }
+
                         if let Some(hdr10_meta) = props.hdr10_metadata {
                             ui.label(RichText::new("Mastering display").color(STATE_LABEL_COLOR));
 
@@ -136,6 +157,107 @@ impl UiFrameProps {
             });
         }
 
+        Self::all_props_ui(pv, ui);
+
         Ok(())
     }
+
+    /// Every prop of the rendered frame, pinned keys first
+    fn all_props_ui(pv: &mut VSPreviewer, ui: &mut egui::Ui) {
+        let Some(props_map) = pv
+            .outputs
+            .get(&pv.state.cur_output)
+            .and_then(|output| output.rendered_frame.as_ref())
+            .map(|pf| pf.read().vsframe.props_map.clone())
+        else {
+            return;
+        };
+
+        let header = RichText::new("All props").color(STATE_LABEL_COLOR);
+
+        egui::CollapsingHeader::new(header).show(ui, |ui| {
+            let search_edit = egui::TextEdit::singleline(&mut pv.props_search)
+                .desired_width(f32::INFINITY)
+                .hint_text("Search keys");
+            let search_res = ui.add(search_edit);
+            update_input_key_state(
+                &mut pv.inputs_focused,
+                "props_search",
+                search_res.has_focus(),
+                &search_res,
+            );
+
+            let search = pv.props_search.to_lowercase();
+            let mut props: Vec<&VSProp> = props_map
+                .props
+                .iter()
+                .filter(|prop| prop.key.to_lowercase().contains(&search))
+                .collect();
+            // Stable, keeps the map's order otherwise
+            props.sort_by_key(|prop| !pv.pinned_props.contains(&prop.key));
+
+            if props.is_empty() {
+                ui.label("No props");
+                return;
+            }
+
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .auto_shrink([false, true])
+                .show(ui, |ui| {
+                    for prop in props {
+                        Self::prop_row(&mut pv.pinned_props, &mut pv.prop_plot, ui, prop);
+                    }
+                });
+        });
+    }
+
+    fn prop_row(
+        pinned_props: &mut Vec<String>,
+        prop_plot: &mut PropPlot,
+        ui: &mut egui::Ui,
+        prop: &VSProp,
+    ) {
+        let pinned = pinned_props.contains(&prop.key);
+
+        ui.horizontal(|ui| {
+            let pin_text = if pinned { "📌" } else { "📍" };
+            let pin_res = ui
+                .selectable_label(pinned, pin_text)
+                .on_hover_text(if pinned { "Unpin" } else { "Pin to the top" });
+
+            if pin_res.clicked() {
+                if pinned {
+                    pinned_props.retain(|key| key != &prop.key);
+                } else {
+                    pinned_props.push(prop.key.clone());
+                }
+            }
+
+            let key_res = ui
+                .add(
+                    egui::Label::new(RichText::new(&prop.key).color(STATE_LABEL_COLOR))
+                        .sense(egui::Sense::click()),
+                )
+                .on_hover_text("Click to copy");
+            if key_res.clicked() {
+                ui.ctx().copy_text(prop.to_text());
+            }
+
+            ui.label(RichText::new(prop.value.type_name()).weak());
+            UiPropPlot::plot_button(ui, prop_plot, &prop.key, &prop.value);
+
+            if prop.value.len() > 1 {
+                egui::CollapsingHeader::new(prop.value.to_string())
+                    .id_salt(("prop_items", &prop.key))
+                    .show(ui, |ui| {
+                        for (i, item) in prop.value.items().iter().enumerate() {
+                            ui.label(format!("[{i}] {item}"));
+                        }
+                    });
+            } else {
+                ui.add(egui::Label::new(prop.value.to_string()).wrap());
+            }
+        });
+    }
 }
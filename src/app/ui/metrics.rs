@@ -0,0 +1,273 @@
+use super::{
+    FrameGraph, MetricKind, STATE_LABEL_COLOR, VSPreviewer, egui, egui::RichText,
+    update_input_key_state,
+};
+
+const GRAPH_HEIGHT: f32 = 100.0;
+const TABLE_HEIGHT: f32 = 200.0;
+const TABLE_COLUMN_WIDTH: f32 = 70.0;
+
+pub struct UiMetrics {}
+
+impl UiMetrics {
+    pub fn ui(pv: &mut VSPreviewer, ctx: &egui::Context) {
+        if !pv.metrics.show {
+            return;
+        }
+
+        let mut outputs: Vec<(i32, u32)> = pv
+            .outputs
+            .iter()
+            .map(|(i, o)| (*i, o.vsoutput.node_info.num_frames))
+            .collect();
+        outputs.sort();
+
+        let metrics = &mut pv.metrics;
+        let num_frames = |output| {
+            outputs
+                .iter()
+                .find(|(i, _)| *i == output)
+                .map(|(_, num_frames)| *num_frames)
+        };
+
+        // Defaults to the compared outputs
+        if num_frames(metrics.reference).is_none() {
+            metrics.reference = pv.state.compare_output;
+        }
+        if num_frames(metrics.distorted).is_none() {
+            metrics.distorted = pv.state.cur_output;
+        }
+
+        let last_frame = num_frames(metrics.reference)
+            .zip(num_frames(metrics.distorted))
+            .map_or(0, |(a, b)| a.min(b).saturating_sub(1));
+        let cur_frame_no = pv.state.cur_frame_no;
+
+        let mut open = true;
+        let mut start_metrics = false;
+        let mut seek_frame = None;
+
+        egui::Window::new("Metrics")
+            .open(&mut open)
+            .default_width(420.0)
+            .resizable(true)
+            .show(ctx, |ui| {
+                metrics.end = metrics.end.min(last_frame);
+                metrics.start = metrics.start.min(metrics.end);
+
+                egui::Grid::new("metrics_grid")
+                    .num_columns(2)
+                    .spacing([8.0, 4.0])
+                    .show(ui, |ui| {
+                        for (name, output) in [
+                            ("Reference", &mut metrics.reference),
+                            ("Distorted", &mut metrics.distorted),
+                        ] {
+                            ui.label(RichText::new(name).color(STATE_LABEL_COLOR));
+                            egui::ComboBox::from_id_salt(egui::Id::new(("metrics_output", name)))
+                                .selected_text(format!("Output {output}"))
+                                .show_ui(ui, |ui| {
+                                    for (i, _) in &outputs {
+                                        ui.selectable_value(output, *i, format!("Output {i}"));
+                                    }
+                                });
+                            ui.end_row();
+                        }
+
+                        ui.label(RichText::new("Frames").color(STATE_LABEL_COLOR));
+                        ui.horizontal(|ui| {
+                            let end = metrics.end;
+                            let start_res =
+                                ui.add(egui::DragValue::new(&mut metrics.start).range(0..=end));
+                            ui.label("to");
+                            let end_res = ui
+                                .add(egui::DragValue::new(&mut metrics.end).range(0..=last_frame));
+
+                            for (name, res) in
+                                [("metrics_start", start_res), ("metrics_end", end_res)]
+                            {
+                                let in_use = res.has_focus() || res.drag_started();
+                                update_input_key_state(&mut pv.inputs_focused, name, in_use, &res);
+                            }
+
+                            if ui.button("Current").clicked() {
+                                metrics.start = cur_frame_no.min(last_frame);
+                                metrics.end = metrics.start;
+                            }
+                            if ui.button("All").clicked() {
+                                metrics.start = 0;
+                                metrics.end = last_frame;
+                            }
+                        });
+                        ui.end_row();
+                    });
+
+                ui.horizontal(|ui| {
+                    if let Some(data) = metrics.data.as_ref().filter(|_| metrics.scanning()) {
+                        if ui.button("Cancel").clicked() {
+                            metrics.cancel();
+                        }
+
+                        let progress = data.lock().progress();
+                        ui.add(egui::ProgressBar::new(progress).show_percentage());
+                    } else {
+                        start_metrics = ui
+                            .button("Compute")
+                            .on_hover_text("PSNR per plane and SSIM of the distorted output")
+                            .clicked();
+                    }
+                });
+
+                let Some(data) = &metrics.data else {
+                    return;
+                };
+                let data = data.lock();
+                if data.frames.is_empty() {
+                    return;
+                }
+
+                ui.separator();
+
+                let kinds = data.kinds();
+                if !kinds.contains(&metrics.metric) {
+                    metrics.metric = kinds[0];
+                }
+
+                egui::Grid::new("metrics_mean_grid")
+                    .num_columns(2)
+                    .spacing([8.0, 2.0])
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Frames").color(STATE_LABEL_COLOR));
+                        ui.label(format!(
+                            "{}, output {} against output {}",
+                            data.frames.len(),
+                            data.distorted,
+                            data.reference
+                        ));
+                        ui.end_row();
+
+                        for kind in &kinds {
+                            ui.label(
+                                RichText::new(data.metric_name(*kind)).color(STATE_LABEL_COLOR),
+                            );
+                            ui.label(data.mean(*kind).map_or("inf".to_string(), |v| {
+                                format!("{} mean", format_metric(*kind, v))
+                            }));
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Metric").color(STATE_LABEL_COLOR));
+                    egui::ComboBox::from_id_salt(egui::Id::new("metrics_kind_select"))
+                        .selected_text(data.metric_name(metrics.metric))
+                        .show_ui(ui, |ui| {
+                            for kind in &kinds {
+                                ui.selectable_value(
+                                    &mut metrics.metric,
+                                    *kind,
+                                    data.metric_name(*kind),
+                                );
+                            }
+                        });
+                });
+
+                let values = data.values(metrics.metric);
+                let graph = FrameGraph {
+                    label: &data.metric_name(metrics.metric),
+                    start: data.start,
+                    end: data.end,
+                    values: &values,
+                    cur_frame_no,
+                };
+                if let Some(frame_no) = graph.show(ui, GRAPH_HEIGHT) {
+                    seek_frame = Some(frame_no);
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Worst frames").color(STATE_LABEL_COLOR));
+                    ui.add(egui::DragValue::new(&mut metrics.worst_count).range(1..=100));
+                });
+                ui.horizontal_wrapped(|ui| {
+                    for frame in data.worst(metrics.metric, metrics.worst_count) {
+                        let value = frame.get(metrics.metric).unwrap_or_default();
+                        let text = format!(
+                            "{} ({})",
+                            frame.frame_no,
+                            format_metric(metrics.metric, value)
+                        );
+
+                        if ui
+                            .small_button(text)
+                            .on_hover_text("Seek to the frame")
+                            .clicked()
+                        {
+                            seek_frame = Some(frame.frame_no);
+                        }
+                    }
+                });
+
+                ui.separator();
+
+                // Per frame table
+                ui.horizontal(|ui| {
+                    let mut header = |text: String| {
+                        ui.add_sized(
+                            [TABLE_COLUMN_WIDTH, 0.0],
+                            egui::Label::new(RichText::new(text).color(STATE_LABEL_COLOR)),
+                        );
+                    };
+
+                    header("Frame".to_string());
+                    for kind in &kinds {
+                        header(data.metric_name(*kind));
+                    }
+                });
+
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::vertical()
+                    .max_height(TABLE_HEIGHT)
+                    .auto_shrink([false, true])
+                    .show_rows(ui, row_height, data.frames.len(), |ui, rows| {
+                        for frame in &data.frames[rows] {
+                            ui.horizontal(|ui| {
+                                let frame_res = ui.add_sized(
+                                    [TABLE_COLUMN_WIDTH, row_height],
+                                    egui::Link::new(frame.frame_no.to_string()),
+                                );
+                                if frame_res.clicked() {
+                                    seek_frame = Some(frame.frame_no);
+                                }
+
+                                for kind in &kinds {
+                                    let value = frame.get(*kind).unwrap_or_default();
+                                    ui.add_sized(
+                                        [TABLE_COLUMN_WIDTH, row_height],
+                                        egui::Label::new(format_metric(*kind, value)),
+                                    );
+                                }
+                            });
+                        }
+                    });
+            });
+
+        pv.metrics.show = open;
+
+        if start_metrics {
+            pv.start_metrics(ctx);
+        }
+        if let Some(frame_no) = seek_frame {
+            pv.seek_to(frame_no);
+        }
+    }
+}
+
+fn format_metric(kind: MetricKind, value: f64) -> String {
+    match kind {
+        MetricKind::Psnr(_) if value.is_infinite() => "inf".to_string(),
+        MetricKind::Psnr(_) => format!("{value:.3}"),
+        MetricKind::Ssim => format!("{value:.5}"),
+    }
+}
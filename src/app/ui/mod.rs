@@ -1,4 +1,8 @@
-use super::{MAX_ZOOM, MIN_ZOOM, PreviewFilterType, VSPreviewer, update_input_key_state};
+use super::{
+    BookmarkFormat, InspectedPixel, MAX_ZOOM, MIN_ZOOM, MetricKind, PLAYBACK_SPEEDS,
+    PixelInspector, PreviewCompareMode, PreviewFilterType, PropPlot, PropPlotFormat,
+    REGION_ALIGNMENTS, VSPlaneView, VSPreviewer, update_input_key_state,
+};
 use anyhow::Result;
 use eframe::{
     egui::{self, Layout, RichText},
@@ -8,20 +12,32 @@ use eframe::{
 
 mod bottom_panel;
 mod controls;
+mod frame_graph;
 mod frame_props;
 mod message_window;
+mod metrics;
+mod pixel_inspector;
 mod preferences;
 mod preview_image;
+mod prop_plot;
+mod region_selection;
+mod scopes;
 mod state_window;
 
 mod custom_widgets;
 
 pub use bottom_panel::UiBottomPanel;
 use controls::UiControls;
+use frame_graph::FrameGraph;
 use frame_props::UiFrameProps;
 pub use message_window::MessageWindowUi;
+use metrics::UiMetrics;
+use pixel_inspector::UiPixelInspector;
 use preferences::UiPreferences;
 pub use preview_image::UiPreviewImage;
+use prop_plot::UiPropPlot;
+use region_selection::UiRegionSelection;
+pub use scopes::UiScopes;
 pub use state_window::UiStateWindow;
 
 const STATE_LABEL_COLOR: epaint::Color32 = epaint::Color32::from_gray(160);
@@ -40,6 +56,15 @@ impl PreviewerMainUi {
 
         // Centered image painted on
         let canvas_res = UiPreviewImage::ui(pv, ui)?;
+
+        if pv.state.pixel_inspector {
+            UiPixelInspector::ui(pv, ctx);
+        }
+
+        UiRegionSelection::ui(pv, ctx);
+        UiPropPlot::ui(pv, ctx);
+        UiMetrics::ui(pv, ctx);
+
         canvas_res.context_menu(|ui| {
             let change_script_text = RichText::new("Open script file")
                 .size(18.0)
@@ -58,6 +83,25 @@ impl PreviewerMainUi {
             }
         });
 
+        // Reload failed, the previous script is still previewed
+        if pv.stale_script && has_current_output {
+            egui::Area::new(egui::Id::new("stale_script_area"))
+                .anchor(Align2::RIGHT_TOP, Vec2::new(-10.0, 10.0))
+                .show(ctx, |ui| {
+                    egui::Frame::default()
+                        .fill(epaint::Color32::from_black_alpha(192))
+                        .inner_margin(epaint::MarginF32::same(6.0))
+                        .show(ui, |ui| {
+                            let stale_text = RichText::new("⚠ Showing stale script")
+                                .size(18.0)
+                                .color(epaint::Color32::from_rgb(255, 165, 0));
+
+                            ui.label(stale_text)
+                                .on_hover_text("Reloading failed, see the messages window");
+                        });
+                });
+        }
+
         // Bottom panel
         if pv.state.show_gui && has_current_output {
             UiBottomPanel::ui(pv, ctx)?;
@@ -0,0 +1,85 @@
+use super::{
+    InspectedPixel, PixelInspector, STATE_LABEL_COLOR, VSPreviewer, egui, egui::RichText, epaint,
+};
+
+pub struct UiPixelInspector {}
+
+impl UiPixelInspector {
+    /// Readout next to the pointer, for the hovered pixel
+    pub fn ui(pv: &VSPreviewer, ctx: &egui::Context) {
+        let inspector = &pv.pixel_inspector;
+
+        let (Some(pixel), Some(pos)) = (inspector.pixel, ctx.pointer_hover_pos()) else {
+            return;
+        };
+
+        egui::Area::new(egui::Id::new("pixel_inspector_area"))
+            .fixed_pos(pos + epaint::Vec2::splat(16.0))
+            .order(egui::Order::Tooltip)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::default()
+                    .fill(epaint::Color32::from_black_alpha(192))
+                    .inner_margin(epaint::MarginF32::same(6.0))
+                    .show(ui, |ui| {
+                        egui::Grid::new("pixel_inspector_grid")
+                            .num_columns(2)
+                            .spacing([8.0, 2.0])
+                            .show(ui, |ui| Self::values_grid(inspector, pixel, ui));
+
+                        let hint = RichText::new("Click to copy").small();
+                        ui.label(hint.color(STATE_LABEL_COLOR));
+                    });
+            });
+    }
+
+    fn values_grid(inspector: &PixelInspector, pixel: InspectedPixel, ui: &mut egui::Ui) {
+        ui.label(RichText::new("Pixel").color(STATE_LABEL_COLOR));
+        ui.label(format!(
+            "{}, {} (output {})",
+            pixel.x, pixel.y, pixel.output
+        ));
+        ui.end_row();
+
+        if let Some(values) = inspector.hovered_values() {
+            ui.label(RichText::new("Format").color(STATE_LABEL_COLOR));
+            ui.label(&values.format_name);
+            ui.end_row();
+
+            for plane in &values.planes {
+                ui.label(RichText::new(plane.name).color(STATE_LABEL_COLOR));
+
+                // Subsampled chroma is read at another position
+                let value = if plane.x as u32 != pixel.x || plane.y as u32 != pixel.y {
+                    format!("{} at {}, {}", plane.value, plane.x, plane.y)
+                } else {
+                    plane.value.to_string()
+                };
+                ui.label(RichText::new(value).monospace());
+                ui.end_row();
+            }
+
+            if let Some(chroma_location) = values.chroma_location {
+                ui.label(RichText::new("Chroma location").color(STATE_LABEL_COLOR));
+                ui.label(chroma_location.to_string());
+                ui.end_row();
+            }
+
+            if let Some(alpha) = values.alpha {
+                ui.label(RichText::new("Alpha").color(STATE_LABEL_COLOR));
+                ui.label(RichText::new(alpha.to_string()).monospace());
+                ui.end_row();
+            }
+        } else if inspector.reading() {
+            ui.label(RichText::new("Source").color(STATE_LABEL_COLOR));
+            ui.spinner();
+            ui.end_row();
+        }
+
+        if let Some(rgb) = &inspector.displayed_rgb {
+            ui.label(RichText::new("Displayed").color(STATE_LABEL_COLOR));
+            ui.label(RichText::new(rgb).monospace());
+            ui.end_row();
+        }
+    }
+}
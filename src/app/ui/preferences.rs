@@ -3,10 +3,55 @@ use super::{
 };
 
 use crate::{
-    app::preview_filter_type::PreviewTextureFilterType,
-    vs_handler::{VSDitherAlgo, VSResizer},
+    app::{
+        PreviewAlphaBackground, preview_filter_type::PreviewTextureFilterType,
+        transforms::tonemap::TonemapCurve,
+    },
+    vs_handler::{
+        VSColorConversion, VSColorOverrides, VSDitherAlgo, VSPlaneView, VSResizer,
+        zimg_map::{VSChromaLocation, VSColorRange, VSMatrix, VSPrimaries},
+    },
 };
 
+const MATRIX_CHOICES: [VSMatrix; 11] = [
+    VSMatrix::BT709,
+    VSMatrix::BT470bg,
+    VSMatrix::ST170M,
+    VSMatrix::ST240M,
+    VSMatrix::Fcc,
+    VSMatrix::YCgCo,
+    VSMatrix::BT2020Ncl,
+    VSMatrix::BT2020cl,
+    VSMatrix::ChromaNcl,
+    VSMatrix::Chromacl,
+    VSMatrix::ICtCp,
+];
+
+const RANGE_CHOICES: [VSColorRange; 2] = [VSColorRange::Limited, VSColorRange::Full];
+
+const CHROMALOC_CHOICES: [VSChromaLocation; 6] = [
+    VSChromaLocation::Left,
+    VSChromaLocation::Center,
+    VSChromaLocation::TopLeft,
+    VSChromaLocation::Top,
+    VSChromaLocation::BottomLeft,
+    VSChromaLocation::Bottom,
+];
+
+const PRIMARIES_CHOICES: [VSPrimaries; 11] = [
+    VSPrimaries::BT709,
+    VSPrimaries::BT470m,
+    VSPrimaries::BT470bg,
+    VSPrimaries::ST170M,
+    VSPrimaries::ST240M,
+    VSPrimaries::Film,
+    VSPrimaries::BT2020,
+    VSPrimaries::Xyz,
+    VSPrimaries::DCIP3,
+    VSPrimaries::DCIP3_D65,
+    VSPrimaries::JEDEC_P22,
+];
+
 pub struct UiPreferences {}
 
 impl UiPreferences {
@@ -15,6 +60,7 @@ impl UiPreferences {
 
         egui::CollapsingHeader::new(header).show(ui, |ui| {
             Self::pref_grid(pv, ui);
+            Self::color_conversion_ui(pv, ui);
             Self::transforms_ui(pv, ui, ctx);
         });
     }
@@ -23,11 +69,20 @@ impl UiPreferences {
         let old_vs_resizer = pv.state.frame_transform_opts.resizer;
         let old_enable_dithering = pv.state.frame_transform_opts.enable_dithering;
         let old_dither_algo = pv.state.frame_transform_opts.dither_algo;
+        let old_high_precision = pv.state.frame_transform_opts.high_precision;
+        let old_plane_view = pv.state.frame_transform_opts.plane_view;
+        let old_upscale_planes = pv.state.frame_transform_opts.upscale_planes;
+        let old_stretch_planes = pv.state.frame_transform_opts.stretch_planes;
 
         let old_texture_filter = pv.state.texture_filter;
         let old_upscale_flag = pv.state.upscale_to_window;
         let old_upsampling_filter = pv.state.upsampling_filter;
         let old_fit_window_flag = pv.state.fit_to_window;
+        let old_auto_reload_flag = pv.state.auto_reload;
+
+        let old_alpha_background = pv.state.alpha_background;
+        let old_alpha_color = pv.state.alpha_color;
+        let old_alpha_mask_flag = pv.state.alpha_mask_only;
 
         egui::Grid::new("prefs_grid")
             .num_columns(2)
@@ -70,10 +125,35 @@ impl UiPreferences {
                 }
                 ui.end_row();
 
+                ui.checkbox(
+                    &mut pv.state.frame_transform_opts.high_precision,
+                    "High precision",
+                )
+                .on_hover_text("Render to 16 bit or float RGB, only reduced to 8 bit for display");
+                ui.end_row();
+
+                Self::plane_view_ui(pv, ui);
+                ui.end_row();
+
                 ui.checkbox(&mut pv.state.upscale_to_window, "Upscale image to window");
                 ui.checkbox(&mut pv.state.fit_to_window, "Fit image to window");
                 ui.end_row();
 
+                ui.add_enabled_ui(!pv.auto_reload_disabled, |ui| {
+                    ui.checkbox(&mut pv.state.auto_reload, "Reload script on change")
+                        .on_hover_text("Watches the script and its local imports")
+                        .on_disabled_hover_text("Disabled from the command line");
+                });
+                ui.checkbox(&mut pv.state.pixel_inspector, "Pixel inspector")
+                    .on_hover_text("Shows the source values of the hovered pixel, click to copy");
+                ui.end_row();
+
+                ui.checkbox(&mut pv.state.scopes.show, "Scopes")
+                    .on_hover_text(
+                        "Waveform, parade, vectorscope and histogram of the current frame",
+                    );
+                ui.end_row();
+
                 if pv.state.upscale_to_window || pv.state.fit_to_window {
                     let new_texture_filter = &mut pv.state.texture_filter;
 
@@ -143,6 +223,34 @@ impl UiPreferences {
                     ui.end_row();
                 }
 
+                let new_alpha_background = &mut pv.state.alpha_background;
+
+                ui.label(RichText::new("Alpha background").color(STATE_LABEL_COLOR));
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt(egui::Id::new("alpha_background_select"))
+                        .selected_text(new_alpha_background.to_string())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                new_alpha_background,
+                                PreviewAlphaBackground::Checkerboard,
+                                PreviewAlphaBackground::Checkerboard.to_string(),
+                            );
+                            ui.selectable_value(
+                                new_alpha_background,
+                                PreviewAlphaBackground::SolidColor,
+                                PreviewAlphaBackground::SolidColor.to_string(),
+                            );
+                        });
+
+                    if *new_alpha_background == PreviewAlphaBackground::SolidColor {
+                        ui.color_edit_button_srgb(&mut pv.state.alpha_color);
+                    }
+                });
+                ui.end_row();
+
+                ui.checkbox(&mut pv.state.alpha_mask_only, "Show alpha mask only");
+                ui.end_row();
+
                 let zoom_mult_dragval = egui::DragValue::new(&mut pv.state.zoom_multiplier)
                     .speed(0.01)
                     .range(1.0..=2.0)
@@ -184,6 +292,18 @@ impl UiPreferences {
                 if lost_focus {
                     pv.reprocess_outputs(true, false);
                 }
+
+                let frame_cache_dragval = egui::DragValue::new(&mut pv.state.frame_cache_size)
+                    .speed(16)
+                    .range(0..=65536)
+                    .suffix(" MiB");
+                ui.label(RichText::new("Frame cache").color(STATE_LABEL_COLOR))
+                    .on_hover_text("Memory used to keep rendered frames, 0 disables the cache");
+                let res = ui.add(frame_cache_dragval);
+                ui.end_row();
+
+                let in_use = res.has_focus() || res.drag_started();
+                update_input_key_state(&mut pv.inputs_focused, "frame_cache_dragval", in_use, &res);
             });
 
         let ft = pv.state.frame_transform_opts;
@@ -192,15 +312,184 @@ impl UiPreferences {
         if ft.resizer != old_vs_resizer
             || ft.enable_dithering != old_enable_dithering
             || ft.dither_algo != old_dither_algo
+            || ft.high_precision != old_high_precision
+            || ft.plane_view != old_plane_view
+            || ft.upscale_planes != old_upscale_planes
+            || ft.stretch_planes != old_stretch_planes
         {
             pv.rerender = true;
         } else if pv.state.upscale_to_window != old_upscale_flag
             || pv.state.upsampling_filter != old_upsampling_filter
             || pv.state.fit_to_window != old_fit_window_flag
             || pv.state.texture_filter != old_texture_filter
+            || pv.state.alpha_background != old_alpha_background
+            || pv.state.alpha_color != old_alpha_color
+            || pv.state.alpha_mask_only != old_alpha_mask_flag
         {
             pv.reprocess_outputs(true, false);
         }
+
+        if pv.state.auto_reload != old_auto_reload_flag {
+            pv.update_script_watcher(ui.ctx());
+        }
+    }
+
+    /// Planes of the current output, viewed without RGB conversion
+    fn plane_view_ui(pv: &mut VSPreviewer, ui: &mut egui::Ui) {
+        let Some(output) = pv.outputs.get(&pv.state.cur_output) else {
+            return;
+        };
+
+        let plane_names = &output.vsoutput.node_info.plane_names;
+        let opts = &mut pv.state.frame_transform_opts;
+
+        let mut views: Vec<VSPlaneView> = std::iter::once(VSPlaneView::Off)
+            .chain((0..plane_names.len()).map(VSPlaneView::Plane))
+            .collect();
+        if output.vsoutput.has_alpha {
+            views.push(VSPlaneView::Alpha);
+        }
+
+        ui.label(RichText::new("Plane view").color(STATE_LABEL_COLOR));
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt(egui::Id::new("plane_view_select"))
+                .selected_text(opts.plane_view.name(plane_names).unwrap_or("Off"))
+                .show_ui(ui, |ui| {
+                    for view in views {
+                        let name = view.name(plane_names).unwrap_or("Off");
+                        ui.selectable_value(&mut opts.plane_view, view, name);
+                    }
+                })
+                .response
+                .on_hover_text("Shows a plane of the original clip as greyscale");
+
+            ui.checkbox(&mut opts.upscale_planes, "Upscale")
+                .on_hover_text("Point upscales subsampled planes to the clip resolution");
+            ui.checkbox(&mut opts.stretch_planes, "Stretch")
+                .on_hover_text("Stretches the plane's values to its minimum and maximum");
+        });
+    }
+
+    fn color_conversion_ui(pv: &mut VSPreviewer, ui: &mut egui::Ui) {
+        let Some(output) = pv.outputs.get_mut(&pv.state.cur_output) else {
+            return;
+        };
+
+        // Values used for the last rendered frame
+        let conversion = output
+            .rendered_frame
+            .as_ref()
+            .and_then(|pf| pf.read().vsframe.conversion);
+
+        let header = RichText::new(format!("Color conversion (output {})", pv.state.cur_output))
+            .color(STATE_LABEL_COLOR);
+
+        let old_overrides = output.color_overrides;
+
+        egui::CollapsingHeader::new(header).show(ui, |ui| {
+            let Some(conv) = conversion else {
+                ui.label("Not converted, greyscale output or plane view");
+                return;
+            };
+
+            let overrides = &mut output.color_overrides;
+
+            egui::Grid::new("color_conversion_grid")
+                .num_columns(2)
+                .spacing([8.0, 4.0])
+                .show(ui, |ui| {
+                    // RGB sources are only converted for their primaries and transfer
+                    if conv.is_rgb() {
+                        Self::primaries_override_ui(ui, overrides, &conv);
+                        return;
+                    }
+
+                    ui.label(RichText::new("Matrix").color(STATE_LABEL_COLOR));
+                    Self::override_combo(
+                        ui,
+                        "matrix_override",
+                        &mut overrides.matrix,
+                        conv.matrix,
+                        &MATRIX_CHOICES,
+                    );
+                    ui.end_row();
+
+                    ui.label(RichText::new("Color range").color(STATE_LABEL_COLOR));
+                    Self::override_combo(
+                        ui,
+                        "range_override",
+                        &mut overrides.color_range,
+                        conv.color_range,
+                        &RANGE_CHOICES,
+                    );
+                    ui.end_row();
+
+                    ui.label(RichText::new("Chroma location").color(STATE_LABEL_COLOR));
+                    Self::override_combo(
+                        ui,
+                        "chromaloc_override",
+                        &mut overrides.chroma_location,
+                        conv.chroma_location,
+                        &CHROMALOC_CHOICES,
+                    );
+                    ui.end_row();
+
+                    Self::primaries_override_ui(ui, overrides, &conv);
+                });
+        });
+
+        if output.color_overrides != old_overrides {
+            pv.rerender = true;
+        }
+    }
+
+    /// Last rows of the conversion grid, shared by YUV and RGB sources
+    fn primaries_override_ui(
+        ui: &mut egui::Ui,
+        overrides: &mut VSColorOverrides,
+        conv: &VSColorConversion,
+    ) {
+        ui.label(RichText::new("Primaries").color(STATE_LABEL_COLOR));
+        Self::override_combo(
+            ui,
+            "primaries_override",
+            &mut overrides.primaries,
+            conv.primaries,
+            &PRIMARIES_CHOICES,
+        )
+        .on_hover_text("Converted to BT.709 when the transfer is SDR, otherwise only tagged");
+        ui.end_row();
+
+        if *overrides != VSColorOverrides::default() {
+            ui.label("");
+            if ui.button("Reset overrides").clicked() {
+                *overrides = VSColorOverrides::default();
+            }
+            ui.end_row();
+        }
+    }
+
+    /// `None` uses the frame props, the used value is shown in that case
+    fn override_combo<T: Copy + PartialEq + std::fmt::Display>(
+        ui: &mut egui::Ui,
+        id: &str,
+        value: &mut Option<T>,
+        used: T,
+        choices: &[T],
+    ) -> egui::Response {
+        let auto_text = format!("Auto ({used})");
+        let selected_text = value.map_or_else(|| auto_text.clone(), |v| v.to_string());
+
+        egui::ComboBox::from_id_salt(egui::Id::new(id))
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(value, None, auto_text);
+
+                for choice in choices {
+                    ui.selectable_value(value, Some(*choice), choice.to_string());
+                }
+            })
+            .response
     }
 
     fn transforms_ui(pv: &mut VSPreviewer, ui: &mut egui::Ui, ctx: &egui::Context) {
@@ -216,6 +505,7 @@ impl UiPreferences {
         let header = RichText::new("Transforms").color(STATE_LABEL_COLOR);
 
         let old_icc_flag = pv.state.icc_enabled;
+        let old_tonemap = pv.state.tonemap;
 
         egui::CollapsingHeader::new(header).show(ui, |ui| {
             egui::Grid::new("prefs_grid")
@@ -240,11 +530,69 @@ impl UiPreferences {
                     if profile_name.len() > 50 {
                         icc_name.on_hover_text(profile_name);
                     }
+
+                    let tonemap = &mut pv.state.tonemap;
+
+                    ui.checkbox(&mut tonemap.enabled, "Tonemap HDR")
+                        .on_hover_text("Applies to PQ and HLG frames only");
+                    ui.end_row();
+
+                    if tonemap.enabled {
+                        ui.label(RichText::new("Tonemap curve").color(STATE_LABEL_COLOR));
+                        egui::ComboBox::from_id_salt(egui::Id::new("tonemap_curve_select"))
+                            .selected_text(tonemap.curve.to_string())
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(
+                                    &mut tonemap.curve,
+                                    TonemapCurve::Bt2390,
+                                    TonemapCurve::Bt2390.to_string(),
+                                );
+                                ui.selectable_value(
+                                    &mut tonemap.curve,
+                                    TonemapCurve::Hable,
+                                    TonemapCurve::Hable.to_string(),
+                                );
+                                ui.selectable_value(
+                                    &mut tonemap.curve,
+                                    TonemapCurve::Reinhard,
+                                    TonemapCurve::Reinhard.to_string(),
+                                );
+                                ui.selectable_value(
+                                    &mut tonemap.curve,
+                                    TonemapCurve::Clip,
+                                    TonemapCurve::Clip.to_string(),
+                                );
+                            });
+                        ui.end_row();
+
+                        let target_peak_dragval = egui::DragValue::new(&mut tonemap.target_peak)
+                            .speed(1)
+                            .range(48.0..=1000.0)
+                            .max_decimals(0)
+                            .suffix(" nits");
+                        ui.label(RichText::new("Target peak").color(STATE_LABEL_COLOR))
+                            .on_hover_text("The source peak is MaxCLL or the mastering display's");
+                        let res = ui.add(target_peak_dragval);
+                        ui.end_row();
+
+                        let in_use = res.has_focus() || res.drag_started();
+                        update_input_key_state(
+                            &mut pv.inputs_focused,
+                            "target_peak_dragval",
+                            in_use,
+                            &res,
+                        );
+                    }
                 });
         });
 
         if pv.state.icc_enabled != old_icc_flag {
             pv.reprocess_outputs(true, false);
         }
+
+        // Tonemapped when the frame is fetched
+        if pv.state.tonemap != old_tonemap {
+            pv.rerender = true;
+        }
     }
 }
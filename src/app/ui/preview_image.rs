@@ -1,12 +1,35 @@
 use super::{
-    MAX_ZOOM, MIN_ZOOM, PreviewFilterType, VSPreviewer, custom_widgets::CustomImage, egui,
-    egui::Key, epaint::Vec2,
+    InspectedPixel, MAX_ZOOM, MIN_ZOOM, PixelInspector, PreviewCompareMode, PreviewFilterType,
+    VSPlaneView, VSPreviewer, custom_widgets::CustomImage, egui, egui::Key, epaint::Vec2,
 };
 use anyhow::{Result, anyhow};
-use eframe::egui::{Response, Sense, UiBuilder};
+use eframe::egui::{
+    Align2, Color32, FontId, PointerButton, Pos2, Rect, Response, Sense, Stroke, UiBuilder,
+};
 
 pub struct UiPreviewImage {}
 
+const SELECTION_COLOR: Color32 = Color32::from_rgb(250, 200, 60);
+
+/// Where the frame of an output was painted on the canvas
+struct PaintedOutput {
+    output: i32,
+    /// Visible part of `rect`
+    clip: Rect,
+    rect: Rect,
+}
+
+/// Maps between the canvas and the clip's coordinates of a painted output
+struct ClipMapping {
+    rect: Rect,
+    /// Region of the frame image painted in `rect`
+    source_rect: Rect,
+    /// Clip pixels per frame image pixel, for subsampled planes
+    scale: Vec2,
+    clip_size: [u32; 2],
+    subsampling: (u8, u8),
+}
+
 impl UiPreviewImage {
     pub fn ui(pv: &mut VSPreviewer, ui: &mut egui::Ui) -> Result<Response> {
         let cur_output = pv.state.cur_output;
@@ -62,7 +85,7 @@ impl UiPreviewImage {
             image_size = Vec2::from([image.width() as f32, image.height() as f32]);
         }
 
-        let win_size = pv.available_size;
+        let win_size = pv.canvas_size();
         let unzoomed_image_size = image_size * pv.state.zoom_factor.min(1.0);
 
         // We want to move the far left side of the image to avoid clipping
@@ -75,7 +98,9 @@ impl UiPreviewImage {
         let canvas_layout = egui::Layout::centered_and_justified(egui::Direction::TopDown)
             .with_cross_align(cross_align);
 
-        let canvas_res = ui.scope_builder(UiBuilder::new().sense(Sense::click()), |ui| {
+        let mut painted_outputs = Vec::new();
+
+        let canvas_res = ui.scope_builder(UiBuilder::new().sense(Sense::click_and_drag()), |ui| {
             ui.with_layout(canvas_layout, |ui| {
                 if let Some(pf) = preview_frame {
                     let pf = pf.read();
@@ -84,29 +109,21 @@ impl UiPreviewImage {
                     {
                         painted_image = true;
 
-                        let mut tex_size = tex.size_vec2();
-
-                        if (tex_size.x > win_size.x || tex_size.y > win_size.y)
-                            && pv.state.fit_to_window
-                        {
-                            // Image larger than window, downscaling
-                            tex_size *= (win_size.x / tex_size.x).min(1.0);
-                            tex_size *= (win_size.y / tex_size.y).min(1.0);
-                        } else if (tex_size.x < win_size.x || tex_size.y < win_size.y)
-                            && pv.state.upscale_to_window
-                            && pv.state.upsampling_filter == PreviewFilterType::Gpu
-                        {
-                            let target_size =
-                                crate::utils::dimensions_for_window(&win_size, &tex_size);
-                            // Image smaller than window, upscale
-                            tex_size = target_size;
-                        }
-
+                        let tex_size = Self::display_size(pv, tex.size_vec2(), &win_size);
                         let custom_image = CustomImage::new(tex.id(), tex_size);
 
-                        ui.add(custom_image);
+                        if pv.state.compare_mode.paints_both() {
+                            painted_outputs = Self::paint_compare(pv, ui, &custom_image, &win_size);
+                        } else {
+                            let rect = ui.add(custom_image).rect;
+                            painted_outputs.push(PaintedOutput {
+                                output: cur_output,
+                                clip: rect,
+                                rect,
+                            });
+                        }
 
-                        if !pv.any_input_focused() && !pv.frame_promise.is_locked() {
+                        if !pv.any_input_focused() {
                             let mut res = Self::handle_move_inputs(
                                 pv,
                                 ui,
@@ -129,18 +146,330 @@ impl UiPreviewImage {
             })
         });
 
+        if pv.state.pixel_inspector {
+            Self::inspect_pixel(pv, ui, &painted_outputs);
+
+            if canvas_res.response.clicked()
+                && let Some(summary) = pv.pixel_inspector.summary()
+            {
+                ui.ctx().copy_text(summary);
+            }
+        }
+
+        Self::select_region(pv, ui, &painted_outputs, &canvas_res.response);
+
         Ok(canvas_res.response)
     }
 
+    /// Dragging selects a region of the output under the pointer
+    fn select_region(
+        pv: &mut VSPreviewer,
+        ui: &egui::Ui,
+        painted_outputs: &[PaintedOutput],
+        response: &Response,
+    ) {
+        let pointer_pos = response.interact_pointer_pos();
+        let painted_output = |output: i32| painted_outputs.iter().find(|p| p.output == output);
+
+        if response.drag_started_by(PointerButton::Primary) {
+            let start = pointer_pos.and_then(|pos| {
+                let painted = painted_outputs.iter().find(|p| p.clip.contains(pos))?;
+                let mapping = ClipMapping::new(pv, painted)?;
+
+                Some((painted.output, mapping.to_clip(pos), mapping))
+            });
+
+            if let Some((output, pos, mapping)) = start {
+                pv.region_selection
+                    .start(output, pos, mapping.clip_size, mapping.subsampling);
+            }
+        } else if response.dragged_by(PointerButton::Primary)
+            && let Some(pos) = pointer_pos
+            && let Some(painted) = pv.region_selection.dragging().and_then(painted_output)
+            && let Some(mapping) = ClipMapping::new(pv, painted)
+        {
+            pv.region_selection.drag_to(mapping.to_clip(pos));
+        }
+
+        if response.drag_stopped() {
+            pv.region_selection.finish();
+        }
+
+        let selected = pv.region_selection.region.zip(pv.region_selection.crop());
+        let Some((region, crop)) = selected else {
+            return;
+        };
+        let Some(painted) = painted_output(region.output) else {
+            return;
+        };
+        let Some(mapping) = ClipMapping::new(pv, painted) else {
+            return;
+        };
+
+        let clip_rect = crop.rect();
+        let rect = Rect::from_min_max(
+            mapping.to_screen(clip_rect.min),
+            mapping.to_screen(clip_rect.max),
+        );
+
+        let painter = ui.painter_at(painted.clip);
+        painter.rect_stroke(
+            rect,
+            0.0,
+            Stroke::new(1.5, SELECTION_COLOR),
+            egui::StrokeKind::Outside,
+        );
+        painter.text(
+            rect.left_top() - Vec2::new(0.0, 4.0),
+            Align2::LEFT_BOTTOM,
+            format!("{}x{}", crop.width, crop.height),
+            FontId::proportional(14.0),
+            SELECTION_COLOR,
+        );
+    }
+
+    /// Updates the pixel under the pointer, in the coordinates of its output frame
+    fn inspect_pixel(pv: &mut VSPreviewer, ui: &egui::Ui, painted_outputs: &[PaintedOutput]) {
+        let hovered = ui.ctx().pointer_hover_pos().and_then(|pos| {
+            painted_outputs
+                .iter()
+                .find(|painted| {
+                    painted.clip.contains(pos) && ui.rect_contains_pointer(painted.clip)
+                })
+                .map(|painted| (pos, painted))
+        });
+
+        let pixel = hovered.and_then(|(pos, painted)| {
+            let pf = pv.outputs.get(&painted.output)?.rendered_frame.clone()?;
+            let pf = pf.read();
+            let image = &pf.vsframe.image;
+
+            let (x, y) = PixelInspector::source_pixel(pos, painted.rect, pf.source_rect, image)?;
+
+            // Viewed chroma planes can be smaller than the clip
+            let (ssw, ssh) = pf.vsframe.plane.map_or((0, 0), |plane| plane.subsampling);
+            let pixel = InspectedPixel {
+                output: painted.output,
+                frame_no: pf.frame_no,
+                x: x << ssw,
+                y: y << ssh,
+            };
+
+            pv.pixel_inspector.hover(pixel, image, (x, y));
+
+            Some(pixel)
+        });
+
+        if pixel.is_none() {
+            pv.pixel_inspector.clear();
+        }
+
+        let res = pv.pixel_inspector.update(&pv.script);
+        if matches!(res, Ok(true)) {
+            ui.ctx().request_repaint();
+        }
+        pv.add_error("pixel_inspector", &res);
+    }
+
+    /// Size to paint the texture at in the window
+    fn display_size(pv: &VSPreviewer, mut tex_size: Vec2, win_size: &Vec2) -> Vec2 {
+        if (tex_size.x > win_size.x || tex_size.y > win_size.y) && pv.state.fit_to_window {
+            // Image larger than window, downscaling
+            tex_size *= (win_size.x / tex_size.x).min(1.0);
+            tex_size *= (win_size.y / tex_size.y).min(1.0);
+        } else if (tex_size.x < win_size.x || tex_size.y < win_size.y)
+            && pv.state.upscale_to_window
+            && pv.state.upsampling_filter == PreviewFilterType::Gpu
+        {
+            // Image smaller than window, upscale
+            tex_size = crate::utils::dimensions_for_window(win_size, &tex_size);
+        }
+
+        tex_size
+    }
+
+    /// Paints the current output along with the compared output, as a wipe or side by side
+    fn paint_compare(
+        pv: &mut VSPreviewer,
+        ui: &mut egui::Ui,
+        image: &CustomImage,
+        win_size: &Vec2,
+    ) -> Vec<PaintedOutput> {
+        let mode = pv.state.compare_mode;
+        let (canvas_rect, res) = ui.allocate_exact_size(ui.available_size(), Sense::drag());
+
+        // Only painted once rendered for the current frame
+        let compare_pf = pv
+            .outputs
+            .get(&pv.state.compare_output)
+            .filter(|_| pv.state.compare_output != pv.state.cur_output)
+            .and_then(|o| o.rendered_frame.clone())
+            .filter(|pf| pf.read().frame_no == pv.state.cur_frame_no);
+        let compare_pf = compare_pf.as_ref().map(|pf| pf.read());
+        let compare_tex = compare_pf.as_ref().and_then(|pf| pf.texture.try_lock());
+        let compare_image = compare_tex.as_ref().and_then(|tex| {
+            tex.as_ref().map(|tex| {
+                let size = Self::display_size(pv, tex.size_vec2(), win_size);
+                CustomImage::new(tex.id(), size)
+            })
+        });
+
+        let painter = ui.painter_at(canvas_rect);
+        let label_font = FontId::proportional(14.0);
+        let cur_label = format!("Output {}", pv.state.cur_output);
+        let compare_label = format!("Output {}", pv.state.compare_output);
+
+        if mode == PreviewCompareMode::SideBySide {
+            let half_size = Vec2::new(canvas_rect.width() / 2.0, canvas_rect.height());
+            let left = Rect::from_min_size(canvas_rect.min, half_size);
+            let right =
+                Rect::from_min_size(canvas_rect.min + Vec2::new(half_size.x, 0.0), half_size);
+
+            let rect = Align2::CENTER_CENTER.align_size_within_rect(image.size(), left);
+            image.paint_with(&painter.with_clip_rect(left), rect);
+
+            let mut painted = vec![PaintedOutput {
+                output: pv.state.cur_output,
+                clip: left.intersect(rect),
+                rect,
+            }];
+            painter.text(
+                left.left_top(),
+                Align2::LEFT_TOP,
+                cur_label,
+                label_font.clone(),
+                Color32::WHITE,
+            );
+
+            if let Some(compare_image) = compare_image {
+                let rect =
+                    Align2::CENTER_CENTER.align_size_within_rect(compare_image.size(), right);
+                compare_image.paint_with(&painter.with_clip_rect(right), rect);
+
+                painted.push(PaintedOutput {
+                    output: pv.state.compare_output,
+                    clip: right.intersect(rect),
+                    rect,
+                });
+            }
+            painter.text(
+                right.left_top(),
+                Align2::LEFT_TOP,
+                compare_label,
+                label_font,
+                Color32::WHITE,
+            );
+
+            return painted;
+        }
+
+        // Wipes paint both outputs in the same rect
+        let rect = Align2::CENTER_CENTER.align_size_within_rect(image.size(), canvas_rect);
+        let vertical = mode == PreviewCompareMode::VerticalWipe;
+
+        if res.dragged()
+            && let Some(pos) = res.interact_pointer_pos()
+        {
+            let new_pos = if vertical {
+                (pos.x - rect.left()) / rect.width()
+            } else {
+                (pos.y - rect.top()) / rect.height()
+            };
+
+            pv.state.compare_wipe_pos = new_pos.clamp(0.0, 1.0);
+        }
+
+        let wipe_pos = pv.state.compare_wipe_pos;
+        let (cur_rect, compare_rect) = if vertical {
+            let split = rect.left() + rect.width() * wipe_pos;
+            (
+                Rect::from_min_max(rect.min, Pos2::new(split, rect.bottom())),
+                Rect::from_min_max(Pos2::new(split, rect.top()), rect.max),
+            )
+        } else {
+            let split = rect.top() + rect.height() * wipe_pos;
+            (
+                Rect::from_min_max(rect.min, Pos2::new(rect.right(), split)),
+                Rect::from_min_max(Pos2::new(rect.left(), split), rect.max),
+            )
+        };
+
+        image.paint_with(&painter.with_clip_rect(cur_rect), rect);
+
+        let mut painted = vec![PaintedOutput {
+            output: pv.state.cur_output,
+            clip: cur_rect,
+            rect,
+        }];
+
+        if let Some(compare_image) = compare_image {
+            compare_image.paint_with(&painter.with_clip_rect(compare_rect), rect);
+
+            painted.push(PaintedOutput {
+                output: pv.state.compare_output,
+                clip: compare_rect,
+                rect,
+            });
+        }
+
+        let split_line = if vertical {
+            [compare_rect.left_top(), compare_rect.left_bottom()]
+        } else {
+            [compare_rect.left_top(), compare_rect.right_top()]
+        };
+        painter.line_segment(split_line, Stroke::new(1.0, Color32::WHITE));
+
+        painter.text(
+            cur_rect.left_top(),
+            Align2::LEFT_TOP,
+            cur_label,
+            label_font.clone(),
+            Color32::WHITE,
+        );
+        painter.text(
+            compare_rect.right_bottom(),
+            Align2::RIGHT_BOTTOM,
+            compare_label,
+            label_font,
+            Color32::WHITE,
+        );
+
+        if res.hovered() {
+            let cursor = if vertical {
+                egui::CursorIcon::ResizeHorizontal
+            } else {
+                egui::CursorIcon::ResizeVertical
+            };
+            ui.ctx().set_cursor_icon(cursor);
+        }
+
+        painted
+    }
+
     pub fn handle_keypresses(pv: &mut VSPreviewer, ui: &mut egui::Ui) -> Result<()> {
         let mut rerender = Self::check_update_seek(pv, ui)?;
         rerender |= Self::check_update_output(pv, ui)?;
         rerender |= Self::check_icc_toggle(pv, ui)?;
+        rerender |= Self::check_alpha_toggle(pv, ui)?;
+        rerender |= Self::check_bookmark_keys(pv, ui)?;
+        rerender |= Self::check_prop_search_keys(pv, ui)?;
+        rerender |= Self::check_plane_view_keys(pv, ui);
+        Self::check_playback_keys(pv, ui);
 
         if ui.input(|i| i.key_pressed(Key::S)) {
             pv.save_screenshot()?;
         }
 
+        if ui.input(|i| i.modifiers.is_none() && i.key_pressed(Key::P)) {
+            pv.state.pixel_inspector = !pv.state.pixel_inspector;
+        } else if ui.input(|i| i.modifiers.is_none() && i.key_pressed(Key::W)) {
+            pv.state.scopes.show = !pv.state.scopes.show;
+        } else if ui.input(|i| i.modifiers.is_none() && i.key_pressed(Key::G)) {
+            pv.prop_plot.show = !pv.prop_plot.show;
+        } else if ui.input(|i| i.modifiers.is_none() && i.key_pressed(Key::M)) {
+            pv.metrics.show = !pv.metrics.show;
+        }
+
         pv.rerender |= rerender;
 
         Ok(())
@@ -176,19 +505,13 @@ impl UiPreviewImage {
                 false
             }
         } else if ui.input(|i| i.key_pressed(Key::ArrowUp) || i.key_pressed(Key::K)) {
-            if current >= node_info.framerate {
-                pv.state.cur_frame_no -= node_info.framerate;
-                true
-            } else if current < node_info.framerate {
-                pv.state.cur_frame_no = 0;
-                true
-            } else {
-                false
-            }
+            pv.state.cur_frame_no = output.seek_one_second(current, false);
+
+            pv.state.cur_frame_no != current
         } else if ui.input(|i| i.key_pressed(Key::ArrowDown) || i.key_pressed(Key::J)) {
-            pv.state.cur_frame_no += node_info.framerate;
+            pv.state.cur_frame_no = output.seek_one_second(current, true);
 
-            pv.state.cur_frame_no < node_info.num_frames - 1
+            pv.state.cur_frame_no != current
         } else {
             false
         };
@@ -279,7 +602,7 @@ impl UiPreviewImage {
             scroll_delta.y = 50.0;
         }
 
-        let win_size = pv.available_size;
+        let win_size = pv.canvas_size();
 
         // Calculate zoom factor
         let res_zoom = if delta != 1.0 {
@@ -377,4 +700,123 @@ impl UiPreviewImage {
 
         Ok(res)
     }
+
+    pub fn check_alpha_toggle(pv: &mut VSPreviewer, ui: &mut egui::Ui) -> Result<bool> {
+        // Must not have modifiers
+        if !ui.input(|i| i.modifiers.is_none()) {
+            return Ok(false);
+        }
+
+        // Nothing to toggle without an alpha clip
+        let has_alpha = pv
+            .outputs
+            .get(&pv.state.cur_output)
+            .is_some_and(|output| output.vsoutput.has_alpha);
+        if !has_alpha {
+            return Ok(false);
+        }
+
+        let mut res = false;
+
+        if ui.input(|i| i.key_pressed(Key::A)) {
+            pv.state.alpha_mask_only = !pv.state.alpha_mask_only;
+
+            pv.reprocess_outputs(true, false);
+            res = true;
+        }
+
+        Ok(res)
+    }
+
+    /// Cycles the viewed plane, or toggles the contrast stretch with shift.
+    /// Returns whether to rerender
+    pub fn check_plane_view_keys(pv: &mut VSPreviewer, ui: &mut egui::Ui) -> bool {
+        let Some(output) = pv.outputs.get(&pv.state.cur_output) else {
+            return false;
+        };
+
+        let opts = &mut pv.state.frame_transform_opts;
+
+        if ui.input(|i| i.modifiers.is_none() && i.key_pressed(Key::Y)) {
+            let vsoutput = &output.vsoutput;
+            opts.plane_view = opts
+                .plane_view
+                .next(vsoutput.node_info.plane_names.len(), vsoutput.has_alpha);
+
+            true
+        } else if ui.input(|i| i.modifiers.shift_only() && i.key_pressed(Key::Y)) {
+            opts.stretch_planes = !opts.stretch_planes;
+
+            opts.plane_view != VSPlaneView::Off
+        } else {
+            false
+        }
+    }
+
+    /// Returns whether to rerender
+    pub fn check_bookmark_keys(pv: &mut VSPreviewer, ui: &mut egui::Ui) -> Result<bool> {
+        if ui.input(|i| i.modifiers.is_none() && i.key_pressed(Key::B)) {
+            pv.bookmarks.toggle(pv.state.cur_frame_no);
+        } else if ui.input(|i| i.modifiers.ctrl && i.key_pressed(Key::ArrowRight)) {
+            return pv.seek_bookmark(true);
+        } else if ui.input(|i| i.modifiers.ctrl && i.key_pressed(Key::ArrowLeft)) {
+            return pv.seek_bookmark(false);
+        }
+
+        Ok(false)
+    }
+
+    /// Returns whether to rerender
+    pub fn check_prop_search_keys(pv: &mut VSPreviewer, ui: &mut egui::Ui) -> Result<bool> {
+        if ui.input(|i| i.modifiers.is_none() && i.key_pressed(Key::N)) {
+            pv.seek_prop_match(true)
+        } else if ui.input(|i| i.modifiers.shift_only() && i.key_pressed(Key::N)) {
+            pv.seek_prop_match(false)
+        } else {
+            Ok(false)
+        }
+    }
+
+    pub fn check_playback_keys(pv: &mut VSPreviewer, ui: &mut egui::Ui) {
+        if ui.input(|i| i.key_pressed(Key::Space)) {
+            let reverse = ui.input(|i| i.modifiers.shift);
+            pv.playback.toggle(reverse);
+        } else if ui.input(|i| i.key_pressed(Key::CloseBracket)) {
+            pv.playback.change_speed(true);
+        } else if ui.input(|i| i.key_pressed(Key::OpenBracket)) {
+            pv.playback.change_speed(false);
+        }
+    }
+}
+
+impl ClipMapping {
+    fn new(pv: &VSPreviewer, painted: &PaintedOutput) -> Option<Self> {
+        let output = pv.outputs.get(&painted.output)?;
+        let pf = output.rendered_frame.as_ref()?.read();
+
+        let (ssw, ssh) = pf.vsframe.plane.map_or((0, 0), |plane| plane.subsampling);
+        let image = &pf.vsframe.image;
+
+        Some(Self {
+            rect: painted.rect,
+            source_rect: pf.source_rect,
+            scale: Vec2::new((1 << ssw) as f32, (1 << ssh) as f32),
+            clip_size: [image.width() << ssw, image.height() << ssh],
+            subsampling: output.vsoutput.node_info.subsampling,
+        })
+    }
+
+    fn to_clip(&self, pos: Pos2) -> Pos2 {
+        let rel = (pos - self.rect.min) / self.rect.size();
+        let image_pos = self.source_rect.min + rel * self.source_rect.size();
+
+        (image_pos.to_vec2() * self.scale).to_pos2()
+    }
+
+    fn to_screen(&self, pos: Pos2) -> Pos2 {
+        let image_pos = pos.to_vec2() / self.scale;
+        let rel = (image_pos - self.source_rect.min.to_vec2()) / self.source_rect.size();
+
+        self.rect.min + rel * self.rect.size()
+    }
 }
@@ -0,0 +1,222 @@
+use super::{
+    FrameGraph, PropPlot, PropPlotFormat, STATE_LABEL_COLOR, VSPreviewer, egui, egui::RichText,
+    update_input_key_state,
+};
+use crate::vs_handler::vsprops::VSPropValue;
+
+const GRAPH_HEIGHT: f32 = 80.0;
+
+pub struct UiPropPlot {}
+
+impl UiPropPlot {
+    pub fn ui(pv: &mut VSPreviewer, ctx: &egui::Context) {
+        if !pv.prop_plot.show {
+            return;
+        }
+
+        let Some(num_frames) = pv
+            .outputs
+            .get(&pv.state.cur_output)
+            .map(|output| output.vsoutput.node_info.num_frames)
+        else {
+            return;
+        };
+        let last_frame = num_frames.saturating_sub(1);
+
+        // Numeric props of the displayed frame
+        let numeric_keys: Vec<String> = pv
+            .outputs
+            .get(&pv.state.cur_output)
+            .and_then(|output| output.rendered_frame.as_ref())
+            .map(|pf| {
+                pf.read()
+                    .vsframe
+                    .props_map
+                    .props
+                    .iter()
+                    .filter(|prop| prop.value.as_f64().is_some())
+                    .map(|prop| prop.key.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut open = true;
+        let mut start_scan = false;
+        let mut clear = false;
+        let mut export_format = None;
+
+        egui::Window::new("Prop plot")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let plot = &mut pv.prop_plot;
+                plot.end = plot.end.min(last_frame);
+                plot.start = plot.start.min(plot.end);
+
+                egui::Grid::new("prop_plot_grid")
+                    .num_columns(2)
+                    .spacing([8.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Prop").color(STATE_LABEL_COLOR));
+                        ui.horizontal(|ui| {
+                            let key_edit = egui::TextEdit::singleline(&mut plot.key)
+                                .desired_width(160.0)
+                                .hint_text("PlaneStatsAverage");
+                            let key_res = ui.add(key_edit);
+                            update_input_key_state(
+                                &mut pv.inputs_focused,
+                                "prop_plot_key",
+                                key_res.has_focus(),
+                                &key_res,
+                            );
+
+                            egui::ComboBox::from_id_salt(egui::Id::new("prop_plot_key_select"))
+                                .width(20.0)
+                                .selected_text("")
+                                .show_ui(ui, |ui| {
+                                    for key in &numeric_keys {
+                                        ui.selectable_value(&mut plot.key, key.clone(), key);
+                                    }
+                                })
+                                .response
+                                .on_hover_text("Numeric props of the current frame");
+                        });
+                        ui.end_row();
+
+                        ui.label(RichText::new("Frames").color(STATE_LABEL_COLOR));
+                        ui.horizontal(|ui| {
+                            let end = plot.end;
+                            let start_res =
+                                ui.add(egui::DragValue::new(&mut plot.start).range(0..=end));
+                            ui.label("to");
+                            let end_res =
+                                ui.add(egui::DragValue::new(&mut plot.end).range(0..=last_frame));
+
+                            for (name, res) in
+                                [("prop_plot_start", start_res), ("prop_plot_end", end_res)]
+                            {
+                                let in_use = res.has_focus() || res.drag_started();
+                                update_input_key_state(&mut pv.inputs_focused, name, in_use, &res);
+                            }
+
+                            if ui.button("All").clicked() {
+                                plot.start = 0;
+                                plot.end = last_frame;
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label(RichText::new("Step").color(STATE_LABEL_COLOR));
+                        let step_res = ui
+                            .add(egui::DragValue::new(&mut plot.step).range(1..=num_frames.max(1)))
+                            .on_hover_text("Only every Nth frame is evaluated");
+                        let in_use = step_res.has_focus() || step_res.drag_started();
+                        update_input_key_state(
+                            &mut pv.inputs_focused,
+                            "prop_plot_step",
+                            in_use,
+                            &step_res,
+                        );
+                        ui.end_row();
+                    });
+
+                ui.horizontal(|ui| {
+                    if let Some(data) = plot.data.as_ref().filter(|_| plot.scanning()) {
+                        if ui.button("Cancel").clicked() {
+                            plot.cancel();
+                        }
+
+                        let progress = data.lock().progress();
+                        ui.add(egui::ProgressBar::new(progress).show_percentage());
+                    } else {
+                        start_scan = ui
+                            .button("Plot")
+                            .on_hover_text("Evaluates the current output over the frames")
+                            .clicked();
+                    }
+                });
+
+                let Some(data) = &plot.data else {
+                    return;
+                };
+
+                ui.separator();
+
+                let data = data.lock();
+                let stats = match (data.min_max(), data.mean()) {
+                    (Some((min, max)), Some(mean)) => format!(
+                        "{}: {} values, min {min:.6}, max {max:.6}, mean {mean:.6}",
+                        data.key,
+                        data.values.len()
+                    ),
+                    _ => format!("{}: no values", data.key),
+                };
+                ui.label(stats);
+
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Export").color(STATE_LABEL_COLOR));
+
+                    for format in PropPlotFormat::ALL {
+                        if ui.button(format.to_string()).clicked() {
+                            export_format = Some(format);
+                        }
+                    }
+
+                    clear = ui
+                        .add_enabled(!plot.scanning(), egui::Button::new("Clear"))
+                        .clicked();
+                });
+            });
+
+        pv.prop_plot.show = open;
+        if clear {
+            pv.prop_plot.data = None;
+        }
+
+        if start_scan {
+            pv.start_prop_plot(ctx);
+        }
+        if let Some(format) = export_format {
+            pv.export_prop_plot(ctx, format);
+        }
+    }
+
+    /// Values over the scanned range, under the timeline.
+    /// Returns the clicked frame.
+    pub fn graph(
+        ui: &mut egui::Ui,
+        plot: &PropPlot,
+        output: i32,
+        cur_frame_no: u32,
+    ) -> Option<u32> {
+        let data = plot.data.as_ref()?.lock();
+        if data.output != output {
+            return None;
+        }
+
+        FrameGraph {
+            label: &data.key,
+            start: data.start,
+            end: data.end,
+            values: &data.values,
+            cur_frame_no,
+        }
+        .show(ui, GRAPH_HEIGHT)
+    }
+
+    /// Button to plot a numeric prop from the props tree
+    pub fn plot_button(ui: &mut egui::Ui, plot: &mut PropPlot, key: &str, value: &VSPropValue) {
+        if value.as_f64().is_none() {
+            return;
+        }
+
+        if ui
+            .small_button("📈")
+            .on_hover_text("Plot over a frame range")
+            .clicked()
+        {
+            plot.key = key.to_string();
+            plot.show = true;
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use super::{REGION_ALIGNMENTS, STATE_LABEL_COLOR, VSPreviewer, egui, egui::RichText};
+
+pub struct UiRegionSelection {}
+
+impl UiRegionSelection {
+    /// Crop values of the selected region, once the drag is done
+    pub fn ui(pv: &mut VSPreviewer, ctx: &egui::Context) {
+        let selection = &pv.region_selection;
+
+        if selection.dragging().is_some() {
+            return;
+        }
+        let (Some(region), Some(crop)) = (selection.region, selection.crop()) else {
+            return;
+        };
+
+        let mut open = true;
+        let mut save_screenshot = false;
+
+        egui::Window::new("Selection")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("region_selection_grid")
+                    .num_columns(2)
+                    .spacing([8.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Output").color(STATE_LABEL_COLOR));
+                        ui.label(format!(
+                            "{} ({}x{})",
+                            region.output, region.clip_size[0], region.clip_size[1]
+                        ));
+                        ui.end_row();
+
+                        ui.label(RichText::new("Region").color(STATE_LABEL_COLOR));
+                        ui.label(format!(
+                            "{}x{} at {}, {}",
+                            crop.width, crop.height, crop.left, crop.top
+                        ));
+                        ui.end_row();
+
+                        ui.label(RichText::new("Crop").color(STATE_LABEL_COLOR));
+                        ui.label(format!(
+                            "left {}, right {}, top {}, bottom {}",
+                            crop.left, crop.right, crop.top, crop.bottom
+                        ));
+                        ui.end_row();
+
+                        let alignment = &mut pv.region_selection.alignment;
+                        let min_alignment = 1 << region.subsampling.0.max(region.subsampling.1);
+
+                        ui.label(RichText::new("Alignment").color(STATE_LABEL_COLOR));
+                        egui::ComboBox::from_id_salt(egui::Id::new("region_alignment_select"))
+                            .selected_text(format!("mod {}", (*alignment).max(min_alignment)))
+                            .show_ui(ui, |ui| {
+                                for value in REGION_ALIGNMENTS
+                                    .into_iter()
+                                    .filter(|v| *v >= min_alignment)
+                                {
+                                    ui.selectable_value(alignment, value, format!("mod {value}"));
+                                }
+                            })
+                            .response
+                            .on_hover_text("The crop is always aligned to the chroma subsampling");
+                        ui.end_row();
+                    });
+
+                ui.horizontal(|ui| {
+                    if ui.button("Copy Crop").clicked() {
+                        ui.ctx().copy_text(crop.crop_snippet());
+                    }
+                    if ui.button("Copy CropAbs").clicked() {
+                        ui.ctx().copy_text(crop.crop_abs_snippet());
+                    }
+
+                    save_screenshot = ui
+                        .button("Save screenshot")
+                        .on_hover_text("Saves the selected region to the script directory")
+                        .clicked();
+                });
+            });
+
+        if save_screenshot {
+            let res = pv.save_output_screenshot(region.output, Some(crop));
+            pv.add_error("region_selection", &res);
+        }
+
+        if !open {
+            pv.region_selection.clear();
+        }
+    }
+}
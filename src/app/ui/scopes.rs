@@ -0,0 +1,200 @@
+use super::{STATE_LABEL_COLOR, VSPreviewer, egui, egui::RichText, epaint};
+use crate::app::scopes::{ScopeSource, ScopeTextures, VectorscopeTargets};
+use eframe::egui::{Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, TextureHandle, pos2};
+
+const GRATICULE_COLOR: Color32 = Color32::from_rgba_premultiplied(120, 120, 120, 120);
+const LIMITED_RANGE_COLOR: Color32 = Color32::from_rgba_premultiplied(160, 110, 40, 160);
+const TARGET_COLOR: Color32 = Color32::from_rgb(200, 160, 60);
+
+/// Limited range bounds, in 8 bit code values
+const LIMITED_RANGE: [f32; 2] = [16.0, 235.0];
+
+pub struct UiScopes {}
+
+impl UiScopes {
+    /// Shown before the central panel, so it can be docked
+    pub fn ui(pv: &mut VSPreviewer, ctx: &egui::Context) {
+        if !pv.state.scopes.show {
+            return;
+        }
+
+        if pv.state.scopes.docked {
+            egui::SidePanel::right("scopes_panel")
+                .resizable(true)
+                .default_width(360.0)
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| Self::scopes_ui(pv, ui));
+                });
+        } else {
+            let mut open = true;
+
+            egui::Window::new("Scopes")
+                .open(&mut open)
+                .default_width(360.0)
+                .resizable(true)
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| Self::scopes_ui(pv, ui));
+                });
+
+            pv.state.scopes.show = open;
+        }
+    }
+
+    fn scopes_ui(pv: &mut VSPreviewer, ui: &mut egui::Ui) {
+        Self::options_ui(pv, ui);
+        ui.separator();
+
+        let textures = &pv.scopes.textures;
+        let settings = pv.state.scopes.settings;
+        let width = ui.available_width();
+
+        if !textures.format_name.is_empty() {
+            ui.label(RichText::new(&textures.format_name).color(STATE_LABEL_COLOR));
+        }
+
+        if let Some(tex) = textures.waveform.as_ref().filter(|_| settings.waveform) {
+            ui.label(RichText::new("Waveform").color(STATE_LABEL_COLOR));
+            let rect = Self::paint_texture(ui, tex, width);
+            Self::paint_levels(ui, rect, textures.limited_range);
+        }
+
+        if let Some(tex) = textures.parade.as_ref().filter(|_| settings.parade) {
+            ui.label(RichText::new("Parade").color(STATE_LABEL_COLOR));
+            let rect = Self::paint_texture(ui, tex, width);
+            Self::paint_levels(ui, rect, textures.limited_range);
+            Self::paint_parade_labels(ui, rect, textures);
+        }
+
+        if let Some(tex) = textures
+            .vectorscope
+            .as_ref()
+            .filter(|_| settings.vectorscope)
+        {
+            ui.label(RichText::new("Vectorscope").color(STATE_LABEL_COLOR));
+            let rect = Self::paint_texture(ui, tex, width.min(360.0));
+            Self::paint_vectorscope_targets(ui, rect, settings.targets, textures.limited_range);
+        }
+
+        if let Some(tex) = textures.histogram.as_ref().filter(|_| settings.histogram) {
+            ui.label(RichText::new("Histogram").color(STATE_LABEL_COLOR));
+            let rect = Self::paint_texture(ui, tex, width);
+            Self::paint_parade_labels(ui, rect, textures);
+        }
+    }
+
+    fn options_ui(pv: &mut VSPreviewer, ui: &mut egui::Ui) {
+        let options = &mut pv.state.scopes;
+        let settings = &mut options.settings;
+
+        ui.horizontal_wrapped(|ui| {
+            ui.checkbox(&mut settings.waveform, "Waveform");
+            ui.checkbox(&mut settings.parade, "Parade");
+            ui.checkbox(&mut settings.vectorscope, "Vectorscope");
+            ui.checkbox(&mut settings.histogram, "Histogram");
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label(RichText::new("Source").color(STATE_LABEL_COLOR));
+            egui::ComboBox::from_id_salt(egui::Id::new("scope_source_select"))
+                .selected_text(settings.source.to_string())
+                .show_ui(ui, |ui| {
+                    for source in [ScopeSource::Preview, ScopeSource::Original] {
+                        ui.selectable_value(&mut settings.source, source, source.to_string());
+                    }
+                })
+                .response
+                .on_hover_text("Original computes the scopes from the clip in its native format");
+
+            ui.label(RichText::new("Targets").color(STATE_LABEL_COLOR));
+            egui::ComboBox::from_id_salt(egui::Id::new("scope_targets_select"))
+                .selected_text(settings.targets.to_string())
+                .show_ui(ui, |ui| {
+                    for targets in VectorscopeTargets::ALL {
+                        ui.selectable_value(&mut settings.targets, targets, targets.to_string());
+                    }
+                });
+
+            ui.checkbox(&mut options.docked, "Docked");
+        });
+    }
+
+    fn paint_texture(ui: &mut egui::Ui, tex: &TextureHandle, width: f32) -> Rect {
+        let size = tex.size_vec2() * (width / tex.size_vec2().x);
+        let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+
+        let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        ui.painter().image(tex.id(), rect, uv, Color32::WHITE);
+
+        rect
+    }
+
+    /// Horizontal lines every 25%, and the limited range bounds
+    fn paint_levels(ui: &egui::Ui, rect: Rect, limited_range: bool) {
+        let painter = ui.painter_at(rect);
+        let line_at = |level: f32, color: Color32| {
+            let y = rect.bottom() - rect.height() * level;
+            painter.hline(rect.x_range(), y, Stroke::new(1.0, color));
+        };
+
+        for level in [0.0, 0.25, 0.5, 0.75, 1.0] {
+            line_at(level, GRATICULE_COLOR);
+        }
+
+        if limited_range {
+            for code in LIMITED_RANGE {
+                line_at(code / 255.0, LIMITED_RANGE_COLOR);
+            }
+        }
+    }
+
+    fn paint_parade_labels(ui: &egui::Ui, rect: Rect, textures: &ScopeTextures) {
+        let painter = ui.painter_at(rect);
+        let names = &textures.plane_names;
+        let plane_width = rect.width() / names.len().max(1) as f32;
+
+        for (i, name) in names.iter().enumerate() {
+            let pos = Pos2::new(rect.left() + plane_width * i as f32 + 4.0, rect.top() + 2.0);
+            painter.text(
+                pos,
+                Align2::LEFT_TOP,
+                *name,
+                FontId::proportional(12.0),
+                STATE_LABEL_COLOR,
+            );
+        }
+    }
+
+    fn paint_vectorscope_targets(
+        ui: &egui::Ui,
+        rect: Rect,
+        targets: VectorscopeTargets,
+        limited_range: bool,
+    ) {
+        let painter = ui.painter_at(rect);
+        let stroke = Stroke::new(1.0, GRATICULE_COLOR);
+
+        painter.hline(rect.x_range(), rect.center().y, stroke);
+        painter.vline(rect.center().x, rect.y_range(), stroke);
+
+        let to_screen = |pos: Pos2| rect.min + pos.to_vec2() * rect.size();
+        let target_size = epaint::Vec2::splat(rect.width() * 0.04);
+
+        for (name, pos) in targets.targets(limited_range) {
+            let center = to_screen(pos);
+
+            painter.rect_stroke(
+                Rect::from_center_size(center, target_size),
+                0.0,
+                Stroke::new(1.0, TARGET_COLOR),
+                egui::StrokeKind::Middle,
+            );
+            painter.text(
+                center + target_size,
+                Align2::LEFT_TOP,
+                name,
+                FontId::proportional(11.0),
+                STATE_LABEL_COLOR,
+            );
+        }
+    }
+}
@@ -11,7 +11,7 @@ impl UiStateWindow {
             .resizable(true)
             .collapsible(false)
             .show(ctx, |ui| {
-                UiControls::ui(pv, ui);
+                UiControls::ui(pv, ctx, ui);
                 ui.separator();
 
                 if has_current_output {
@@ -1,16 +1,18 @@
 use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::{Result, anyhow, bail};
 use eframe::egui::Key;
-use eframe::egui::{self, TextureOptions};
+use eframe::egui::{self, Pos2, Rect, TextureOptions};
 use fast_image_resize as fir;
 use image::DynamicImage;
 use parking_lot::{Mutex, RwLock};
 use tokio::sync::mpsc::Sender;
 
 use crate::utils::image_to_colorimage;
-use crate::vs_handler::PreviewedScript;
+use crate::vs_handler::{PendingFrame, PreviewedScript};
 
 use super::*;
 
@@ -24,10 +26,14 @@ pub struct VSPreviewer {
 
     /// Promise returning the newly reloaded outputs
     pub reload_data: Option<Promise<PreviewerResponse>>,
+    /// The last reload failed, the outputs are from the previous script
+    pub stale_script: bool,
     /// Outputs available from the script
     pub outputs: HashMap<i32, PreviewOutput>,
     /// Last output used
     pub last_output_key: i32,
+    /// Last (output, frame) requested for comparison, not retried on errors
+    compare_requested: Option<(i32, u32)>,
 
     /// Canvas drawing available size
     pub available_size: Vec2,
@@ -39,8 +45,6 @@ pub struct VSPreviewer {
     /// Override to only reprocess without requesting a new VS frame
     pub reprocess: bool,
 
-    /// Promise returning a new requested frame
-    pub frame_promise: Arc<Mutex<Option<Promise<PreviewerResponse>>>>,
     /// Promise returning the original props of the current frame
     pub original_props_promise: Arc<Mutex<Option<Promise<PreviewerResponse>>>>,
 
@@ -49,10 +53,48 @@ pub struct VSPreviewer {
 
     pub transforms: Arc<Mutex<PreviewTransforms>>,
     pub exit_promise: Option<Promise<PreviewerResponse>>,
+
+    /// Watches the script files to reload on change
+    pub script_watcher: Option<ScriptWatcher>,
+    /// Disabled from the command line, overrides the preference
+    pub auto_reload_disabled: bool,
+
+    pub playback: Playback,
+
+    /// Bookmarks of the current script
+    pub bookmarks: Bookmarks,
+    /// Promise of the bookmarks export/import dialog
+    pub bookmarks_promise: Option<Promise<PreviewerResponse>>,
+
+    /// Frame number or timecode to seek to
+    pub seek_input: String,
+
+    /// Filter of the frame props keys
+    pub props_search: String,
+    /// Frame props keys shown first
+    pub pinned_props: Vec<String>,
+    /// Color overrides of the outputs, restored on reload
+    pub saved_color_overrides: SavedColorOverrides,
+
+    pub pixel_inspector: PixelInspector,
+    pub scopes: Scopes,
+    pub region_selection: RegionSelection,
+    pub prop_plot: PropPlot,
+    pub prop_search: PropSearch,
+    pub metrics: Metrics,
+
+    /// Address of the control socket to start, from the command line
+    ipc_address: Option<IpcAddress>,
+    pub ipc_server: Option<IpcServer>,
 }
 
 impl VSPreviewer {
-    pub fn new(script: Arc<Mutex<PreviewedScript>>, cmd_sender: Sender<VSCommandMsg>) -> Self {
+    pub fn new(
+        script: Arc<Mutex<PreviewedScript>>,
+        cmd_sender: Sender<VSCommandMsg>,
+        auto_reload_disabled: bool,
+        ipc_address: Option<IpcAddress>,
+    ) -> Self {
         Self {
             script,
             cmd_sender,
@@ -60,25 +102,44 @@ impl VSPreviewer {
             errors: Default::default(),
             about_window_open: Default::default(),
             reload_data: Default::default(),
+            stale_script: Default::default(),
             outputs: Default::default(),
             last_output_key: Default::default(),
+            compare_requested: Default::default(),
             available_size: Default::default(),
             inputs_focused: Default::default(),
             rerender: Default::default(),
             reprocess: Default::default(),
-            frame_promise: Default::default(),
             original_props_promise: Default::default(),
             misc_promise: Default::default(),
             transforms: Default::default(),
             exit_promise: Default::default(),
+            script_watcher: Default::default(),
+            auto_reload_disabled,
+            playback: Default::default(),
+            bookmarks: Default::default(),
+            bookmarks_promise: Default::default(),
+            seek_input: Default::default(),
+            props_search: Default::default(),
+            pinned_props: Default::default(),
+            saved_color_overrides: Default::default(),
+            pixel_inspector: Default::default(),
+            scopes: Default::default(),
+            region_selection: Default::default(),
+            prop_plot: Default::default(),
+            prop_search: Default::default(),
+            metrics: Default::default(),
+            ipc_address,
+            ipc_server: Default::default(),
         }
     }
 
+    /// Also returns the region of the original image that was kept, in its pixels
     pub fn process_image(
         orig: &DynamicImage,
         state: &PreviewState,
         win_size: &eframe::epaint::Vec2,
-    ) -> Result<DynamicImage> {
+    ) -> Result<(DynamicImage, Rect)> {
         // Rounded up
         let win_size = win_size.round();
         let src_size = Vec2::from([orig.width() as f32, orig.height() as f32]);
@@ -90,6 +151,10 @@ impl VSPreviewer {
         let zoom_factor = state.zoom_factor;
         let (mut w, mut h) = (src_w, src_h);
 
+        let mut source_rect = Rect::from_min_size(Pos2::ZERO, src_size);
+        // Size of an original pixel in the cropped image
+        let mut source_scale = Vec2::splat(1.0);
+
         // Unzoom first and foremost
         if zoom_factor < 1.0 && !state.upscale_to_window {
             w *= zoom_factor;
@@ -101,6 +166,8 @@ impl VSPreviewer {
                 h.round() as u32,
                 fir::FilterType::Box,
             )?;
+
+            source_scale = image_size(&img) / src_size;
         }
 
         if w > win_size.x || h > win_size.y || zoom_factor > 1.0 {
@@ -135,6 +202,10 @@ impl VSPreviewer {
             }
 
             img = img.crop_imm(x as u32, y as u32, w as u32, h as u32);
+
+            let offset = Vec2::new(x.trunc(), y.trunc()) / source_scale;
+            source_rect =
+                Rect::from_min_size(source_rect.min + offset, image_size(&img) / source_scale);
         }
 
         // Zoom after translate
@@ -145,6 +216,7 @@ impl VSPreviewer {
 
             // Crop for performance, we only want the visible zoomed part
             img = img.crop_imm(0, 0, cw as u32, ch as u32);
+            source_rect = Rect::from_min_size(source_rect.min, image_size(&img) / source_scale);
 
             // Size for nearest resize, same as current image size
             // But since we cropped, it creates the zoom effect.
@@ -179,7 +251,7 @@ impl VSPreviewer {
             }
         }
 
-        Ok(img)
+        Ok((img, source_rect))
     }
 
     // Always reloads the script
@@ -203,18 +275,37 @@ impl VSPreviewer {
             .ok();
     }
 
-    pub fn check_reload_finish(&mut self) -> Result<()> {
+    pub fn check_reload_finish(&mut self, ctx: &egui::Context) -> Result<()> {
         if let Some(promise) = &self.reload_data
             && let Some(PreviewerResponse::Reload(promise_res)) = promise.ready()
         {
-            self.outputs.clear();
+            // Stored for the previous script, before its outputs are replaced
+            self.saved_color_overrides
+                .update(self.bookmarks.script(), &self.outputs);
+
+            // The script may have been changed
+            let script_file = self.script.lock().get_script_file();
+            self.bookmarks.set_script(&script_file);
 
             if let Some(outputs) = promise_res {
+                self.stale_script = false;
+                self.compare_requested = None;
+                self.pixel_inspector = Default::default();
+                self.region_selection.clear();
+                self.prop_plot.cancel();
+                self.prop_search.cancel();
+                self.metrics.cancel();
+
                 self.outputs = outputs
                     .iter()
                     .map(|(key, o)| {
+                        let color_overrides = self
+                            .saved_color_overrides
+                            .get(self.bookmarks.script(), *key);
+
                         let new = PreviewOutput {
                             vsoutput: o.clone(),
+                            color_overrides,
                             ..Default::default()
                         };
 
@@ -247,6 +338,9 @@ impl VSPreviewer {
 
                 // Fetch a frame for new current output
                 self.rerender = true;
+            } else if !self.outputs.is_empty() {
+                // The previous environment was kept, its outputs are still valid
+                self.stale_script = true;
             }
 
             // Done reloading, remove promise
@@ -258,106 +352,226 @@ impl VSPreviewer {
 
             // Reset reload data even if errored
             self.reload_data = None;
+
+            // Imports may have changed, also keep watching if the script failed
+            self.update_script_watcher(ctx);
         }
 
         Ok(())
     }
 
     pub fn try_rerender(&mut self, ctx: &egui::Context) -> Result<()> {
-        if let Some(mut promise) = self.frame_promise.try_lock() {
-            let misc_in_progress = if let Some(p) = self.misc_promise.try_lock() {
-                p.is_some()
-            } else {
-                true
-            };
+        let misc_in_progress = if let Some(p) = self.misc_promise.try_lock() {
+            p.is_some()
+        } else {
+            true
+        };
 
-            // Still rendering, reloading or changing
-            if promise.is_some() || misc_in_progress || self.reload_data.is_some() {
-                return Ok(());
-            }
+        // Reloading or changing
+        if misc_in_progress || self.reload_data.is_some() || self.outputs.is_empty() {
+            return Ok(());
+        }
 
-            if !self.outputs.is_empty() {
-                let output = self
-                    .outputs
-                    .get_mut(&self.state.cur_output)
-                    .ok_or_else(|| anyhow!("rerender: Invalid current output key"))?;
+        let output = self
+            .outputs
+            .get_mut(&self.state.cur_output)
+            .ok_or_else(|| anyhow!("rerender: Invalid current output key"))?;
 
-                if output.force_reprocess {
-                    self.rerender = true;
+        // A render for another frame is superseded, otherwise wait for the current one.
+        // The compared output can render concurrently.
+        let can_request = if self.rerender && !self.reprocess {
+            output.supersede_request(self.state.cur_frame_no)
+        } else {
+            output.frame_request.is_none()
+        };
 
-                    // Reprocess only if the output is already the correct frame
-                    self.reprocess = output.last_frame_no == self.state.cur_frame_no;
+        if can_request {
+            if output.force_reprocess {
+                self.rerender = true;
 
-                    output.force_reprocess = false;
-                }
+                // Reprocess only if the output is already the correct frame
+                self.reprocess = output.last_frame_no == self.state.cur_frame_no;
 
-                if self.rerender && !self.reprocess {
-                    // Remove original frame props when a VS render is requested
-                    output.original_props = None;
-                }
+                output.force_reprocess = false;
+            }
+
+            if self.rerender && !self.reprocess {
+                // Remove original frame props when a VS render is requested
+                output.original_props = None;
             }
 
             if self.rerender {
                 self.rerender = false;
 
-                let mut reprocess = self.reprocess;
+                let reprocess = self.reprocess;
                 self.reprocess = false;
 
-                // Still reloading, can't reprocess
-                if self.reload_data.is_some() && reprocess {
-                    reprocess = false;
-                }
-
-                // Get current state at the moment the frame is requested
                 let state = self.state;
 
                 // Reset current changed flag
                 self.state.translate_changed = false;
 
-                let win_size = self.available_size;
+                // Worker busy, retried on the next update
+                if !self.request_frame(ctx, state, reprocess) {
+                    self.rerender = true;
+                    self.reprocess = reprocess;
+                    self.state.translate_changed |= state.translate_changed;
+                    ctx.request_repaint();
+                }
+            }
+        }
+
+        if let Some((compare_output, reprocess)) = self.compare_output_needs_render() {
+            // Compared output is rendered for the same frame as the current one
+            let mut state = self.state;
+            state.cur_output = compare_output;
+            state.translate_changed = reprocess;
 
-                let pf = self.get_current_frame()?;
+            if !self.request_frame(ctx, state, reprocess) {
+                self.compare_requested = None;
+                ctx.request_repaint();
+            }
+        }
 
-                let frame_mutex = self.frame_promise.clone();
+        Ok(())
+    }
 
-                let fetch_image_state = FetchImageState {
-                    frame_mutex,
-                    state,
-                    pf,
-                    reprocess,
-                    win_size,
-                };
+    /// Requests the frame for `state.cur_output`.
+    /// Returns false if the command couldn't be sent.
+    fn request_frame(
+        &mut self,
+        ctx: &egui::Context,
+        mut state: PreviewState,
+        reprocess: bool,
+    ) -> bool {
+        // Get current state at the moment the frame is requested
+        state.frame_transform_opts.linearize_hdr = state.tonemap.enabled;
 
-                let (res_sender, new_promise) = Promise::new();
-                *promise = Some(new_promise);
-                self.cmd_sender
-                    .try_send(VSCommandMsg {
-                        res_sender,
-                        cmd: VSCommand::Frame(fetch_image_state),
-                        egui_ctx: ctx.clone(),
-                    })
-                    .ok();
+        let win_size = self.canvas_size();
+
+        let compare_color_overrides = self
+            .outputs
+            .get(&state.compare_output)
+            .map(|output| output.color_overrides)
+            .unwrap_or_default();
+
+        let Some(output) = self.outputs.get_mut(&state.cur_output) else {
+            return true;
+        };
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let fetch_image_state = FetchImageState {
+            state,
+            pf: output.rendered_frame.clone(),
+            reprocess,
+            win_size,
+            color_overrides: output.color_overrides,
+            compare_color_overrides,
+            cancelled: cancelled.clone(),
+        };
+
+        let (res_sender, promise) = Promise::new();
+        let sent = self
+            .cmd_sender
+            .try_send(VSCommandMsg {
+                res_sender,
+                cmd: VSCommand::Frame(fetch_image_state),
+                egui_ctx: ctx.clone(),
+            })
+            .is_ok();
+
+        // The promise would never resolve if the command was dropped
+        if sent {
+            output.frame_request = Some(FrameRequest {
+                promise,
+                frame_no: state.cur_frame_no,
+                reprocess,
+                requested_at: Instant::now(),
+                cancelled,
+            });
+        }
+
+        sent
+    }
+
+    /// Returns the compared output key and whether it only needs reprocessing
+    fn compare_output_needs_render(&mut self) -> Option<(i32, bool)> {
+        let key = self.state.compare_output;
+
+        if !self.state.compare_mode.paints_both() || key == self.state.cur_output {
+            return None;
+        }
+
+        let cur_frame_no = self.state.cur_frame_no;
+        let output = self.outputs.get_mut(&key)?;
+
+        if cur_frame_no >= output.vsoutput.node_info.num_frames {
+            return None;
+        }
+
+        let same_frame = output
+            .rendered_frame
+            .as_ref()
+            .is_some_and(|pf| pf.read().frame_no == cur_frame_no);
+
+        let requested = self.compare_requested == Some((key, cur_frame_no));
+
+        if !same_frame && !requested {
+            if !output.supersede_request(cur_frame_no) {
+                return None;
             }
+
+            self.compare_requested = Some((key, cur_frame_no));
+            output.force_reprocess = false;
+
+            Some((key, false))
+        } else if same_frame && output.force_reprocess && output.frame_request.is_none() {
+            output.force_reprocess = false;
+
+            Some((key, true))
+        } else {
+            None
+        }
+    }
+
+    /// Size available to each displayed image
+    pub fn canvas_size(&self) -> Vec2 {
+        let mut size = self.available_size;
+
+        if self.state.compare_mode == PreviewCompareMode::SideBySide {
+            size.x = (size.x / 2.0).floor();
         }
 
-        Ok(())
+        size
     }
 
+    /// Polls the frame promises of every output, the outputs render independently
     pub fn check_rerender_finish(&mut self, ctx: &egui::Context) -> Result<()> {
-        if let Some(mut promise_mutex) = self.frame_promise.try_lock() {
-            let mut updated_tex = false;
-
-            if let Some(promise) = &*promise_mutex
-                && let Some(PreviewerResponse::Frame(Some(rendered_frame))) = promise.ready()
+        for (key, output) in self.outputs.iter_mut() {
+            // Dropped, a newer frame was requested
+            if output
+                .superseded_request
+                .as_ref()
+                .is_some_and(|req| req.promise.ready().is_some())
             {
-                // Block as it's supposed to be ready
-                let pf = rendered_frame.read();
+                output.superseded_request = None;
+            }
 
-                if let Some(mut tex_mutex) = pf.texture.try_lock() {
-                    let output = self
-                        .outputs
-                        .get_mut(&self.state.cur_output)
-                        .ok_or_else(|| anyhow!("current_output_mut: Invalid current output key"))?;
+            let Some(req) = &output.frame_request else {
+                continue;
+            };
+
+            match req.promise.ready() {
+                Some(PreviewerResponse::Frame(Some(rendered_frame))) => {
+                    let rendered_frame = rendered_frame.clone();
+
+                    // Block as it's supposed to be ready
+                    let pf = rendered_frame.read();
+
+                    let Some(mut tex_mutex) = pf.texture.try_lock() else {
+                        continue;
+                    };
 
                     // Set PreviewFrame from what the promise returned
                     output.rendered_frame = Some(rendered_frame.clone());
@@ -388,52 +602,38 @@ impl VSPreviewer {
                     }
 
                     // Update last output once the new frame is rendered
-                    self.last_output_key = output.vsoutput.index;
-
-                    updated_tex = true;
-                };
+                    if *key == self.state.cur_output {
+                        self.last_output_key = *key;
+                    }
+                }
+                // Failed without a previous frame to display
+                Some(_) => (),
+                None => continue,
             }
 
-            if updated_tex {
-                *promise_mutex = None;
-            }
+            output.frame_request = None;
         }
 
         Ok(())
     }
 
-    pub fn get_current_frame(&self) -> Result<Option<VSPreviewFrame>> {
-        if !self.outputs.is_empty() {
-            let output = self
-                .outputs
-                .get(&self.state.cur_output)
-                .ok_or_else(|| anyhow!("get_current_Frame: Invalid current output key"))?;
-            Ok(output.rendered_frame.clone())
-        } else {
-            Ok(None)
-        }
-    }
-
     pub fn get_preview_image(
         ctx: Context,
         script: Arc<Mutex<PreviewedScript>>,
         fetch_image_state: FetchImageState,
     ) -> Result<Option<VSPreviewFrame>> {
         let FetchImageState {
-            frame_mutex,
             state,
             pf,
             reprocess,
             win_size,
+            color_overrides,
+            compare_color_overrides,
+            cancelled,
         } = fetch_image_state;
 
-        // This is fine because only one promise may be executing at a time
-        let mut script_mutex = script.lock();
-
         let have_existing_frame = pf.is_some();
 
-        let _lock = frame_mutex.lock();
-
         // Reuse existing image, process and recreate texture
         let pf = if reprocess && have_existing_frame {
             // Verified above, cannot panic
@@ -441,51 +641,81 @@ impl VSPreviewer {
 
             // Force blocking as we need to reprocess the image
             let mut existing_frame = pf.write();
-            let image = &existing_frame.vsframe.image;
-            let image_size = Vec2::from([image.width() as f32, image.height() as f32]);
 
-            if Self::state_needs_processing(&state, &image_size, &win_size) {
-                // Reprocess and update image for painting
-                existing_frame.processed_image =
-                    Some(Self::process_image(image, &state, &win_size)?);
-            } else {
-                existing_frame.processed_image = None;
-            }
+            // Reprocess and update image for painting
+            let (processed_image, source_rect) =
+                Self::display_image(&existing_frame.vsframe, &state, &win_size)?;
+            existing_frame.processed_image = processed_image;
+            existing_frame.source_rect = source_rect;
 
             Some(pf.clone())
         } else {
-            // Request new frame, process and recreate image for painting
-            let vsframe_res = script_mutex.get_frame(
-                state.cur_output,
-                state.cur_frame_no,
-                &state.frame_transform_opts,
-            );
-            script_mutex.add_vs_error(&vsframe_res);
+            // Superseded before the frame was even requested
+            if cancelled.load(Ordering::Acquire) {
+                return Ok(None);
+            }
 
-            if let Ok(vsframe) = vsframe_res {
-                let image_size =
-                    Vec2::from([vsframe.image.width() as f32, vsframe.image.height() as f32]);
+            let diff_output = (state.compare_mode == PreviewCompareMode::Difference
+                && state.compare_output != state.cur_output)
+                .then_some(state.compare_output);
+
+            // Request new frames, only locking the script until VS has them queued
+            let (pending, diff_pending) = {
+                let script_mutex = script.lock();
+                script_mutex.set_frame_cache_limit(state.frame_cache_size as usize * 1024 * 1024);
+
+                let pending = script_mutex.request_frame(
+                    state.cur_output,
+                    state.cur_frame_no,
+                    &state.frame_transform_opts,
+                    &color_overrides,
+                );
+                let diff_pending = diff_output.map(|output| {
+                    script_mutex.request_frame(
+                        output,
+                        state.cur_frame_no,
+                        &state.frame_transform_opts,
+                        &compare_color_overrides,
+                    )
+                });
+
+                (pending, diff_pending)
+            };
 
-                let processed_image =
-                    if Self::state_needs_processing(&state, &image_size, &win_size) {
-                        Some(Self::process_image(&vsframe.image, &state, &win_size)?)
-                    } else {
-                        None
-                    };
+            let vsframe_res = pending.and_then(PendingFrame::wait);
+            script.lock().add_vs_error(&vsframe_res);
 
-                let new_pf = if let Some(existing_frame) = pf {
-                    let mut pf = existing_frame.write();
-                    pf.vsframe = vsframe;
-                    pf.processed_image = processed_image;
+            // Still rendered and cached by VS, but not worth processing
+            if cancelled.load(Ordering::Acquire) {
+                return Ok(None);
+            }
 
-                    existing_frame.clone()
-                } else {
-                    Arc::new(RwLock::new(PreviewFrame {
-                        vsframe,
-                        processed_image,
-                        texture: Mutex::new(None),
-                    }))
-                };
+            if let Ok(mut vsframe) = vsframe_res {
+                let high_precision = state.frame_transform_opts.high_precision;
+                state.tonemap.apply(&mut vsframe, high_precision);
+
+                if let Some(diff_pending) = diff_pending {
+                    let diff_res =
+                        diff_pending
+                            .and_then(PendingFrame::wait)
+                            .and_then(|mut other| {
+                                state.tonemap.apply(&mut other, high_precision);
+                                difference_frame(&mut vsframe, &other, &state)
+                            });
+                    script.lock().add_vs_error(&diff_res);
+                }
+
+                let (processed_image, source_rect) =
+                    Self::display_image(&vsframe, &state, &win_size)?;
+
+                // Not written to the displayed frame, other requests may be in flight for the output
+                let new_pf = Arc::new(RwLock::new(PreviewFrame {
+                    vsframe,
+                    frame_no: state.cur_frame_no,
+                    processed_image,
+                    source_rect,
+                    texture: Mutex::new(None),
+                }));
 
                 Some(new_pf)
             } else {
@@ -499,27 +729,78 @@ impl VSPreviewer {
         Ok(pf)
     }
 
-    pub fn save_screenshot(&self) -> Result<()> {
+    /// Composites the alpha if available, then processes the image for painting.
+    /// Returns `None` when the original image can be painted as is,
+    /// along with the region of the original image that is painted.
+    pub fn display_image(
+        vsframe: &VSFrame,
+        state: &PreviewState,
+        win_size: &Vec2,
+    ) -> Result<(Option<DynamicImage>, Rect)> {
+        let composited = vsframe
+            .alpha
+            .as_ref()
+            .map(|alpha| composite_alpha(&vsframe.image, alpha, state));
+
+        let image = composited.as_ref().unwrap_or(&vsframe.image);
+        let image_size = Vec2::from([image.width() as f32, image.height() as f32]);
+
+        if Self::state_needs_processing(state, &image_size, win_size) {
+            let (processed, source_rect) = Self::process_image(image, state, win_size)?;
+
+            Ok((Some(processed), source_rect))
+        } else {
+            Ok((composited, Rect::from_min_size(Pos2::ZERO, image_size)))
+        }
+    }
+
+    pub fn save_screenshot(&self) -> Result<PathBuf> {
+        self.save_output_screenshot(self.state.cur_output, None)
+    }
+
+    /// Saves the rendered frame of the output, or only a region of the clip
+    pub fn save_output_screenshot(
+        &self,
+        output: i32,
+        region: Option<RegionCrop>,
+    ) -> Result<PathBuf> {
         if let Some(script) = self.script.try_lock() {
             let mut save_path = script.get_script_dir();
 
-            let screen_file = format!(
-                "vspreview-rs_out{}_{}.png",
-                self.state.cur_output, self.state.cur_frame_no
-            );
+            let mut screen_file = format!("vspreview-rs_out{}_{}", output, self.state.cur_frame_no);
+            if let Some(crop) = region {
+                write!(
+                    screen_file,
+                    "_{}x{}+{}+{}",
+                    crop.width, crop.height, crop.left, crop.top
+                )?;
+            }
+            screen_file.push_str(".png");
             save_path.push(screen_file);
 
             let output = self
                 .outputs
-                .get(&self.state.cur_output)
-                .ok_or_else(|| anyhow!("save_screenshot: Invalid current output key"))?;
+                .get(&output)
+                .ok_or_else(|| anyhow!("save_screenshot: Invalid output key"))?;
             if let Some(pf) = &output.rendered_frame {
                 let pf = pf.read();
 
-                // Shouldn't fail at this point
-                pf.vsframe
-                    .image
-                    .save_with_format(&save_path, image::ImageFormat::Png)?;
+                if let Some(crop) = region {
+                    // Viewed planes can be subsampled
+                    let (ssw, ssh) = pf.vsframe.plane.map_or((0, 0), |plane| plane.subsampling);
+
+                    pf.vsframe
+                        .cropped(
+                            crop.left >> ssw,
+                            crop.top >> ssh,
+                            crop.width >> ssw,
+                            crop.height >> ssh,
+                        )
+                        .save_png(&save_path)?;
+                } else {
+                    // Shouldn't fail at this point
+                    pf.vsframe.save_png(&save_path)?;
+                }
             } else {
                 bail!("There is no rendered frame for the current output");
             }
@@ -529,16 +810,16 @@ impl VSPreviewer {
                 .ok_or_else(|| anyhow!("Invalid UTF-8 save path"))?;
 
             script.send_debug_message(format!("Screenshot saved to {}", path_str))?;
+
+            Ok(save_path)
         } else {
             bail!("The script is busy rendering a frame, try again later");
         }
-
-        Ok(())
     }
 
     // Returns fixed pixel based and normalized translation vectors
     pub fn fix_translation_bounds(&self, image_size: &Vec2, new_translate: &Vec2) -> (Vec2, Vec2) {
-        let win_size = self.available_size;
+        let win_size = self.canvas_size();
 
         // Updated zoom factor
         // We need the new zoom factor to be able to correct invalid translations
@@ -588,15 +869,14 @@ impl VSPreviewer {
             .ok_or_else(|| anyhow!("output_needs_rerender: Invalid current output key"))?;
 
         // Update translate values
-        let new_node = &new.vsoutput.node_info;
-        let new_size = Vec2::from([new_node.width as f32, new_node.height as f32]);
+        let new_size = new.size();
 
         // Scale normalized coords back to pixels
         self.state.translate_changed = true;
         self.state.translate = crate::utils::translate_norm_to_pixels(
             &self.state.translate_norm,
             &new_size,
-            &self.available_size,
+            &self.canvas_size(),
             self.state.zoom_factor,
         );
 
@@ -614,21 +894,19 @@ impl VSPreviewer {
             return Ok(false);
         }
 
-        let info = {
+        let image_size = {
             let output = self.outputs.get(&self.state.cur_output).ok_or_else(|| {
                 anyhow!("correct_translate_for_current_output: Invalid current output key")
             })?;
-            output.vsoutput.node_info.clone()
+            output.size()
         };
-
-        let image_size = Vec2::from([info.width as f32, info.height as f32]);
         let old_translate = self.state.translate;
 
         let new_translate = if normalized {
             crate::utils::translate_norm_to_pixels(
                 &new_translate,
                 &image_size,
-                &self.available_size,
+                &self.canvas_size(),
                 self.state.zoom_factor,
             )
         } else {
@@ -674,10 +952,7 @@ impl VSPreviewer {
     // Can only be called when an output is selected
     pub fn fetch_original_props(&mut self, ctx: &egui::Context) {
         if let Some(mut promise_mutex) = self.original_props_promise.try_lock() {
-            let fetch_props_state = FetchPropsState {
-                frame_mutex: self.frame_promise.clone(),
-                state: self.state,
-            };
+            let fetch_props_state = FetchPropsState { state: self.state };
 
             let (res_sender, new_promise) = Promise::new();
             *promise_mutex = Some(new_promise);
@@ -773,7 +1048,17 @@ impl VSPreviewer {
 
     pub fn check_promise_callbacks(&mut self, ctx: &egui::Context) -> Result<()> {
         // Initial callback
-        self.check_reload_finish()?;
+        self.check_reload_finish(ctx)?;
+
+        // Script modified on disk
+        if self.reload_data.is_none()
+            && self
+                .script_watcher
+                .as_ref()
+                .is_some_and(ScriptWatcher::take_changed)
+        {
+            self.reload(ctx.clone());
+        }
 
         // Poll new requested frame, replace old if ready
         self.check_rerender_finish(ctx)?;
@@ -782,6 +1067,14 @@ impl VSPreviewer {
         self.check_original_props_finish()?;
 
         self.check_misc_finish(ctx);
+        self.check_bookmarks_finish();
+        self.check_scopes(ctx);
+        self.check_prop_plot_finish();
+        self.check_prop_search_finish();
+        self.check_metrics_finish();
+        self.check_ipc_requests(ctx);
+
+        self.check_playback(ctx)?;
 
         // We want a new frame
         // Previously rendering frames must have completed to request a new one
@@ -790,6 +1083,63 @@ impl VSPreviewer {
         Ok(())
     }
 
+    /// Advances the frame while playing, and requests the upcoming frames ahead
+    pub fn check_playback(&mut self, ctx: &egui::Context) -> Result<()> {
+        if !self.playback.playing || self.outputs.is_empty() || self.reload_data.is_some() {
+            return Ok(());
+        }
+
+        let cur_output = self.state.cur_output;
+        let output = self
+            .outputs
+            .get_mut(&cur_output)
+            .ok_or_else(|| anyhow!("check_playback: Invalid current output key"))?;
+        let num_frames = output.vsoutput.node_info.num_frames;
+        let frame_duration = output.frame_duration_secs();
+        let color_overrides = output.color_overrides;
+
+        // Only move on once the previous frame is displayed
+        if output.frame_request.is_none()
+            && !self.rerender
+            && let Some(target) =
+                self.playback
+                    .next_frame(self.state.cur_frame_no, num_frames, frame_duration)
+        {
+            output.last_frame_no = self.state.cur_frame_no;
+            self.state.cur_frame_no = target;
+            self.rerender = true;
+        }
+
+        // Not blocking, the requests are async
+        let script = self.script.clone();
+        if let Some(script) = script.try_lock() {
+            let frames = self
+                .playback
+                .frames_to_prefetch(self.state.cur_frame_no, num_frames);
+
+            if !frames.is_empty() {
+                let res = script.prefetch_frames(
+                    cur_output,
+                    &frames,
+                    &self.state.frame_transform_opts,
+                    &color_overrides,
+                );
+
+                if let Ok(pending) = res {
+                    self.playback.add_prefetched(pending);
+                } else {
+                    self.add_error("preview", &res);
+                }
+            }
+        }
+
+        if self.playback.playing {
+            ctx.request_repaint_after(self.playback.time_to_next_frame(frame_duration));
+        }
+
+        Ok(())
+    }
+
     pub fn add_error<T>(&mut self, key: &'static str, res: &Result<T>) {
         if let Err(e) = res {
             if let Some(list) = self.errors.get_mut(key) {
@@ -846,6 +1196,467 @@ impl VSPreviewer {
         }
     }
 
+    /// Seeks to the next or previous bookmark, returns whether to rerender
+    pub fn seek_bookmark(&mut self, forward: bool) -> Result<bool> {
+        let current = self.state.cur_frame_no;
+        let target = if forward {
+            self.bookmarks.next(current)
+        } else {
+            self.bookmarks.previous(current)
+        };
+
+        let output = self
+            .outputs
+            .get_mut(&self.state.cur_output)
+            .ok_or_else(|| anyhow!("seek_bookmark: Invalid current output key"))?;
+
+        match target.filter(|frame_no| *frame_no < output.vsoutput.node_info.num_frames) {
+            Some(frame_no) => {
+                output.last_frame_no = current;
+                self.state.cur_frame_no = frame_no;
+
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn current_framerate(&self) -> Option<VSFramerate> {
+        self.outputs
+            .get(&self.state.cur_output)
+            .and_then(|output| output.vsoutput.node_info.framerate)
+    }
+
+    pub fn export_bookmarks(&mut self, ctx: &egui::Context) {
+        if self.bookmarks_promise.is_some() {
+            return;
+        }
+
+        let format = self.state.bookmark_format;
+        let res = self.bookmarks.export(format, self.current_framerate());
+
+        match res {
+            Ok(contents) => {
+                let (res_sender, new_promise) = Promise::new();
+                let sent = self
+                    .cmd_sender
+                    .try_send(VSCommandMsg {
+                        res_sender,
+                        cmd: VSCommand::ExportBookmarks(contents, format),
+                        egui_ctx: ctx.clone(),
+                    })
+                    .is_ok();
+
+                self.set_bookmarks_promise(sent, new_promise);
+            }
+            Err(_) => self.add_error("bookmarks", &res),
+        }
+    }
+
+    pub fn import_bookmarks(&mut self, ctx: &egui::Context) {
+        if self.bookmarks_promise.is_some() {
+            return;
+        }
+
+        let (res_sender, new_promise) = Promise::new();
+        let sent = self
+            .cmd_sender
+            .try_send(VSCommandMsg {
+                res_sender,
+                cmd: VSCommand::ImportBookmarks(self.current_framerate()),
+                egui_ctx: ctx.clone(),
+            })
+            .is_ok();
+
+        self.set_bookmarks_promise(sent, new_promise);
+    }
+
+    /// The promise is only kept when the command was sent
+    fn set_bookmarks_promise(&mut self, sent: bool, promise: Promise<PreviewerResponse>) {
+        if sent {
+            self.bookmarks_promise = Some(promise);
+        } else {
+            let res: Result<()> = Err(anyhow!("The VS worker is busy, try again"));
+            self.add_error("bookmarks", &res);
+        }
+    }
+
+    pub fn check_bookmarks_finish(&mut self) {
+        let Some(promise) = self.bookmarks_promise.take() else {
+            return;
+        };
+
+        match promise.try_take() {
+            Ok(PreviewerResponse::Bookmarks(Ok(Some(mut frames)))) => {
+                // Frames past the end of the current output are dropped
+                if let Some(output) = self.outputs.get(&self.state.cur_output) {
+                    let num_frames = output.vsoutput.node_info.num_frames;
+                    frames.retain(|frame_no| *frame_no < num_frames);
+                }
+
+                self.bookmarks.replace(frames);
+            }
+            Ok(PreviewerResponse::Bookmarks(res @ Err(_))) => self.add_error("bookmarks", &res),
+            Ok(_) => (),
+            Err(promise) => self.bookmarks_promise = Some(promise),
+        }
+    }
+
+    /// Computes the scopes of the displayed frame on the worker, when it changed
+    pub fn check_scopes(&mut self, ctx: &egui::Context) {
+        if let Some((key, promise)) = self.scopes.promise.take() {
+            match promise.try_take() {
+                Ok(PreviewerResponse::Scopes(images)) => {
+                    // Not retried on errors, until the frame changes
+                    self.scopes.key = Some(key);
+
+                    if let Some(images) = images {
+                        self.scopes.set_images(ctx, images);
+                    }
+                }
+                Ok(_) => (),
+                Err(promise) => {
+                    self.scopes.promise = Some((key, promise));
+                    return;
+                }
+            }
+        }
+
+        let options = self.state.scopes;
+        let output = self.state.cur_output;
+
+        let Some(pf) = self
+            .outputs
+            .get(&output)
+            .and_then(|o| o.rendered_frame.clone())
+            .filter(|_| options.show && self.reload_data.is_none())
+        else {
+            return;
+        };
+
+        let key = ScopesKey {
+            output,
+            frame_no: pf.read().frame_no,
+            frame_ptr: Arc::as_ptr(&pf) as usize,
+            settings: options.settings,
+        };
+
+        if self.scopes.key == Some(key) {
+            return;
+        }
+
+        let (res_sender, promise) = Promise::new();
+        let sent = self
+            .cmd_sender
+            .try_send(VSCommandMsg {
+                res_sender,
+                cmd: VSCommand::Scopes(FetchScopesState { key, pf }),
+                egui_ctx: ctx.clone(),
+            })
+            .is_ok();
+
+        // Worker busy, retried on the next update
+        if sent {
+            self.scopes.promise = Some((key, promise));
+        }
+    }
+
+    /// Scans the prop over the frame range of the current output
+    pub fn start_prop_plot(&mut self, ctx: &egui::Context) {
+        let plot = &self.prop_plot;
+        if plot.scanning() {
+            return;
+        }
+
+        let res = if plot.key.is_empty() {
+            Err(anyhow!("No prop to plot"))
+        } else if plot.start > plot.end {
+            Err(anyhow!("The first frame is after the last frame"))
+        } else {
+            Ok(())
+        };
+        if res.is_err() {
+            return self.add_error("prop_plot", &res);
+        }
+
+        let data = Arc::new(Mutex::new(PropPlotData {
+            output: self.state.cur_output,
+            key: plot.key.clone(),
+            start: plot.start,
+            end: plot.end,
+            step: plot.step.max(1),
+            values: Vec::new(),
+        }));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let (res_sender, promise) = Promise::new();
+        let sent = self
+            .cmd_sender
+            .try_send(VSCommandMsg {
+                res_sender,
+                cmd: VSCommand::PropPlot(FetchPropPlotState {
+                    data: data.clone(),
+                    cancelled: cancelled.clone(),
+                }),
+                egui_ctx: ctx.clone(),
+            })
+            .is_ok();
+
+        if sent {
+            self.prop_plot.data = Some(data);
+            self.prop_plot.scan = Some(PropPlotScan { cancelled, promise });
+        } else {
+            let res: Result<()> = Err(anyhow!("The VS worker is busy, try again"));
+            self.add_error("prop_plot", &res);
+        }
+    }
+
+    pub fn export_prop_plot(&mut self, ctx: &egui::Context, format: PropPlotFormat) {
+        if self.prop_plot.export_promise.is_some() {
+            return;
+        }
+        let Some(data) = &self.prop_plot.data else {
+            return;
+        };
+
+        let res = data.lock().export(format);
+        let contents = match res {
+            Ok(contents) => contents,
+            Err(_) => return self.add_error("prop_plot", &res),
+        };
+
+        let (res_sender, new_promise) = Promise::new();
+        let sent = self
+            .cmd_sender
+            .try_send(VSCommandMsg {
+                res_sender,
+                cmd: VSCommand::ExportPropPlot(contents, format),
+                egui_ctx: ctx.clone(),
+            })
+            .is_ok();
+
+        if sent {
+            self.prop_plot.export_promise = Some(new_promise);
+        } else {
+            let res: Result<()> = Err(anyhow!("The VS worker is busy, try again"));
+            self.add_error("prop_plot", &res);
+        }
+    }
+
+    pub fn check_prop_plot_finish(&mut self) {
+        if let Some(scan) = self.prop_plot.scan.take() {
+            match scan.promise.try_take() {
+                Ok(PreviewerResponse::PropPlot(res)) => self.add_error("prop_plot", &res),
+                Ok(_) => (),
+                Err(promise) => {
+                    self.prop_plot.scan = Some(PropPlotScan {
+                        cancelled: scan.cancelled,
+                        promise,
+                    })
+                }
+            }
+        }
+
+        if let Some(promise) = self.prop_plot.export_promise.take() {
+            match promise.try_take() {
+                Ok(PreviewerResponse::PropPlot(res)) => self.add_error("prop_plot", &res),
+                Ok(_) => (),
+                Err(promise) => self.prop_plot.export_promise = Some(promise),
+            }
+        }
+    }
+
+    /// Scans the current output for the frames matching the search expression
+    pub fn start_prop_search(&mut self, ctx: &egui::Context) {
+        if self.prop_search.scanning() {
+            return;
+        }
+
+        let expr = match PropExpr::parse(&self.prop_search.query) {
+            Ok(expr) => expr,
+            res @ Err(_) => return self.add_error("prop_search", &res),
+        };
+        let Some(output) = self.outputs.get(&self.state.cur_output) else {
+            return;
+        };
+
+        let data = Arc::new(Mutex::new(PropSearchData {
+            output: self.state.cur_output,
+            query: self.prop_search.query.clone(),
+            num_frames: output.vsoutput.node_info.num_frames,
+            ..Default::default()
+        }));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let (res_sender, promise) = Promise::new();
+        let sent = self
+            .cmd_sender
+            .try_send(VSCommandMsg {
+                res_sender,
+                cmd: VSCommand::PropSearch(FetchPropSearchState {
+                    expr,
+                    data: data.clone(),
+                    cancelled: cancelled.clone(),
+                }),
+                egui_ctx: ctx.clone(),
+            })
+            .is_ok();
+
+        if sent {
+            self.prop_search.data = Some(data);
+            self.prop_search.scan = Some(PropSearchScan { cancelled, promise });
+        } else {
+            let res: Result<()> = Err(anyhow!("The VS worker is busy, try again"));
+            self.add_error("prop_search", &res);
+        }
+    }
+
+    pub fn check_prop_search_finish(&mut self) {
+        let Some(scan) = self.prop_search.scan.take() else {
+            return;
+        };
+
+        match scan.promise.try_take() {
+            Ok(PreviewerResponse::PropSearch(res)) => self.add_error("prop_search", &res),
+            Ok(_) => (),
+            Err(promise) => {
+                self.prop_search.scan = Some(PropSearchScan {
+                    cancelled: scan.cancelled,
+                    promise,
+                })
+            }
+        }
+    }
+
+    /// Returns whether a matching frame was found
+    pub fn seek_prop_match(&mut self, forward: bool) -> Result<bool> {
+        let current = self.state.cur_frame_no;
+        let target = self
+            .prop_search
+            .next_match(self.state.cur_output, current, forward);
+
+        let output = self
+            .outputs
+            .get_mut(&self.state.cur_output)
+            .ok_or_else(|| anyhow!("seek_prop_match: Invalid current output key"))?;
+
+        match target.filter(|frame_no| *frame_no < output.vsoutput.node_info.num_frames) {
+            Some(frame_no) => {
+                output.last_frame_no = current;
+                self.state.cur_frame_no = frame_no;
+
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Seeks the current output, frames past its end are ignored
+    pub fn seek_to(&mut self, frame_no: u32) {
+        let current = self.state.cur_frame_no;
+
+        if let Some(output) = self
+            .outputs
+            .get_mut(&self.state.cur_output)
+            .filter(|output| frame_no < output.vsoutput.node_info.num_frames)
+            .filter(|_| frame_no != current)
+        {
+            output.last_frame_no = current;
+            self.state.cur_frame_no = frame_no;
+
+            self.rerender = true;
+        }
+    }
+
+    /// Computes the metrics between the two outputs over the frame range
+    pub fn start_metrics(&mut self, ctx: &egui::Context) {
+        let metrics = &self.metrics;
+        if metrics.scanning() {
+            return;
+        }
+
+        let num_frames = |output| {
+            self.outputs
+                .get(&output)
+                .map(|o: &PreviewOutput| o.vsoutput.node_info.num_frames)
+        };
+
+        let res = match (num_frames(metrics.reference), num_frames(metrics.distorted)) {
+            (Some(_), Some(_)) if metrics.reference == metrics.distorted => {
+                Err(anyhow!("The reference and distorted outputs are the same"))
+            }
+            (Some(a), Some(b)) if metrics.end >= a.min(b) => Err(anyhow!(
+                "Frame {} is past the end of the outputs",
+                metrics.end
+            )),
+            (Some(_), Some(_)) if metrics.start > metrics.end => {
+                Err(anyhow!("The first frame is after the last frame"))
+            }
+            (Some(_), Some(_)) => Ok(()),
+            _ => Err(anyhow!("Invalid metrics outputs")),
+        };
+        if res.is_err() {
+            return self.add_error("metrics", &res);
+        }
+
+        let data = Arc::new(Mutex::new(MetricsData {
+            reference: metrics.reference,
+            distorted: metrics.distorted,
+            start: metrics.start,
+            end: metrics.end,
+            ..Default::default()
+        }));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let (res_sender, promise) = Promise::new();
+        let sent = self
+            .cmd_sender
+            .try_send(VSCommandMsg {
+                res_sender,
+                cmd: VSCommand::Metrics(FetchMetricsState {
+                    data: data.clone(),
+                    cancelled: cancelled.clone(),
+                }),
+                egui_ctx: ctx.clone(),
+            })
+            .is_ok();
+
+        if sent {
+            self.metrics.data = Some(data);
+            self.metrics.scan = Some(MetricsScan { cancelled, promise });
+        } else {
+            let res: Result<()> = Err(anyhow!("The VS worker is busy, try again"));
+            self.add_error("metrics", &res);
+        }
+    }
+
+    pub fn check_metrics_finish(&mut self) {
+        let Some(scan) = self.metrics.scan.take() else {
+            return;
+        };
+
+        match scan.promise.try_take() {
+            Ok(PreviewerResponse::Metrics(res)) => self.add_error("metrics", &res),
+            Ok(_) => (),
+            Err(promise) => {
+                self.metrics.scan = Some(MetricsScan {
+                    cancelled: scan.cancelled,
+                    promise,
+                })
+            }
+        }
+    }
+
+    /// Starts the control socket, if enabled from the command line
+    pub fn start_ipc_server(&mut self, ctx: &egui::Context) {
+        if let Some(address) = self.ipc_address.take() {
+            let res = IpcServer::new(address, ctx.clone());
+            self.add_error("ipc", &res);
+
+            self.ipc_server = res.ok();
+        }
+    }
+
     pub fn init_transforms(&mut self) {
         let mut transforms = self.transforms.lock();
 
@@ -867,4 +1678,24 @@ impl VSPreviewer {
                 .ok();
         }
     }
+
+    pub fn update_script_watcher(&mut self, ctx: &egui::Context) {
+        if !self.state.auto_reload || self.auto_reload_disabled {
+            self.script_watcher = None;
+            return;
+        }
+
+        let script_file = self.script.lock().get_script_file();
+
+        let res = match self.script_watcher.as_mut() {
+            Some(watcher) => watcher.watch_script(&script_file),
+            None => ScriptWatcher::new(ctx.clone()).and_then(|mut watcher| {
+                watcher.watch_script(&script_file)?;
+                self.script_watcher = Some(watcher);
+
+                Ok(())
+            }),
+        };
+        self.add_error("callbacks", &res);
+    }
 }
@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow, bail};
+use itertools::Itertools;
+
+use crate::app::TonemapOptions;
+use crate::vs_handler::{PreviewedScript, VSColorOverrides, VSTransformOptions};
+
+pub const DEFAULT_NAME_TEMPLATE: &str = "{script}_out{output}_{frame}.png";
+
+/// Renders frames to PNG files without opening a window.
+/// The frames are converted with the default settings of the window, HDR is tonemapped.
+pub struct FrameExporter {
+    pub out_dir: PathBuf,
+    pub frames: Vec<u32>,
+    /// All outputs when empty
+    pub outputs: Vec<i32>,
+    /// Supports `{script}`, `{output}`, `{frame}` and `{frame:N}`
+    pub name_template: String,
+}
+
+impl FrameExporter {
+    pub fn new(
+        out_dir: PathBuf,
+        frames: &[String],
+        outputs: Vec<i32>,
+        name_template: String,
+    ) -> Result<Self> {
+        let frames = parse_frames(frames)?;
+
+        if frames.is_empty() {
+            bail!("No frames to export, use `--frames`");
+        }
+
+        // Invalid widths are reported before rendering
+        replace_frame(&name_template, 0, 1)?;
+
+        Ok(Self {
+            out_dir,
+            frames,
+            outputs,
+            name_template,
+        })
+    }
+
+    pub fn export(&self, script: &mut PreviewedScript) -> Result<()> {
+        let script_outputs = script.reload()?;
+
+        let outputs = if self.outputs.is_empty() {
+            script_outputs.keys().copied().sorted().collect()
+        } else {
+            self.outputs.clone()
+        };
+
+        // Nothing is written unless every frame can be exported
+        let num_frames = outputs
+            .iter()
+            .map(|output| {
+                let num_frames = script_outputs
+                    .get(output)
+                    .ok_or_else(|| anyhow!("Output {output} does not exist"))?
+                    .node_info
+                    .num_frames;
+
+                if let Some(frame_no) = self.frames.iter().find(|n| **n >= num_frames) {
+                    bail!(
+                        "Frame {frame_no} is out of range for output {output} ({num_frames} frames)"
+                    );
+                }
+
+                Ok(num_frames)
+            })
+            .collect::<Result<Vec<u32>>>()?;
+
+        let script_name = script
+            .get_script_file()
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("vspreview-rs")
+            .to_string();
+
+        std::fs::create_dir_all(&self.out_dir)?;
+
+        let tonemap = TonemapOptions::default();
+        let opts = VSTransformOptions {
+            linearize_hdr: tonemap.enabled,
+            ..Default::default()
+        };
+        let overrides = VSColorOverrides::default();
+
+        for (output, num_frames) in outputs.into_iter().zip(num_frames) {
+            // Sorted by name like by frame number
+            let width = num_frames.saturating_sub(1).to_string().len();
+
+            for &frame_no in &self.frames {
+                let mut vsframe = script.get_frame(output, frame_no, &opts, &overrides)?;
+                tonemap.apply(&mut vsframe, opts.high_precision);
+
+                let mut file_name = replace_frame(&self.name_template, frame_no, width)?
+                    .replace("{script}", &script_name)
+                    .replace("{output}", &output.to_string());
+
+                if !file_name.ends_with(".png") {
+                    file_name.push_str(".png");
+                }
+
+                let path = self.out_dir.join(file_name);
+                vsframe.save_png(&path)?;
+
+                println!("{}", path.display());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces `{frame}` zero padded to `width`, and `{frame:N}` zero padded to N digits
+fn replace_frame(template: &str, frame_no: u32, width: usize) -> Result<String> {
+    let mut name = template.replace("{frame}", &format!("{frame_no:0width$}"));
+
+    while let Some(start) = name.find("{frame:") {
+        let spec_start = start + "{frame:".len();
+        let spec_len = name[spec_start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("Unclosed `{{frame:` in the name template"))?;
+
+        let spec = &name[spec_start..spec_start + spec_len];
+        let width: usize = spec
+            .parse()
+            .map_err(|_| anyhow!("Invalid frame width `{spec}` in the name template"))?;
+
+        name.replace_range(
+            start..spec_start + spec_len + 1,
+            &format!("{frame_no:0width$}"),
+        );
+    }
+
+    Ok(name)
+}
+
+/// Comma separated frame numbers or inclusive ranges, `0,100,2000-2010`
+fn parse_frames(values: &[String]) -> Result<Vec<u32>> {
+    let mut frames = Vec::new();
+
+    for value in values.iter().map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        if let Some((start, end)) = value.split_once('-') {
+            let start: u32 = start.trim().parse()?;
+            let end: u32 = end.trim().parse()?;
+
+            if end < start {
+                bail!("Invalid frame range `{value}`");
+            }
+
+            frames.extend(start..=end);
+        } else {
+            frames.push(value.parse()?);
+        }
+    }
+
+    Ok(frames.into_iter().unique().collect())
+}
@@ -7,11 +7,16 @@ use std::{path::PathBuf, sync::Arc};
 use tokio::sync::mpsc::Receiver;
 
 mod app;
+mod export;
 mod utils;
 mod vs_handler;
 
-use app::{IccProfile, PreviewerResponse, ReloadType, VSCommand, VSCommandMsg, VSPreviewer};
-use vs_handler::PreviewedScript;
+use app::{
+    Bookmarks, IccProfile, IpcAddress, Metrics, PreviewerResponse, PropPlot, PropSearch,
+    ReloadType, Scopes, VSCommand, VSCommandMsg, VSPreviewer,
+};
+use export::{DEFAULT_NAME_TEMPLATE, FrameExporter};
+use vs_handler::{PendingFrame, PreviewedScript};
 
 #[derive(Parser, Debug)]
 #[command(name = env!("CARGO_PKG_NAME"), about = "VapourSynth script previewer", author = "quietvoid", version = env!("CARGO_PKG_VERSION"))]
@@ -28,6 +33,51 @@ struct Opt {
         value_delimiter = ','
     )]
     variables: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Disable reloading the script when it or its local imports change on disk"
+    )]
+    no_auto_reload: bool,
+
+    #[arg(
+        long,
+        value_name = "SOCKET",
+        help = "Listen for line-delimited JSON commands on a Unix socket path, or a localhost TCP port or host:port"
+    )]
+    ipc: Option<IpcAddress>,
+
+    #[arg(
+        long,
+        value_hint = ValueHint::DirPath,
+        requires = "frames",
+        help = "Export frames to PNG files in this directory, without opening a window. Uses the default conversion and HDR tonemapping settings"
+    )]
+    export: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "export",
+        help = "Frames to export, numbers or inclusive ranges. Example: `0,100,2000-2010`",
+        value_delimiter = ','
+    )]
+    frames: Vec<String>,
+
+    #[arg(
+        long,
+        requires = "export",
+        help = "Outputs to export, defaults to all. Example: `0,1`",
+        value_delimiter = ','
+    )]
+    outputs: Vec<i32>,
+
+    #[arg(
+        long,
+        requires = "export",
+        default_value = DEFAULT_NAME_TEMPLATE,
+        help = "Exported file name, `{script}`, `{output}` and `{frame}` are replaced. `{frame}` is zero padded to the length of the last frame, `{frame:05}` to 5 digits"
+    )]
+    name_template: String,
 }
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 1)]
@@ -38,6 +88,16 @@ async fn main() -> Result<()> {
         bail!("Input script file does not exist!");
     }
 
+    if let Some(out_dir) = opt.export {
+        let mut script = PreviewedScript::new(opt.input, opt.variables);
+        let exporter = FrameExporter::new(out_dir, &opt.frames, opt.outputs, opt.name_template)?;
+
+        let res = exporter.export(&mut script);
+        let exit_res = script.exit();
+
+        return res.and(exit_res);
+    }
+
     let script = Arc::new(Mutex::new(PreviewedScript::new(opt.input, opt.variables)));
     let (cmd_sender, cmd_receiver) = tokio::sync::mpsc::channel(1);
 
@@ -48,7 +108,7 @@ async fn main() -> Result<()> {
         });
     }
 
-    let previewer = VSPreviewer::new(script, cmd_sender);
+    let previewer = VSPreviewer::new(script, cmd_sender, opt.no_auto_reload, opt.ipc);
     let res = eframe::run_native(
         "vspreview-rs",
         eframe::NativeOptions::default(),
@@ -80,62 +140,92 @@ pub async fn init_vs_command_loop(
                 let res = script_mutex.reload();
                 script_mutex.add_vs_error(&res);
 
-                let ret = if res.is_ok() {
-                    let outputs_res = script_mutex.get_outputs();
-                    script_mutex.add_vs_error(&outputs_res);
-
-                    let outputs = if let Ok(outputs) = outputs_res {
-                        // No output case handled by vapoursynth-rs
-                        assert!(!outputs.is_empty());
-                        Some(outputs)
-                    } else {
-                        None
-                    };
-
-                    // Not ready but we need to get the checker going
-                    egui_ctx.request_repaint();
-
-                    outputs
-                } else {
-                    None
-                };
+                // Not ready but we need to get the checker going
+                egui_ctx.request_repaint();
 
-                res_sender.send(PreviewerResponse::Reload(ret))
+                res_sender.send(PreviewerResponse::Reload(res.ok()))
             }
             VSCommand::Frame(fetch_image_state) => {
-                let ret = match VSPreviewer::get_preview_image(egui_ctx, script, fetch_image_state)
-                {
-                    Ok(preview_frame) => preview_frame,
-                    Err(e) => {
-                        // Errors here are not recoverable
-                        panic!("{}", e)
+                // Waits for VS without blocking the loop, so several frames can be in flight
+                tokio::task::spawn_blocking(move || {
+                    let res = VSPreviewer::get_preview_image(
+                        egui_ctx.clone(),
+                        script.clone(),
+                        fetch_image_state,
+                    );
+                    script.lock().add_vs_error(&res);
+
+                    // Shows the error
+                    if res.is_err() {
+                        egui_ctx.request_repaint();
                     }
-                };
 
-                res_sender.send(PreviewerResponse::Frame(ret));
+                    res_sender.send(PreviewerResponse::Frame(res.ok().flatten()));
+                });
             }
-            VSCommand::FrameProps(fetch_image_state) => {
-                let ret = if let Some(mut script_mutex) = script.try_lock() {
-                    let cur_output = fetch_image_state.state.cur_output;
-                    let cur_frame_no = fetch_image_state.state.cur_frame_no;
+            VSCommand::FrameProps(fetch_props_state) => {
+                tokio::task::spawn_blocking(move || {
+                    let cur_output = fetch_props_state.state.cur_output;
+                    let cur_frame_no = fetch_props_state.state.cur_frame_no;
 
-                    let _lock = fetch_image_state.frame_mutex.lock();
+                    // Only locked to request, rendered alongside the displayed frames
+                    let pending = script
+                        .lock()
+                        .request_original_props(cur_output, cur_frame_no);
 
-                    let props_res = script_mutex.get_original_props(cur_output, cur_frame_no);
-                    script_mutex.add_vs_error(&props_res);
+                    let props_res = pending.and_then(PendingFrame::wait);
+                    script.lock().add_vs_error(&props_res);
 
-                    if let Ok(props) = props_res {
+                    let ret = if let Ok(props) = props_res {
                         egui_ctx.request_repaint();
 
                         Some(props)
                     } else {
                         None
-                    }
-                } else {
-                    None
-                };
+                    };
 
-                res_sender.send(PreviewerResponse::Props(ret));
+                    res_sender.send(PreviewerResponse::Props(ret));
+                });
+            }
+            VSCommand::Scopes(fetch_scopes_state) => {
+                tokio::task::spawn_blocking(move || {
+                    let res = Scopes::compute(&script, fetch_scopes_state);
+                    script.lock().add_vs_error(&res);
+
+                    egui_ctx.request_repaint();
+
+                    res_sender.send(PreviewerResponse::Scopes(res.ok()));
+                });
+            }
+            VSCommand::PropPlot(fetch_plot_state) => {
+                tokio::task::spawn_blocking(move || {
+                    let res = PropPlot::compute(&script, &fetch_plot_state, &egui_ctx);
+                    egui_ctx.request_repaint();
+
+                    res_sender.send(PreviewerResponse::PropPlot(res));
+                });
+            }
+            VSCommand::ExportPropPlot(contents, format) => {
+                let res = PropPlot::export_dialog(contents, format);
+                egui_ctx.request_repaint();
+
+                res_sender.send(PreviewerResponse::PropPlot(res));
+            }
+            VSCommand::PropSearch(fetch_search_state) => {
+                tokio::task::spawn_blocking(move || {
+                    let res = PropSearch::compute(&script, &fetch_search_state, &egui_ctx);
+                    egui_ctx.request_repaint();
+
+                    res_sender.send(PreviewerResponse::PropSearch(res));
+                });
+            }
+            VSCommand::Metrics(fetch_metrics_state) => {
+                tokio::task::spawn_blocking(move || {
+                    let res = Metrics::compute(&script, &fetch_metrics_state, &egui_ctx);
+                    egui_ctx.request_repaint();
+
+                    res_sender.send(PreviewerResponse::Metrics(res));
+                });
             }
             VSCommand::ChangeScript => {
                 let path = std::env::current_dir().unwrap();
@@ -182,9 +272,23 @@ pub async fn init_vs_command_loop(
 
                 res_sender.send(PreviewerResponse::Misc(ret));
             }
+            VSCommand::ExportBookmarks(contents, format) => {
+                let res = Bookmarks::export_dialog(contents, format).map(|_| None);
+                egui_ctx.request_repaint();
+
+                res_sender.send(PreviewerResponse::Bookmarks(res));
+            }
+            VSCommand::ImportBookmarks(framerate) => {
+                let res = Bookmarks::import_dialog(framerate);
+                egui_ctx.request_repaint();
+
+                res_sender.send(PreviewerResponse::Bookmarks(res));
+            }
             VSCommand::Exit => {
                 let script_mutex = script.lock();
-                script_mutex.exit();
+                if let Err(e) = script_mutex.exit() {
+                    eprintln!("{e:?}");
+                }
 
                 res_sender.send(PreviewerResponse::Close);
 
@@ -1,58 +1,131 @@
 use std::collections::HashMap;
 
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use eframe::epaint::{Color32, ColorImage, Vec2};
 use fast_image_resize::{self as fr, ResizeAlg, ResizeOptions};
 use fr::images::Image as FrImage;
-use image::{DynamicImage, ImageBuffer};
+use image::{DynamicImage, GrayImage, ImageBuffer, Rgb32FImage, RgbImage};
 use rgb::{AsPixels, ComponentSlice};
-use vapoursynth::prelude::{ColorFamily, FrameRef};
+use vapoursynth::prelude::{ColorFamily, Component, FrameRef, SampleType};
 
-use crate::app::{PreviewState, PreviewTransforms};
+use crate::app::{CHECKERBOARD_SIZE, PreviewAlphaBackground, PreviewState, PreviewTransforms};
 
 /// `DynamicImage` from `VS::FrameRef`
-///    `ColorFamily::Gray` => `DynamicImage::ImageLuma8`
-///    `ColorFamily::RGB` => `DynamicImage::ImageRgb8`
-pub fn frame_to_dynimage(frame: &FrameRef) -> DynamicImage {
+///    `ColorFamily::Gray` => `DynamicImage::ImageLuma8` or `ImageLuma16`
+///    `ColorFamily::RGB` => `DynamicImage::ImageRgb8`, `ImageRgb16` or `ImageRgb32F`
+/// Other formats must be converted first.
+pub fn frame_to_dynimage(frame: &FrameRef) -> Result<DynamicImage> {
     let format = frame.format();
+    let plane_count = format.plane_count();
 
-    // Gray or RGB
-    assert!(matches!(
-        format.color_family(),
-        ColorFamily::Gray | ColorFamily::RGB
-    ));
-
-    let plane_count = frame.format().plane_count();
-    assert!(plane_count == 1 || plane_count == 3);
+    let supported = match format.color_family() {
+        ColorFamily::Gray => plane_count == 1,
+        ColorFamily::RGB => plane_count == 3,
+        _ => false,
+    };
+    if !supported {
+        bail!("Unsupported frame format for preview: {}", format.name());
+    }
 
     // Assumes all planes are the same resolution
+    let (w, h) = (frame.width(0) as u32, frame.height(0) as u32);
+    let invalid_size = || anyhow!("Invalid {} frame size {w}x{h}", format.name());
+
+    let image = match (format.sample_type(), format.bytes_per_sample(), plane_count) {
+        (SampleType::Integer, 1, 1) => DynamicImage::ImageLuma8(
+            ImageBuffer::from_raw(w, h, interleave_planes(frame)).ok_or_else(invalid_size)?,
+        ),
+        (SampleType::Integer, 1, _) => DynamicImage::ImageRgb8(
+            ImageBuffer::from_raw(w, h, interleave_planes(frame)).ok_or_else(invalid_size)?,
+        ),
+        (SampleType::Integer, 2, 1) => DynamicImage::ImageLuma16(
+            ImageBuffer::from_raw(w, h, interleave_planes(frame)).ok_or_else(invalid_size)?,
+        ),
+        (SampleType::Integer, 2, _) => DynamicImage::ImageRgb16(
+            ImageBuffer::from_raw(w, h, interleave_planes(frame)).ok_or_else(invalid_size)?,
+        ),
+        (SampleType::Float, 4, 3) => DynamicImage::ImageRgb32F(
+            ImageBuffer::from_raw(w, h, interleave_planes(frame)).ok_or_else(invalid_size)?,
+        ),
+        _ => bail!("Unsupported frame format for preview: {}", format.name()),
+    };
+
+    Ok(image)
+}
+
+/// Packed samples from the planes of the frame
+fn interleave_planes<T: Component + Copy>(frame: &FrameRef) -> Vec<T> {
+    let plane_count = frame.format().plane_count();
     let (w, h) = (frame.width(0), frame.height(0));
 
-    if plane_count == 1 {
-        let mut buf = ImageBuffer::new(w as u32, h as u32);
+    let mut buf = Vec::with_capacity(w * h * plane_count);
 
-        buf.enumerate_rows_mut().for_each(|(row, pixels)| {
-            let y = frame.plane_row(0, row as usize);
-            pixels.for_each(|(x, _, p)| *p = image::Luma([y[x as usize]]));
-        });
+    for row in 0..h {
+        let planes: Vec<&[T]> = (0..plane_count)
+            .map(|plane| frame.plane_row(plane, row))
+            .collect();
 
-        DynamicImage::ImageLuma8(buf)
-    } else {
-        let mut buf = ImageBuffer::new(w as u32, h as u32);
+        for x in 0..w {
+            buf.extend(planes.iter().map(|p| p[x]));
+        }
+    }
 
-        buf.enumerate_rows_mut().for_each(|(row, pixels)| {
-            let row = row as usize;
-            let r = frame.plane_row(0, row);
-            let g = frame.plane_row(1, row);
-            let b = frame.plane_row(2, row);
+    buf
+}
 
-            pixels.for_each(|(x, _, p)| {
-                let x = x as usize;
-                *p = image::Rgb([r[x], g[x], b[x]])
+/// Composites the image over the configured background using its alpha plane.
+/// Returns the alpha plane alone when only the mask is viewed.
+pub fn composite_alpha(
+    img: &DynamicImage,
+    alpha: &GrayImage,
+    state: &PreviewState,
+) -> DynamicImage {
+    if state.alpha_mask_only {
+        return DynamicImage::ImageLuma8(alpha.clone());
+    }
+
+    let (w, h) = (img.width(), img.height());
+
+    let background = |x: u32, y: u32| match state.alpha_background {
+        PreviewAlphaBackground::Checkerboard => {
+            let odd = ((x / CHECKERBOARD_SIZE) + (y / CHECKERBOARD_SIZE)) % 2 == 1;
+            if odd { [153; 3] } else { [102; 3] }
+        }
+        PreviewAlphaBackground::SolidColor => state.alpha_color,
+    };
+
+    match img {
+        DynamicImage::ImageLuma8(_) | DynamicImage::ImageRgb8(_) => {
+            let rgb = img.to_rgb8();
+
+            let buf = RgbImage::from_fn(w, h, |x, y| {
+                let bg = background(x, y);
+                let a = alpha.get_pixel(x, y)[0] as u16;
+                let src = rgb.get_pixel(x, y).0;
+
+                image::Rgb(std::array::from_fn(|i| {
+                    ((src[i] as u16 * a + bg[i] as u16 * (255 - a) + 127) / 255) as u8
+                }))
             });
-        });
 
-        DynamicImage::ImageRgb8(buf)
+            DynamicImage::ImageRgb8(buf)
+        }
+        _ => {
+            // Keep the precision of high bit depth images
+            let rgb = img.to_rgb32f();
+
+            let buf = Rgb32FImage::from_fn(w, h, |x, y| {
+                let bg = background(x, y);
+                let a = alpha.get_pixel(x, y)[0] as f32 / 255.0;
+                let src = rgb.get_pixel(x, y).0;
+
+                image::Rgb(std::array::from_fn(|i| {
+                    src[i] * a + (bg[i] as f32 / 255.0) * (1.0 - a)
+                }))
+            });
+
+            DynamicImage::ImageRgb32F(buf)
+        }
     }
 }
 
@@ -73,6 +146,18 @@ pub fn resize_fast(
         DynamicImage::ImageRgb8(rgb) => {
             FrImage::from_vec_u8(width, height, rgb.into_raw(), fr::PixelType::U8x3)?
         }
+        DynamicImage::ImageLuma16(luma) => {
+            let bytes = luma.iter().flat_map(|v| v.to_ne_bytes()).collect();
+            FrImage::from_vec_u8(width, height, bytes, fr::PixelType::U16)?
+        }
+        DynamicImage::ImageRgb16(rgb) => {
+            let bytes = rgb.iter().flat_map(|v| v.to_ne_bytes()).collect();
+            FrImage::from_vec_u8(width, height, bytes, fr::PixelType::U16x3)?
+        }
+        DynamicImage::ImageRgb32F(rgb) => {
+            let bytes = rgb.iter().flat_map(|v| v.to_ne_bytes()).collect();
+            FrImage::from_vec_u8(width, height, bytes, fr::PixelType::F32x3)?
+        }
         _ => unreachable!(),
     };
 
@@ -94,12 +179,38 @@ pub fn resize_fast(
             image::ImageBuffer::from_raw(dst_width, dst_height, dst_image.buffer().to_vec())
                 .ok_or_else(|| anyhow!("Failed resizing RGB"))?,
         ),
+        fr::PixelType::U16 => DynamicImage::ImageLuma16(
+            image::ImageBuffer::from_raw(dst_width, dst_height, u16_samples(dst_image.buffer()))
+                .ok_or_else(|| anyhow!("Failed resizing 16 bit luma"))?,
+        ),
+        fr::PixelType::U16x3 => DynamicImage::ImageRgb16(
+            image::ImageBuffer::from_raw(dst_width, dst_height, u16_samples(dst_image.buffer()))
+                .ok_or_else(|| anyhow!("Failed resizing 16 bit RGB"))?,
+        ),
+        fr::PixelType::F32x3 => DynamicImage::ImageRgb32F(
+            image::ImageBuffer::from_raw(dst_width, dst_height, f32_samples(dst_image.buffer()))
+                .ok_or_else(|| anyhow!("Failed resizing float RGB"))?,
+        ),
         _ => unreachable!(),
     };
 
     Ok(resized_img)
 }
 
+fn u16_samples(bytes: &[u8]) -> Vec<u16> {
+    bytes
+        .chunks_exact(2)
+        .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+        .collect()
+}
+
+fn f32_samples(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
 pub fn dimensions_for_window(win_size: &Vec2, orig_size: &Vec2) -> Vec2 {
     let mut size = *orig_size;
 
@@ -118,6 +229,10 @@ pub fn dimensions_for_window(win_size: &Vec2, orig_size: &Vec2) -> Vec2 {
     size
 }
 
+pub fn image_size(img: &DynamicImage) -> Vec2 {
+    Vec2::new(img.width() as f32, img.height() as f32)
+}
+
 pub fn image_to_colorimage(
     img: &DynamicImage,
     state: &PreviewState,
@@ -154,6 +269,37 @@ pub fn image_to_colorimage(
                     .collect()
             }
         }
+        // High bit depth, only reduced to 8 bits for display
+        DynamicImage::ImageLuma16(luma) => {
+            let dither = state.frame_transform_opts.enable_dithering;
+
+            luma.iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    let (x, y) = (i % size[0], i / size[0]);
+                    Color32::from_gray(quantize_to_u8(*v as f32 / 65535.0, x, y, dither))
+                })
+                .collect()
+        }
+        DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgb32F(_) => {
+            let dither = state.frame_transform_opts.enable_dithering;
+            let mut rgb = img.to_rgb32f().into_raw();
+
+            if let Some(icc) = icc {
+                let t = icc.transform_float.as_ref().unwrap();
+                t.transform_in_place(rgb.as_pixels_mut());
+            }
+
+            rgb.chunks_exact(3)
+                .enumerate()
+                .map(|(i, p)| {
+                    let (x, y) = (i % size[0], i / size[0]);
+                    let [r, g, b] = [p[0], p[1], p[2]].map(|v| quantize_to_u8(v, x, y, dither));
+
+                    Color32::from_rgb(r, g, b)
+                })
+                .collect()
+        }
         _ => unreachable!(),
     };
 
@@ -164,6 +310,23 @@ pub fn image_to_colorimage(
     }
 }
 
+/// Normalized 4x4 Bayer matrix, thresholds for ordered dithering
+const BAYER_4X4: [[f32; 4]; 4] = [
+    [0.5 / 16.0, 8.5 / 16.0, 2.5 / 16.0, 10.5 / 16.0],
+    [12.5 / 16.0, 4.5 / 16.0, 14.5 / 16.0, 6.5 / 16.0],
+    [3.5 / 16.0, 11.5 / 16.0, 1.5 / 16.0, 9.5 / 16.0],
+    [15.5 / 16.0, 7.5 / 16.0, 13.5 / 16.0, 5.5 / 16.0],
+];
+
+/// Quantizes a [0, 1] value to 8 bits, rounding or with ordered dithering
+fn quantize_to_u8(value: f32, x: usize, y: usize, dither: bool) -> u8 {
+    let threshold = if dither { BAYER_4X4[y % 4][x % 4] } else { 0.5 };
+
+    (value.clamp(0.0, 1.0) * 255.0 + threshold)
+        .floor()
+        .min(255.0) as u8
+}
+
 // Normalize from max translate value to float with range [-1, 1]
 pub fn translate_norm_coeffs(size: &Vec2, win_size: &Vec2, zoom_factor: f32) -> Vec2 {
     // Clips left and right
@@ -240,3 +403,52 @@ fn release_on_focus_lost<'a>(
         false
     }
 }
+
+/// `HH:MM:SS` with `decimals` fractional digits of seconds, truncated
+pub fn format_timestamp(nanos: u128, decimals: u32) -> String {
+    let secs = nanos / 1_000_000_000;
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+    if decimals == 0 {
+        return format!("{h:02}:{m:02}:{s:02}");
+    }
+
+    let decimals = decimals.min(9);
+    let frac = nanos % 1_000_000_000 / 10u128.pow(9 - decimals);
+
+    format!(
+        "{h:02}:{m:02}:{s:02}.{frac:0width$}",
+        width = decimals as usize
+    )
+}
+
+/// Parses `[[HH:]MM:]SS[.fraction]` to nanoseconds
+pub fn parse_timestamp(value: &str) -> Option<u128> {
+    let parts: Vec<&str> = value.trim().split(':').collect();
+
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+
+    let (secs_part, units) = parts.split_last()?;
+
+    let mut secs: u128 = 0;
+    for unit in units {
+        secs = secs * 60 + unit.trim().parse::<u128>().ok()?;
+    }
+
+    let (whole, frac) = secs_part.split_once('.').unwrap_or((secs_part, ""));
+    secs = secs * 60 + whole.trim().parse::<u128>().ok()?;
+
+    if frac.len() > 9 || !frac.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let frac_nanos = if frac.is_empty() {
+        0
+    } else {
+        frac.parse::<u128>().ok()? * 10u128.pow(9 - frac.len() as u32)
+    };
+
+    Some(secs * 1_000_000_000 + frac_nanos)
+}
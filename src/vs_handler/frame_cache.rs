@@ -0,0 +1,94 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use super::{VSColorOverrides, VSFrame, VSTransformOptions};
+
+/// Identifies a frame rendered with the same conversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameCacheKey {
+    pub output: i32,
+    pub frame_no: u32,
+    pub opts: VSTransformOptions,
+    pub overrides: VSColorOverrides,
+}
+
+/// Least recently used cache of converted frames, bounded in memory
+#[derive(Default)]
+pub struct FrameCache {
+    /// Frames with their last use
+    entries: HashMap<FrameCacheKey, (Arc<VSFrame>, u64)>,
+    /// Keys by last use, least recently used first
+    order: BTreeMap<u64, FrameCacheKey>,
+    next_use: u64,
+    size: usize,
+    /// In bytes, disabled when 0
+    limit: usize,
+}
+
+impl FrameCache {
+    pub fn get(&mut self, key: &FrameCacheKey) -> Option<Arc<VSFrame>> {
+        let use_no = self.next_use();
+        let (frame, last_use) = self.entries.get_mut(key)?;
+
+        // Most recently used
+        self.order.remove(last_use);
+        self.order.insert(use_no, *key);
+        *last_use = use_no;
+
+        Some(frame.clone())
+    }
+
+    pub fn insert(&mut self, key: FrameCacheKey, frame: Arc<VSFrame>) {
+        let frame_size = frame.size_bytes();
+
+        if frame_size > self.limit {
+            return;
+        }
+
+        self.remove(&key);
+
+        let use_no = self.next_use();
+        self.entries.insert(key, (frame, use_no));
+        self.order.insert(use_no, key);
+        self.size += frame_size;
+
+        self.evict();
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        if self.limit != limit {
+            self.limit = limit;
+            self.evict();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.size = 0;
+    }
+
+    fn next_use(&mut self) -> u64 {
+        self.next_use += 1;
+        self.next_use
+    }
+
+    fn remove(&mut self, key: &FrameCacheKey) {
+        if let Some((frame, last_use)) = self.entries.remove(key) {
+            self.order.remove(&last_use);
+            self.size -= frame.size_bytes();
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.limit {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+
+            if let Some((frame, _)) = self.entries.remove(&key) {
+                self.size -= frame.size_bytes();
+            }
+        }
+    }
+}
@@ -1,21 +1,46 @@
 use itertools::Itertools;
 use parking_lot::Mutex;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use anyhow::{Result, anyhow, bail};
 use vapoursynth::prelude::*;
 
 use crate::utils::frame_to_dynimage;
+use zimg_map::{VSPrimaries, VSTransferCharacteristics};
 
+/// Brightness of 1.0 in linearized HDR frames, in cd/m²
+pub const NOMINAL_LUMINANCE: f64 = 100.0;
+
+/// Longest wait for VS to complete its requests before freeing the environment
+const REQUESTS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Frames requested at once when scanning a range, leaves VS threads for the preview
+const SCAN_REQUESTS_IN_FLIGHT: usize = 4;
+
+pub mod frame_cache;
+pub mod node_cache;
+pub mod pending_frame;
 pub mod vsframe;
 pub mod vsnode;
+pub mod vspixel;
+pub mod vsplanes;
+pub mod vsprops;
 pub mod vstransform;
 pub mod zimg_map;
 
+use frame_cache::{FrameCache, FrameCacheKey};
+use node_cache::{NodeCache, NodeCacheKey};
+pub use pending_frame::PendingFrame;
+use pending_frame::{FrameSender, RequestsInFlight};
 pub use vsframe::{VSFrame, VSFrameProps};
 pub use vsnode::VSNode;
+pub use vspixel::VSPixelValues;
+pub use vsplanes::{VSPlane, VSPlanes, VSViewedPlane};
+pub use vsprops::VSPropsMap;
 pub use vstransform::*;
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -24,17 +49,30 @@ pub struct PreviewedScript {
     script_dir: PathBuf,
     variables: Vec<String>,
 
+    /// Converted nodes, declared first to be dropped before the environment
+    #[serde(skip)]
+    node_cache: Arc<Mutex<NodeCache>>,
+
     #[serde(skip)]
     env: Option<Environment>,
 
     #[serde(skip)]
     pub vs_messages: Arc<Mutex<Vec<VSMessage>>>,
+
+    /// Converted frames, cleared on reload
+    #[serde(skip)]
+    frame_cache: Arc<Mutex<FrameCache>>,
+
+    /// Async frame requests VS hasn't called back for yet
+    #[serde(skip)]
+    requests_in_flight: Arc<RequestsInFlight>,
 }
 
 #[derive(Default, Clone, Debug)]
 pub struct VSOutput {
     pub index: i32,
     pub node_info: VSNode,
+    pub has_alpha: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -57,19 +95,18 @@ impl PreviewedScript {
             script_file,
             script_dir,
             variables,
+            node_cache: Default::default(),
             env: None,
             vs_messages: Arc::new(Mutex::new(Vec::new())),
+            frame_cache: Default::default(),
+            requests_in_flight: Default::default(),
         }
     }
 
-    pub fn reload(&mut self) -> Result<()> {
-        let env = if let Some(env) = self.env.as_mut() {
-            env.clear();
-
-            env
-        } else {
-            self.env.get_or_insert(Environment::new()?)
-        };
+    /// Evaluates the script in a new environment, replacing the current one on success.
+    /// The previous environment stays usable when the script fails.
+    pub fn reload(&mut self) -> Result<HashMap<i32, VSOutput>> {
+        let mut env = Environment::new()?;
 
         if !self.variables.is_empty() {
             let mut variables = OwnedMap::new(API::get().unwrap());
@@ -88,19 +125,25 @@ impl PreviewedScript {
         }
 
         env.eval_file(&self.script_file, EvalFlags::SetWorkingDir)?;
+        let outputs = Self::env_outputs(&env)?;
 
-        Ok(())
-    }
+        // Old environment is freed on drop
+        self.wait_for_requests()?;
+        self.node_cache.lock().clear();
+        self.env = Some(env);
+        self.frame_cache.lock().clear();
 
-    pub fn get_outputs(&mut self) -> Result<HashMap<i32, VSOutput>> {
-        let env = self.env.get_or_insert(Environment::new()?);
+        Ok(outputs)
+    }
 
+    fn env_outputs(env: &Environment) -> Result<HashMap<i32, VSOutput>> {
         let outputs: HashMap<i32, VSOutput> = (0..9)
             .map(|i| {
-                env.get_output(i).map(|(node, _alpha)| {
+                env.get_output(i).map(|(node, alpha)| {
                     let out = VSOutput {
                         index: i,
                         node_info: VSNode::from_videoinfo(node.info()),
+                        has_alpha: alpha.is_some(),
                     };
 
                     (i, out)
@@ -116,18 +159,53 @@ impl PreviewedScript {
         }
     }
 
+    /// Renders the frame synchronously, waiting for the async request
     pub fn get_frame(
-        &mut self,
+        &self,
         output: i32,
         frame_no: u32,
         opts: &VSTransformOptions,
-    ) -> Result<VSFrame> {
+        overrides: &VSColorOverrides,
+    ) -> Result<Arc<VSFrame>> {
+        self.request_frame(output, frame_no, opts, overrides)?
+            .wait()
+    }
+
+    /// Requests the converted frame asynchronously, the mutex only has to be held to request.
+    /// YUV and RGB sources are requested first for their props, which decide the conversion.
+    /// Frames in the cache are returned without a request.
+    pub fn request_frame(
+        &self,
+        output: i32,
+        frame_no: u32,
+        opts: &VSTransformOptions,
+        overrides: &VSColorOverrides,
+    ) -> Result<PendingFrame<Arc<VSFrame>>> {
+        let key = FrameCacheKey {
+            output,
+            frame_no,
+            opts: *opts,
+            overrides: *overrides,
+        };
+
+        if let Some(frame) = self.frame_cache.lock().get(&key) {
+            return Ok(PendingFrame::ready(Ok(frame)));
+        }
+
         let env = self
             .env
             .as_ref()
             .ok_or_else(|| anyhow!("Cannot request VS frame without environment"))?;
 
-        let (mut node, _alpha) = env.get_output(output)?;
+        let (node, alpha_node) = env.get_output(output)?;
+
+        if opts.plane_view != VSPlaneView::Off {
+            let (sender, pending) = PendingFrame::new(&self.requests_in_flight);
+            let cache = (self.frame_cache.clone(), key);
+            Self::request_plane_frame(node, alpha_node, frame_no as usize, *opts, cache, sender)?;
+
+            return Ok(pending);
+        }
 
         // std plugin, should always exist
         let resize_plugin = env
@@ -135,6 +213,140 @@ impl PreviewedScript {
             .get_plugin_by_id("com.vapoursynth.resize")?
             .unwrap();
 
+        let alpha_node = alpha_node
+            .map(|alpha_node| Self::convert_alpha_node(&resize_plugin, alpha_node))
+            .transpose()?;
+
+        let (sender, pending) = PendingFrame::new(&self.requests_in_flight);
+        let cache = (self.frame_cache.clone(), key);
+        let node_cache = self.node_cache.clone();
+        let opts = *opts;
+        let overrides = *overrides;
+
+        if node.info().format.color_family() != ColorFamily::Gray {
+            node.get_frame_async(frame_no as usize, move |res, n, node| {
+                let (src_props, src_props_map) = match res {
+                    Ok(src_frame) => (
+                        VSFrameProps::from_mapref(src_frame.props()),
+                        VSPropsMap::from_map(&src_frame.props()),
+                    ),
+                    Err(e) => return sender.send(Err(anyhow!("{e}"))),
+                };
+
+                let converted = Self::color_conversion(&node, Some(&src_props), &opts, &overrides)
+                    .and_then(|conversion| {
+                        let key = NodeCacheKey {
+                            output,
+                            opts,
+                            conversion,
+                        };
+                        let rgb_node =
+                            Self::converted_node(&resize_plugin, node, key, &node_cache)?;
+
+                        Ok((rgb_node, conversion))
+                    });
+
+                match converted {
+                    Ok((rgb_node, conversion)) => Self::request_rgb_frame(
+                        rgb_node,
+                        alpha_node,
+                        n,
+                        (conversion, Some(src_props_map)),
+                        cache,
+                        sender,
+                    ),
+                    Err(e) => sender.send(Err(e)),
+                }
+            });
+        } else {
+            let conversion = Self::color_conversion(&node, None, &opts, &overrides)?;
+            let key = NodeCacheKey {
+                output,
+                opts,
+                conversion,
+            };
+            let rgb_node = Self::converted_node(&resize_plugin, node, key, &node_cache)?;
+            Self::request_rgb_frame(
+                rgb_node,
+                alpha_node,
+                frame_no as usize,
+                (conversion, None),
+                cache,
+                sender,
+            );
+        }
+
+        Ok(pending)
+    }
+
+    /// Alpha is always full range, displayed as 8 bit
+    fn convert_alpha_node<'core>(
+        resize_plugin: &Plugin<'core>,
+        alpha_node: Node<'core>,
+    ) -> Result<Node<'core>> {
+        if i32::from(alpha_node.info().format.id()) == PresetFormat::Gray8 as i32 {
+            return Ok(alpha_node);
+        }
+
+        let mut args =
+            OwnedMap::new(API::get().ok_or_else(|| anyhow!("Couldn't initialize VS API"))?);
+        args.set_node("clip", &alpha_node)?;
+        args.set_int("format", PresetFormat::Gray8 as i64)?;
+        args.set_int("range_in", 1)?;
+        args.set_int("range", 1)?;
+
+        let gray = resize_plugin.invoke("Point", &args)?;
+
+        Ok(gray.get_video_node("clip")?)
+    }
+
+    /// Values used to convert YUV and RGB sources, from the frame props and overrides.
+    /// `src_props` are required for YUV and RGB sources.
+    fn color_conversion(
+        node: &Node,
+        src_props: Option<&VSFrameProps>,
+        opts: &VSTransformOptions,
+        overrides: &VSColorOverrides,
+    ) -> Result<Option<VSColorConversion>> {
+        let color_family = node.info().format.color_family();
+        if color_family == ColorFamily::Gray {
+            return Ok(None);
+        }
+
+        let src_props =
+            src_props.ok_or_else(|| anyhow!("Color conversion requires the frame props"))?;
+        let mut conv = if color_family == ColorFamily::RGB {
+            VSColorConversion::rgb(src_props, overrides)
+        } else {
+            VSColorConversion::new(src_props, overrides)
+        };
+
+        // Tonemapped by the previewer
+        conv.linear_hdr = opts.linearize_hdr && conv.is_hdr();
+
+        Ok(Some(conv))
+    }
+
+    /// Converted output node, only built the first time it's requested
+    fn converted_node<'core>(
+        resize_plugin: &Plugin<'core>,
+        node: Node<'core>,
+        key: NodeCacheKey,
+        node_cache: &Mutex<NodeCache>,
+    ) -> Result<Node<'core>> {
+        node_cache.lock().get_or_insert(key, || {
+            Self::convert_node(resize_plugin, node, key.conversion.as_ref(), &key.opts)
+        })
+    }
+
+    /// Converts the node to the preview format.
+    /// `conversion` is required for YUV and RGB sources.
+    fn convert_node<'core>(
+        resize_plugin: &Plugin<'core>,
+        node: Node<'core>,
+        conversion: Option<&VSColorConversion>,
+        opts: &VSTransformOptions,
+    ) -> Result<Node<'core>> {
         let mut args =
             OwnedMap::new(API::get().ok_or_else(|| anyhow!("Couldn't initialize VS API"))?);
         args.set_node("clip", &node)?;
@@ -144,67 +356,477 @@ impl PreviewedScript {
         let id = i32::from(format.id());
         let is_rgb24 = id == PresetFormat::RGB24 as i32;
 
+        // High precision keeps 16 bits, or float for float sources
+        let (gray_format, rgb_format) = if !opts.high_precision {
+            (PresetFormat::Gray8, PresetFormat::RGB24)
+        } else if format.sample_type() == SampleType::Float {
+            (PresetFormat::Gray16, PresetFormat::RGBS)
+        } else {
+            (PresetFormat::Gray16, PresetFormat::RGB48)
+        };
+
         // Disable dither for RGB24 src
         // Always dither for GRAY/YUV src
-        if opts.enable_dithering && !is_rgb24 && format.bitsPerSample >= 8 {
+        // High precision is only dithered for display
+        if opts.enable_dithering && !opts.high_precision && !is_rgb24 && format.bitsPerSample >= 8 {
             args.set_data("dither_type", opts.dither_algo.as_str().as_bytes())?;
         }
 
         let modified = match format.color_family() {
             ColorFamily::Gray => {
-                if id != PresetFormat::Gray8 as i32 {
-                    args.set_int("format", PresetFormat::Gray8 as i64)?;
+                if id != gray_format as i32 {
+                    args.set_int("format", gray_format as i64)?;
                     true
                 } else {
                     false
                 }
             }
             ColorFamily::YUV => {
-                args.set_int("format", PresetFormat::RGB24 as i64)?;
-                args.set_int("matrix_in", 1)?;
+                let conv =
+                    conversion.ok_or_else(|| anyhow!("YUV conversion requires the frame props"))?;
+
+                args.set_int("format", rgb_format as i64)?;
+                args.set_int("matrix_in", conv.matrix as i64)?;
+                args.set_int("range_in", conv.resize_range())?;
+                args.set_int("chromaloc_in", conv.chroma_location as i64)?;
+
+                // Output primaries and transfer default to the input ones
+                if conv.matrix_needs_primaries() {
+                    args.set_int("primaries_in", conv.primaries as i64)?;
+                    args.set_int("transfer_in", conv.transfer as i64)?;
+                }
+
+                Self::set_transfer_args(&mut args, conv)?;
 
                 true
             }
             ColorFamily::RGB => {
-                if !is_rgb24 {
-                    args.set_int("format", PresetFormat::RGB24 as i64)?;
-                    true
-                } else {
-                    false
+                let conv =
+                    conversion.ok_or_else(|| anyhow!("RGB conversion requires the frame props"))?;
+
+                if id != rgb_format as i32 {
+                    args.set_int("format", rgb_format as i64)?;
                 }
+
+                Self::set_transfer_args(&mut args, conv)? || id != rgb_format as i32
             }
-            _ => panic!("Invalid frame color family for preview!"),
+            _ => bail!("Invalid frame color family for preview!"),
         };
 
         if modified {
             let rgb = resize_plugin.invoke(opts.resizer.as_str(), &args)?;
-            node = rgb.get_video_node("clip")?;
+            Ok(rgb.get_video_node("clip")?)
+        } else {
+            Ok(node)
         }
+    }
 
-        let frame = node.get_frame(frame_no as usize)?;
-        let props = VSFrameProps::from_mapref(frame.props());
-        let image = frame_to_dynimage(&frame);
+    /// Linearizes HDR to BT.709 primaries, or converts the gamut of SDR transfers.
+    /// Returns whether any argument was set.
+    fn set_transfer_args(args: &mut OwnedMap, conv: &VSColorConversion) -> Result<bool> {
+        if conv.linear_hdr {
+            args.set_int("format", PresetFormat::RGBS as i64)?;
+            args.set_int("primaries_in", conv.primaries as i64)?;
+            args.set_int("primaries", VSPrimaries::BT709 as i64)?;
+            args.set_int("transfer_in", conv.transfer as i64)?;
+            args.set_int("transfer", VSTransferCharacteristics::Linear as i64)?;
+            args.set_float("nominal_luminance", NOMINAL_LUMINANCE)?;
+        } else if conv.converts_gamut() {
+            args.set_int("primaries_in", conv.primaries as i64)?;
+            args.set_int("primaries", VSPrimaries::BT709 as i64)?;
+            args.set_int("transfer_in", conv.transfer as i64)?;
+            args.set_int("transfer", conv.transfer as i64)?;
+        } else {
+            return Ok(false);
+        }
 
-        Ok(VSFrame { image, props })
+        Ok(true)
     }
 
-    pub fn get_original_props(&mut self, output: i32, frame_no: u32) -> Result<VSFrameProps> {
+    /// Requests the converted frame, then the alpha frame if any.
+    /// The props map of the source frame is kept when it was already requested.
+    /// The complete frame is added to the cache.
+    fn request_rgb_frame<'core>(
+        node: Node<'core>,
+        alpha_node: Option<Node<'core>>,
+        frame_no: usize,
+        (conversion, src_props_map): (Option<VSColorConversion>, Option<VSPropsMap>),
+        cache: (Arc<Mutex<FrameCache>>, FrameCacheKey),
+        sender: FrameSender<Arc<VSFrame>>,
+    ) {
+        let send_frame = move |res| Self::send_cached(res, cache, sender);
+
+        node.get_frame_async(frame_no, move |res, n, _| {
+            let frame = match res {
+                Ok(frame) => frame,
+                Err(e) => return send_frame(Err(anyhow!("{e}"))),
+            };
+            let image = match frame_to_dynimage(&frame) {
+                Ok(image) => image,
+                Err(e) => return send_frame(Err(e)),
+            };
+            let props = VSFrameProps::from_mapref(frame.props());
+            let props_map = src_props_map.unwrap_or_else(|| VSPropsMap::from_map(&frame.props()));
+
+            let Some(alpha_node) = alpha_node else {
+                return send_frame(Ok(VSFrame {
+                    image,
+                    props,
+                    props_map,
+                    alpha: None,
+                    conversion,
+                    plane: None,
+                }));
+            };
+
+            alpha_node.get_frame_async(n, move |res, _, _| {
+                let alpha_frame = match res {
+                    Ok(alpha_frame) => alpha_frame,
+                    Err(e) => return send_frame(Err(anyhow!("{e}"))),
+                };
+
+                // Composited pixel by pixel
+                let alpha_size = (alpha_frame.width(0), alpha_frame.height(0));
+                let image_size = (image.width() as usize, image.height() as usize);
+                if alpha_size != image_size {
+                    return send_frame(Err(anyhow!(
+                        "The alpha clip is {}x{}, but the output is {}x{}",
+                        alpha_size.0,
+                        alpha_size.1,
+                        image_size.0,
+                        image_size.1
+                    )));
+                }
+
+                // Converted to Gray8 by `convert_alpha_node`
+                let res = frame_to_dynimage(&alpha_frame).map(|alpha| VSFrame {
+                    image,
+                    props,
+                    props_map,
+                    alpha: Some(alpha.into_luma8()),
+                    conversion,
+                    plane: None,
+                });
+
+                send_frame(res);
+            });
+        });
+    }
+
+    /// Requests a single plane of the unconverted frame, as greyscale.
+    /// The props are always from the output clip, even when viewing the alpha.
+    fn request_plane_frame<'core>(
+        node: Node<'core>,
+        alpha_node: Option<Node<'core>>,
+        frame_no: usize,
+        opts: VSTransformOptions,
+        cache: (Arc<Mutex<FrameCache>>, FrameCacheKey),
+        sender: FrameSender<Arc<VSFrame>>,
+    ) -> Result<()> {
+        let alpha_node = match opts.plane_view {
+            VSPlaneView::Alpha => {
+                Some(alpha_node.ok_or_else(|| anyhow!("The output has no alpha clip to view"))?)
+            }
+            _ => None,
+        };
+
+        let plane_frame = move |frame: &FrameRef, props: VSFrameProps, props_map: VSPropsMap| {
+            let format = frame.format();
+            let plane = match opts.plane_view {
+                VSPlaneView::Plane(plane) if plane < format.plane_count() => plane,
+                VSPlaneView::Plane(plane) => bail!("{} has no plane {plane}", format.name()),
+                _ => 0,
+            };
+
+            let vsplane = VSPlane::from_frame(frame, plane);
+            let subsampling = if plane > 0 && !opts.upscale_planes {
+                (format.sub_sampling_w(), format.sub_sampling_h())
+            } else {
+                (0, 0)
+            };
+
+            let image = vsplane.to_image(
+                frame.width(0) >> subsampling.0,
+                frame.height(0) >> subsampling.1,
+                opts.stretch_planes,
+                opts.high_precision,
+            );
+            let name = match opts.plane_view {
+                VSPlaneView::Alpha => "Alpha",
+                _ => vsplane.name,
+            };
+
+            Ok(VSFrame {
+                image,
+                props,
+                props_map,
+                alpha: None,
+                conversion: None,
+                plane: Some(VSViewedPlane { name, subsampling }),
+            })
+        };
+
+        node.get_frame_async(frame_no, move |res, n, _| {
+            let frame = match res {
+                Ok(frame) => frame,
+                Err(e) => return Self::send_cached(Err(anyhow!("{e}")), cache, sender),
+            };
+            let props = VSFrameProps::from_mapref(frame.props());
+            let props_map = VSPropsMap::from_map(&frame.props());
+
+            let Some(alpha_node) = alpha_node else {
+                return Self::send_cached(plane_frame(&frame, props, props_map), cache, sender);
+            };
+
+            alpha_node.get_frame_async(n, move |res, _, _| {
+                let res = res
+                    .map_err(|e| anyhow!("{e}"))
+                    .and_then(|alpha_frame| plane_frame(&alpha_frame, props, props_map));
+
+                Self::send_cached(res, cache, sender);
+            });
+        });
+
+        Ok(())
+    }
+
+    /// Sends the rendered frame, adding it to the cache
+    fn send_cached(
+        res: Result<VSFrame>,
+        cache: (Arc<Mutex<FrameCache>>, FrameCacheKey),
+        sender: FrameSender<Arc<VSFrame>>,
+    ) {
+        let res = res.map(Arc::new);
+
+        if let Ok(vsframe) = &res {
+            let (cache, key) = cache;
+            cache.lock().insert(key, vsframe.clone());
+        }
+
+        sender.send(res);
+    }
+
+    /// Cache memory limit in bytes, 0 disables the cache
+    pub fn set_frame_cache_limit(&self, limit: usize) {
+        self.frame_cache.lock().set_limit(limit);
+    }
+
+    /// Requests the converted frames asynchronously, so they are cached ahead of display.
+    /// Goes through `request_frame`, the rendered frames are added to the frame cache.
+    pub fn prefetch_frames(
+        &self,
+        output: i32,
+        frames: &[u32],
+        opts: &VSTransformOptions,
+        overrides: &VSColorOverrides,
+    ) -> Result<Vec<PendingFrame<Arc<VSFrame>>>> {
+        frames
+            .iter()
+            .map(|&frame_no| self.request_frame(output, frame_no, opts, overrides))
+            .collect()
+    }
+
+    /// Requests the unconverted frame for its props
+    pub fn request_original_props(
+        &self,
+        output: i32,
+        frame_no: u32,
+    ) -> Result<PendingFrame<VSFrameProps>> {
         let env = self
             .env
             .as_ref()
             .ok_or_else(|| anyhow!("Cannot request VS frame without environment"))?;
 
         let (node, _alpha) = env.get_output(output)?;
-        let frame = node.get_frame(frame_no as usize)?;
+        let (sender, pending) = PendingFrame::new(&self.requests_in_flight);
 
-        Ok(VSFrameProps::from_mapref(frame.props()))
+        node.get_frame_async(frame_no as usize, move |res, _, _| {
+            let res = res
+                .map(|frame| VSFrameProps::from_mapref(frame.props()))
+                .map_err(|e| anyhow!("{e}"));
+
+            sender.send(res);
+        });
+
+        Ok(pending)
+    }
+
+    /// Requests the unconverted frame for all of its props
+    pub fn request_props_map(
+        &self,
+        output: i32,
+        frame_no: u32,
+    ) -> Result<PendingFrame<VSPropsMap>> {
+        let env = self
+            .env
+            .as_ref()
+            .ok_or_else(|| anyhow!("Cannot request VS frame without environment"))?;
+
+        let (node, _alpha) = env.get_output(output)?;
+        let (sender, pending) = PendingFrame::new(&self.requests_in_flight);
+
+        node.get_frame_async(frame_no as usize, move |res, _, _| {
+            let res = res
+                .map(|frame| VSPropsMap::from_map(&frame.props()))
+                .map_err(|e| anyhow!("{e}"));
+
+            sender.send(res);
+        });
+
+        Ok(pending)
+    }
+
+    /// Requests each frame with `request`, keeping a few requests in flight.
+    /// The script is only locked to request, `on_frame` is called in frame order.
+    /// Stops early when cancelled, the remaining requests are dropped.
+    pub fn scan_frames<T>(
+        script: &Mutex<Self>,
+        frames: impl IntoIterator<Item = u32>,
+        cancelled: &AtomicBool,
+        request: impl Fn(&Self, u32) -> Result<PendingFrame<T>>,
+        mut on_frame: impl FnMut(u32, T),
+    ) -> Result<()> {
+        let mut frames = frames.into_iter();
+        let mut pending = VecDeque::with_capacity(SCAN_REQUESTS_IN_FLIGHT);
+
+        loop {
+            if cancelled.load(Ordering::Acquire) {
+                return Ok(());
+            }
+
+            while pending.len() < SCAN_REQUESTS_IN_FLIGHT {
+                let Some(frame_no) = frames.next() else {
+                    break;
+                };
+
+                let request = request(&script.lock(), frame_no)?;
+                pending.push_back((frame_no, request));
+            }
+
+            let Some((frame_no, request)) = pending.pop_front() else {
+                return Ok(());
+            };
+
+            on_frame(frame_no, request.wait()?);
+        }
+    }
+
+    /// Reads a pixel from the unconverted output frame, and its alpha clip if any
+    pub fn request_pixel_values(
+        &self,
+        output: i32,
+        frame_no: u32,
+        x: usize,
+        y: usize,
+    ) -> Result<PendingFrame<VSPixelValues>> {
+        let env = self
+            .env
+            .as_ref()
+            .ok_or_else(|| anyhow!("Cannot request VS frame without environment"))?;
+
+        let (node, alpha_node) = env.get_output(output)?;
+        let (sender, pending) = PendingFrame::new(&self.requests_in_flight);
+
+        node.get_frame_async(frame_no as usize, move |res, n, _| {
+            let values = res
+                .map_err(|e| anyhow!("{e}"))
+                .and_then(|frame| VSPixelValues::from_frame(&frame, x, y));
+
+            let (mut values, alpha_node) = match (values, alpha_node) {
+                (Ok(values), Some(alpha_node)) => (values, alpha_node),
+                (values, None) | (values @ Err(_), _) => return sender.send(values),
+            };
+
+            alpha_node.get_frame_async(n, move |res, _, _| {
+                let res = res
+                    .map_err(|e| anyhow!("{e}"))
+                    .and_then(|alpha_frame| values.set_alpha(&alpha_frame, x, y))
+                    .map(|_| values);
+
+                sender.send(res);
+            });
+        });
+
+        Ok(pending)
+    }
+
+    /// Requests the unconverted output frame, as normalized planes
+    pub fn request_source_planes(
+        &self,
+        output: i32,
+        frame_no: u32,
+    ) -> Result<PendingFrame<VSPlanes>> {
+        let env = self
+            .env
+            .as_ref()
+            .ok_or_else(|| anyhow!("Cannot request VS frame without environment"))?;
+
+        let (node, _alpha) = env.get_output(output)?;
+        let (sender, pending) = PendingFrame::new(&self.requests_in_flight);
+
+        node.get_frame_async(frame_no as usize, move |res, _, _| {
+            let res = res
+                .map_err(|e| anyhow!("{e}"))
+                .and_then(|frame| VSPlanes::from_frame(&frame));
+
+            sender.send(res);
+        });
+
+        Ok(pending)
+    }
+
+    /// Requests the same unconverted frame of both outputs, as normalized planes
+    pub fn request_source_planes_pair(
+        &self,
+        output: i32,
+        other_output: i32,
+        frame_no: u32,
+    ) -> Result<PendingFrame<(VSPlanes, VSPlanes)>> {
+        let env = self
+            .env
+            .as_ref()
+            .ok_or_else(|| anyhow!("Cannot request VS frame without environment"))?;
+
+        let (node, _alpha) = env.get_output(output)?;
+        let (other_node, _alpha) = env.get_output(other_output)?;
+        let (sender, pending) = PendingFrame::new(&self.requests_in_flight);
+
+        node.get_frame_async(frame_no as usize, move |res, n, _| {
+            let planes = match res
+                .map_err(|e| anyhow!("{e}"))
+                .and_then(|frame| VSPlanes::from_frame(&frame))
+            {
+                Ok(planes) => planes,
+                Err(e) => return sender.send(Err(e)),
+            };
+
+            other_node.get_frame_async(n, move |res, _, _| {
+                let res = res
+                    .map_err(|e| anyhow!("{e}"))
+                    .and_then(|frame| VSPlanes::from_frame(&frame))
+                    .map(|other_planes| (planes, other_planes));
+
+                sender.send(res);
+            });
+        });
+
+        Ok(pending)
+    }
+
+    /// The environment can only be freed once VS is done with its requests
+    fn wait_for_requests(&self) -> Result<()> {
+        self.requests_in_flight.wait(REQUESTS_TIMEOUT)
     }
 
     pub fn get_script_dir(&self) -> PathBuf {
         self.script_dir.clone()
     }
 
-    pub fn add_vs_error<T>(&mut self, res: &Result<T>) {
+    pub fn get_script_file(&self) -> PathBuf {
+        PathBuf::from(&self.script_file)
+    }
+
+    pub fn add_vs_error<T>(&self, res: &Result<T>) {
         if let Err(e) = res {
             let mut messages = self.vs_messages.lock();
 
@@ -235,9 +857,15 @@ impl PreviewedScript {
         Ok(())
     }
 
-    pub fn exit(&self) {
+    /// The environment is left as is when VS doesn't complete its requests
+    pub fn exit(&self) -> Result<()> {
+        self.wait_for_requests()?;
+        self.node_cache.lock().clear();
+
         if let Some(env) = self.env.as_ref() {
             env.clear();
         }
+
+        Ok(())
     }
 }
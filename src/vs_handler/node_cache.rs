@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use vapoursynth::prelude::*;
+
+use super::{VSColorConversion, VSTransformOptions};
+
+/// Identifies an output converted with the same arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeCacheKey {
+    pub output: i32,
+    pub opts: VSTransformOptions,
+    /// Decided by the source props for YUV outputs
+    pub conversion: Option<VSColorConversion>,
+}
+
+/// Converted nodes of the current environment, so the filters are only built once.
+/// Must be cleared before the environment is replaced or dropped.
+#[derive(Default)]
+pub struct NodeCache {
+    nodes: HashMap<NodeCacheKey, Node<'static>>,
+}
+
+impl NodeCache {
+    /// Returns the cached node, or builds it with `convert`
+    pub fn get_or_insert<'core>(
+        &mut self,
+        key: NodeCacheKey,
+        convert: impl FnOnce() -> Result<Node<'core>>,
+    ) -> Result<Node<'core>> {
+        if let Some(node) = self.nodes.get(&key) {
+            return Ok(node.clone());
+        }
+
+        let node = convert()?;
+
+        // SAFETY: The lifetime only ties the node to the environment's core.
+        // Nodes are cleared before the environment is replaced or dropped.
+        let stored = unsafe { std::mem::transmute::<Node<'core>, Node<'static>>(node.clone()) };
+        self.nodes.insert(key, stored);
+
+        Ok(node)
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, SyncSender, TryRecvError, sync_channel};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow, bail};
+use parking_lot::{Condvar, Mutex};
+
+/// Result of an async VS frame request, rendered on the VS threads
+pub struct PendingFrame<T> {
+    receiver: Receiver<Result<T>>,
+}
+
+/// Completes a `PendingFrame` from the VS callback
+pub(super) struct FrameSender<T> {
+    sender: SyncSender<Result<T>>,
+    _in_flight: InFlightRequest,
+}
+
+/// Async requests VS hasn't called back for yet
+#[derive(Default)]
+pub(super) struct RequestsInFlight {
+    count: Mutex<usize>,
+    done: Condvar,
+}
+
+/// Counts the request as in flight until dropped
+struct InFlightRequest(Arc<RequestsInFlight>);
+
+impl<T> PendingFrame<T> {
+    pub(super) fn new(in_flight: &Arc<RequestsInFlight>) -> (FrameSender<T>, Self) {
+        let (sender, receiver) = sync_channel(1);
+
+        let sender = FrameSender {
+            sender,
+            _in_flight: InFlightRequest::new(in_flight),
+        };
+
+        (sender, Self { receiver })
+    }
+
+    /// Already available, without a VS request
+    pub(super) fn ready(res: Result<T>) -> Self {
+        let (sender, receiver) = sync_channel(1);
+        sender.send(res).ok();
+
+        Self { receiver }
+    }
+
+    /// Blocks until VS is done with the request
+    pub fn wait(self) -> Result<T> {
+        self.receiver
+            .recv()
+            .map_err(|_| anyhow!("VS frame request was dropped"))?
+    }
+
+    /// Polls for the result without blocking
+    pub fn try_get(&self) -> Option<Result<T>> {
+        match self.receiver.try_recv() {
+            Ok(res) => Some(res),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(anyhow!("VS frame request was dropped"))),
+        }
+    }
+}
+
+impl<T> FrameSender<T> {
+    pub fn send(self, res: Result<T>) {
+        // The receiver may have been dropped, the result is unused
+        self.sender.send(res).ok();
+    }
+}
+
+impl RequestsInFlight {
+    /// Blocks until every request has completed, or errors after `timeout`
+    pub fn wait(&self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let mut count = self.count.lock();
+
+        while *count > 0 {
+            if self.done.wait_until(&mut count, deadline).timed_out() && *count > 0 {
+                bail!(
+                    "VS still has {} frame requests in flight after {}s",
+                    *count,
+                    timeout.as_secs()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl InFlightRequest {
+    fn new(requests: &Arc<RequestsInFlight>) -> Self {
+        *requests.count.lock() += 1;
+
+        Self(requests.clone())
+    }
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        let mut count = self.0.count.lock();
+        *count -= 1;
+
+        if *count == 0 {
+            self.0.done.notify_all();
+        }
+    }
+}
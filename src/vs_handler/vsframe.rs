@@ -1,8 +1,13 @@
 use std::fmt::Display;
+use std::path::Path;
 
-use image::DynamicImage;
+use anyhow::Result;
+use image::{DynamicImage, GrayImage};
 use vapoursynth::map::MapRef;
 
+use super::VSColorConversion;
+use super::vsplanes::VSViewedPlane;
+use super::vsprops::VSPropsMap;
 use super::zimg_map::*;
 
 /// Reserved props
@@ -13,6 +18,9 @@ const KEY_PRIMARIES: &str = "_Primaries";
 const KEY_MATRIX: &str = "_Matrix";
 const KEY_TRANSFER: &str = "_Transfer";
 const KEY_SCENE_CUT: &str = "_SceneChangePrev";
+const KEY_DURATION_NUM: &str = "_DurationNum";
+const KEY_DURATION_DEN: &str = "_DurationDen";
+const KEY_ABSOLUTE_TIME: &str = "_AbsoluteTime";
 
 /// Potentially relevant props
 const KEY_CAMBI: &str = "CAMBI";
@@ -32,6 +40,17 @@ const KEY_HDR10_MAXFALL: &str = "ContentLightLevelAverage";
 pub struct VSFrame {
     pub image: DynamicImage,
     pub props: VSFrameProps,
+    /// Every prop of the frame, from the source clip
+    pub props_map: VSPropsMap,
+
+    /// Alpha plane when the output was set with an alpha clip
+    pub alpha: Option<GrayImage>,
+
+    /// Values used to convert from YUV, if it was converted
+    pub conversion: Option<VSColorConversion>,
+
+    /// Single plane viewed instead of the converted frame
+    pub plane: Option<VSViewedPlane>,
 }
 
 #[derive(Default, Debug, Clone, Copy, PartialEq)]
@@ -48,6 +67,11 @@ pub struct VSFrameProps {
     pub is_scenecut: Option<bool>,
     pub cambi_score: Option<f64>,
 
+    /// Frame duration as a (numerator, denominator) fraction of seconds
+    pub duration: Option<(i64, i64)>,
+    /// Presentation time of the frame in seconds
+    pub absolute_time: Option<f64>,
+
     pub hdr10_metadata: Option<Hdr10Metadata>,
     pub is_dolbyvision: bool,
 }
@@ -70,6 +94,76 @@ pub struct MdcvMetadata {
     pub white_point: [f64; 2],
 }
 
+impl VSFrame {
+    /// Memory used by the image and alpha planes
+    pub fn size_bytes(&self) -> usize {
+        self.image.as_bytes().len() + self.alpha.as_ref().map_or(0, |alpha| alpha.len())
+    }
+
+    /// Same frame with another image, for frames shared with the cache
+    pub fn with_image(&self, image: DynamicImage) -> Self {
+        Self {
+            image,
+            props: self.props,
+            props_map: self.props_map.clone(),
+            alpha: self.alpha.clone(),
+            conversion: self.conversion,
+            plane: self.plane,
+        }
+    }
+
+    /// Region of the frame, the props are kept
+    pub fn cropped(&self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            image: self.image.crop_imm(x, y, width, height),
+            alpha: self
+                .alpha
+                .as_ref()
+                .map(|alpha| image::imageops::crop_imm(alpha, x, y, width, height).to_image()),
+            props: self.props,
+            props_map: self.props_map.clone(),
+            conversion: self.conversion,
+            plane: self.plane,
+        }
+    }
+
+    /// PNG has no float support, high bit depth is saved as 16 bits
+    pub fn save_png(&self, path: &Path) -> Result<()> {
+        let image = &self.image;
+        let high_depth = !matches!(
+            image,
+            DynamicImage::ImageLuma8(_) | DynamicImage::ImageRgb8(_)
+        );
+
+        if let Some(alpha) = &self.alpha {
+            // Keep the transparency
+            if high_depth {
+                let mut rgba = image.to_rgba16();
+                rgba.pixels_mut()
+                    .zip(alpha.pixels())
+                    .for_each(|(p, a)| p[3] = a[0] as u16 * 257);
+
+                rgba.save_with_format(path, image::ImageFormat::Png)?;
+            } else {
+                let mut rgba = image.to_rgba8();
+                rgba.pixels_mut()
+                    .zip(alpha.pixels())
+                    .for_each(|(p, a)| p[3] = a[0]);
+
+                rgba.save_with_format(path, image::ImageFormat::Png)?;
+            }
+        } else if let DynamicImage::ImageRgb32F(_) = image {
+            image
+                .to_rgb16()
+                .save_with_format(path, image::ImageFormat::Png)?;
+        } else {
+            image.save_with_format(path, image::ImageFormat::Png)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl VSFrameProps {
     // Only reserved frame props
     pub fn from_mapref(map: MapRef) -> Self {
@@ -105,6 +199,13 @@ impl VSFrameProps {
         let is_scenecut = map.get_int(KEY_SCENE_CUT).map_or(None, |v| Some(v != 0));
         let cambi_score = map.get_float(KEY_CAMBI).ok();
 
+        let duration = map
+            .get_int(KEY_DURATION_NUM)
+            .and_then(|num| map.get_int(KEY_DURATION_DEN).map(|den| (num, den)))
+            .ok()
+            .filter(|(num, den)| *num > 0 && *den > 0);
+        let absolute_time = map.get_float(KEY_ABSOLUTE_TIME).ok().filter(|t| *t >= 0.0);
+
         let hdr10_metadata = Hdr10Metadata::new(&map);
         let is_dolbyvision = map.value_count(KEY_DOVI_RPU).is_ok_and(|v| v > 0);
 
@@ -117,10 +218,17 @@ impl VSFrameProps {
             transfer,
             is_scenecut,
             cambi_score,
+            duration,
+            absolute_time,
             is_dolbyvision,
             hdr10_metadata,
         }
     }
+
+    /// Frame duration in seconds
+    pub fn duration_secs(&self) -> Option<f64> {
+        self.duration.map(|(num, den)| num as f64 / den as f64)
+    }
 }
 
 // Requires MDCV minimum
@@ -1,53 +1,135 @@
+use anyhow::{Result, anyhow, bail};
 use vapoursynth::{prelude::Property, video_info::VideoInfo};
 
+use super::vsplanes::plane_names;
+use crate::utils::parse_timestamp;
+
 #[derive(Debug, Default, Clone, serde::Deserialize, serde::Serialize)]
 pub struct VSNode {
     pub num_frames: u32,
+    /// `None` when the resolution is variable, it must be taken from each frame
+    pub resolution: Option<VSResolution>,
+    /// `None` when the framerate is variable, timing comes from each frame's duration
+    pub framerate: Option<VSFramerate>,
+    pub format_name: String,
+    /// Empty when the format is variable
+    #[serde(skip)]
+    pub plane_names: Vec<&'static str>,
+    /// Log2 chroma subsampling, horizontal and vertical
+    #[serde(skip)]
+    pub subsampling: (u8, u8),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct VSResolution {
     pub width: u32,
     pub height: u32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct VSFramerate {
     pub fr_num: u32,
     pub fr_denom: u32,
-    pub framerate: u32,
-    pub format_name: String,
 }
 
 impl VSNode {
     pub fn from_videoinfo(info: VideoInfo) -> VSNode {
-        let (width, height) = match info.resolution {
-            Property::Constant(r) => (r.width as u32, r.height as u32),
-            Property::Variable => panic!("Only supports constant resolution!"),
+        let resolution = match info.resolution {
+            Property::Constant(r) => Some(VSResolution {
+                width: r.width as u32,
+                height: r.height as u32,
+            }),
+            Property::Variable => None,
         };
         let format = info.format;
 
-        let (fr_num, fr_denom) = match info.framerate {
-            Property::Constant(fr) => (fr.numerator as u32, fr.denominator as u32),
-            Property::Variable => panic!("Only supports constant framerate!"),
+        let framerate = match info.framerate {
+            Property::Constant(fr) => Some(VSFramerate {
+                fr_num: fr.numerator as u32,
+                fr_denom: fr.denominator as u32,
+            }),
+            Property::Variable => None,
         };
 
         VSNode {
             num_frames: info.num_frames as u32,
-            width,
-            height,
-            fr_num,
-            fr_denom,
-            framerate: (fr_num as f64 / fr_denom as f64).ceil() as u32,
+            resolution,
+            framerate,
             format_name: String::from(format.name()),
+            plane_names: plane_names(format.color_family()).to_vec(),
+            subsampling: (format.sub_sampling_w(), format.sub_sampling_h()),
         }
     }
 }
 
+impl VSNode {
+    /// Frame number or `[[HH:]MM:]SS[.fraction]` timecode
+    pub fn parse_seek_target(&self, input: &str) -> Result<u32> {
+        let input = input.trim();
+
+        let frame_no = if input.contains(':') {
+            let nanos =
+                parse_timestamp(input).ok_or_else(|| anyhow!("Invalid timecode `{input}`"))?;
+            let fr = self
+                .framerate
+                .ok_or_else(|| anyhow!("Seeking by time requires a constant framerate"))?;
+
+            fr.nanos_to_frame(nanos)
+        } else {
+            input
+                .parse()
+                .map_err(|_| anyhow!("Invalid frame number `{input}`"))?
+        };
+
+        if frame_no >= self.num_frames {
+            bail!(
+                "Frame {frame_no} is out of range ({} frames)",
+                self.num_frames
+            );
+        }
+
+        Ok(frame_no)
+    }
+}
+
+impl VSFramerate {
+    /// Start time of the frame in nanoseconds, exact for rational framerates
+    pub fn frame_to_nanos(&self, frame_no: u32) -> u128 {
+        frame_no as u128 * self.fr_denom as u128 * 1_000_000_000 / self.fr_num as u128
+    }
+
+    /// Frame starting the closest to the time
+    pub fn nanos_to_frame(&self, nanos: u128) -> u32 {
+        let num = nanos * self.fr_num as u128;
+        let den = self.fr_denom as u128 * 1_000_000_000;
+
+        ((num + den / 2) / den).min(u32::MAX as u128) as u32
+    }
+}
+
 impl std::fmt::Display for VSNode {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let size = if let Some(res) = self.resolution {
+            format!("{}x{}", res.width, res.height)
+        } else {
+            String::from("variable")
+        };
+
+        let fps = if let Some(fr) = self.framerate {
+            format!(
+                "{}/{} = {:.3}",
+                fr.fr_num,
+                fr.fr_denom,
+                (fr.fr_num as f32 / fr.fr_denom as f32)
+            )
+        } else {
+            String::from("variable")
+        };
+
         write!(
             f,
-            "Frames: {} | Size: {}x{} | FPS: {}/{} = {:.3} | Format: {}",
-            self.num_frames,
-            self.width,
-            self.height,
-            self.fr_num,
-            self.fr_denom,
-            (self.fr_num as f32 / self.fr_denom as f32),
-            self.format_name,
+            "Frames: {} | Size: {} | FPS: {} | Format: {}",
+            self.num_frames, size, fps, self.format_name,
         )
     }
 }
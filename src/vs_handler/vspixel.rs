@@ -0,0 +1,134 @@
+use std::fmt::Display;
+
+use anyhow::{Result, bail};
+use vapoursynth::prelude::*;
+
+use super::VSFrameProps;
+use super::zimg_map::VSChromaLocation;
+
+/// Sample value in the native format of the clip
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VSSample {
+    Integer(u32),
+    Float(f32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VSPlaneSample {
+    pub name: &'static str,
+    /// Position read in the plane, subsampled for chroma
+    pub x: usize,
+    pub y: usize,
+    pub value: VSSample,
+}
+
+/// Values of a pixel before any conversion, read from the output clip
+#[derive(Debug, Clone, PartialEq)]
+pub struct VSPixelValues {
+    pub format_name: String,
+    pub planes: Vec<VSPlaneSample>,
+    /// Only for subsampled formats
+    pub chroma_location: Option<VSChromaLocation>,
+    pub alpha: Option<VSSample>,
+}
+
+impl VSPixelValues {
+    pub fn from_frame(frame: &FrameRef, x: usize, y: usize) -> Result<Self> {
+        let format = frame.format();
+        let (width, height) = (frame.width(0), frame.height(0));
+
+        // Must not panic from the VS callback
+        if x >= width || y >= height {
+            bail!("Pixel ({x}, {y}) is outside of the {width}x{height} frame");
+        }
+
+        let names = match format.color_family() {
+            ColorFamily::Gray => ["Y", "", ""],
+            ColorFamily::RGB => ["R", "G", "B"],
+            ColorFamily::YUV => ["Y", "U", "V"],
+            ColorFamily::Undefined => bail!("Cannot read pixels of an undefined format"),
+        };
+
+        let planes = (0..format.plane_count())
+            .map(|plane| {
+                let (plane_x, plane_y) = if plane == 0 {
+                    (x, y)
+                } else {
+                    (x >> format.sub_sampling_w(), y >> format.sub_sampling_h())
+                };
+
+                VSPlaneSample {
+                    name: names[plane],
+                    x: plane_x,
+                    y: plane_y,
+                    value: read_sample(frame, plane, plane_x, plane_y),
+                }
+            })
+            .collect();
+
+        let subsampled = format.sub_sampling_w() > 0 || format.sub_sampling_h() > 0;
+        let chroma_location =
+            subsampled.then(|| VSFrameProps::from_mapref(frame.props()).chroma_location);
+
+        Ok(Self {
+            format_name: format.name().to_string(),
+            planes,
+            chroma_location,
+            alpha: None,
+        })
+    }
+
+    /// The alpha clip has the resolution of the output
+    pub fn set_alpha(&mut self, alpha_frame: &FrameRef, x: usize, y: usize) -> Result<()> {
+        if x >= alpha_frame.width(0) || y >= alpha_frame.height(0) {
+            bail!("Pixel ({x}, {y}) is outside of the alpha frame");
+        }
+
+        self.alpha = Some(read_sample(alpha_frame, 0, x, y));
+
+        Ok(())
+    }
+}
+
+fn read_sample(frame: &FrameRef, plane: usize, x: usize, y: usize) -> VSSample {
+    let format = frame.format();
+    let bytes = format.bytes_per_sample() as usize;
+
+    let row = frame.data_row(plane, y);
+    let sample = &row[x * bytes..(x + 1) * bytes];
+
+    match (format.sample_type(), sample) {
+        (SampleType::Integer, [a]) => VSSample::Integer(*a as u32),
+        (SampleType::Integer, [a, b]) => VSSample::Integer(u16::from_ne_bytes([*a, *b]) as u32),
+        (SampleType::Integer, [a, b, c, d]) => {
+            VSSample::Integer(u32::from_ne_bytes([*a, *b, *c, *d]))
+        }
+        (SampleType::Float, [a, b]) => VSSample::Float(half_to_f32(u16::from_ne_bytes([*a, *b]))),
+        (SampleType::Float, [a, b, c, d]) => VSSample::Float(f32::from_ne_bytes([*a, *b, *c, *d])),
+        // 3 bytes per sample is not a valid VS format
+        _ => VSSample::Integer(0),
+    }
+}
+
+/// Half precision float samples, without the `half` crate
+pub(super) fn half_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32 / 1024.0;
+
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-14),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa) * 2f32.powi(exponent - 15),
+    }
+}
+
+impl Display for VSSample {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Integer(v) => write!(f, "{v}"),
+            Self::Float(v) => write!(f, "{v:.5}"),
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use anyhow::{Result, bail};
+use image::{DynamicImage, ImageBuffer, Luma};
+use vapoursynth::prelude::*;
+
+use super::VSFrameProps;
+use super::vspixel::half_to_f32;
+use super::zimg_map::VSColorRange;
+
+/// Plane samples normalized to `[0, 1]`, chroma centered on 0.5
+#[derive(Debug, Clone, Default)]
+pub struct VSPlane {
+    pub name: &'static str,
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<f32>,
+}
+
+/// Plane displayed instead of the converted frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VSViewedPlane {
+    pub name: &'static str,
+    /// Log2 subsampling of the displayed image, relative to the clip
+    pub subsampling: (u8, u8),
+}
+
+/// Planes of a frame in its native format, before any conversion
+#[derive(Debug, Clone, Default)]
+pub struct VSPlanes {
+    pub format_name: String,
+    pub color_family: Option<ColorFamily>,
+    pub sample_type: Option<SampleType>,
+    pub bits_per_sample: u8,
+    pub planes: Vec<VSPlane>,
+    /// YUV in limited range, from the props
+    pub limited_range: bool,
+}
+
+impl VSPlanes {
+    pub fn from_frame(frame: &FrameRef) -> Result<Self> {
+        let format = frame.format();
+        let color_family = format.color_family();
+
+        if color_family == ColorFamily::Undefined {
+            bail!("Cannot read planes of an undefined format");
+        }
+
+        let planes = (0..format.plane_count())
+            .map(|plane| VSPlane::from_frame(frame, plane))
+            .collect();
+
+        let color_range = VSFrameProps::from_mapref(frame.props()).color_range;
+        let limited_range = color_family == ColorFamily::YUV && color_range != VSColorRange::Full;
+
+        Ok(Self {
+            format_name: format.name().to_string(),
+            color_family: Some(color_family),
+            sample_type: Some(format.sample_type()),
+            bits_per_sample: format.bits_per_sample(),
+            planes,
+            limited_range,
+        })
+    }
+
+    /// Planes of a converted RGB or gray image
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+
+        let (color_family, planes) = if image.color().has_color() {
+            let rgb = image.to_rgb32f();
+            let planes = ["R", "G", "B"]
+                .into_iter()
+                .enumerate()
+                .map(|(i, name)| VSPlane {
+                    name,
+                    width,
+                    height,
+                    data: rgb.pixels().map(|p| p.0[i]).collect(),
+                })
+                .collect();
+
+            (ColorFamily::RGB, planes)
+        } else {
+            let plane = VSPlane {
+                name: "Y",
+                width,
+                height,
+                data: image.to_luma32f().into_raw(),
+            };
+
+            (ColorFamily::Gray, vec![plane])
+        };
+
+        Self {
+            format_name: format!("{:?}", image.color()),
+            color_family: Some(color_family),
+            sample_type: Some(SampleType::Float),
+            bits_per_sample: 32,
+            planes,
+            limited_range: false,
+        }
+    }
+
+    pub fn is_yuv(&self) -> bool {
+        self.color_family == Some(ColorFamily::YUV)
+    }
+
+    pub fn is_rgb(&self) -> bool {
+        self.color_family == Some(ColorFamily::RGB)
+    }
+}
+
+impl VSPlane {
+    pub fn from_frame(frame: &FrameRef, plane: usize) -> Self {
+        let color_family = frame.format().color_family();
+        let chroma = color_family == ColorFamily::YUV && plane > 0;
+
+        Self {
+            name: plane_names(color_family).get(plane).copied().unwrap_or(""),
+            width: frame.width(plane),
+            height: frame.height(plane),
+            data: normalized_plane(frame, plane, chroma),
+        }
+    }
+
+    pub fn sample(&self, x: usize, y: usize) -> f32 {
+        self.data[y * self.width + x]
+    }
+
+    /// Greyscale image of the samples, point resized to `width` and `height`.
+    /// Stretching maps the plane's minimum and maximum to black and white.
+    pub fn to_image(
+        &self,
+        width: usize,
+        height: usize,
+        stretch: bool,
+        high_precision: bool,
+    ) -> DynamicImage {
+        let (min, max) = self
+            .data
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), v| {
+                (min.min(*v), max.max(*v))
+            });
+
+        let (offset, scale) = if stretch && max > min {
+            (min, 1.0 / (max - min))
+        } else {
+            (0.0, 1.0)
+        };
+
+        let value = |x: u32, y: u32| {
+            let src_x = x as usize * self.width / width;
+            let src_y = y as usize * self.height / height;
+
+            ((self.sample(src_x, src_y) - offset) * scale).clamp(0.0, 1.0)
+        };
+
+        let (w, h) = (width as u32, height as u32);
+
+        if high_precision {
+            DynamicImage::ImageLuma16(ImageBuffer::from_fn(w, h, |x, y| {
+                Luma([(value(x, y) * 65535.0).round() as u16])
+            }))
+        } else {
+            DynamicImage::ImageLuma8(ImageBuffer::from_fn(w, h, |x, y| {
+                Luma([(value(x, y) * 255.0).round() as u8])
+            }))
+        }
+    }
+}
+
+/// Plane names of the color family, empty when undefined
+pub fn plane_names(color_family: ColorFamily) -> &'static [&'static str] {
+    match color_family {
+        ColorFamily::Gray => &["Y"],
+        ColorFamily::RGB => &["R", "G", "B"],
+        ColorFamily::YUV => &["Y", "U", "V"],
+        ColorFamily::Undefined => &[],
+    }
+}
+
+fn normalized_plane(frame: &FrameRef, plane: usize, chroma: bool) -> Vec<f32> {
+    let format = frame.format();
+    let (w, h) = (frame.width(plane), frame.height(plane));
+    let bytes = format.bytes_per_sample() as usize;
+
+    let max = ((1_u64 << format.bits_per_sample()) - 1) as f32;
+    // Float chroma is in [-0.5, 0.5]
+    let float_offset = if chroma { 0.5 } else { 0.0 };
+
+    let mut data = Vec::with_capacity(w * h);
+
+    for row in 0..h {
+        let row = &frame.data_row(plane, row)[..w * bytes];
+
+        match (format.sample_type(), bytes) {
+            (SampleType::Integer, 1) => data.extend(row.iter().map(|v| *v as f32 / max)),
+            (SampleType::Integer, 2) => data.extend(
+                row.chunks_exact(2)
+                    .map(|v| u16::from_ne_bytes([v[0], v[1]]) as f32 / max),
+            ),
+            (SampleType::Integer, _) => data.extend(
+                row.chunks_exact(4)
+                    .map(|v| u32::from_ne_bytes([v[0], v[1], v[2], v[3]]) as f32 / max),
+            ),
+            (SampleType::Float, 2) => data.extend(
+                row.chunks_exact(2)
+                    .map(|v| half_to_f32(u16::from_ne_bytes([v[0], v[1]])) + float_offset),
+            ),
+            (SampleType::Float, _) => data.extend(
+                row.chunks_exact(4)
+                    .map(|v| f32::from_ne_bytes([v[0], v[1], v[2], v[3]]) + float_offset),
+            ),
+        }
+    }
+
+    data
+}
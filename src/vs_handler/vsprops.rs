@@ -0,0 +1,218 @@
+use std::fmt::{Display, Write};
+
+use vapoursynth::map::{Map, ValueType};
+
+use super::VSNode;
+
+/// Bytes of binary data kept for display
+const DATA_PREVIEW_LEN: usize = 32;
+
+/// Every prop of a frame, in the map's order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VSPropsMap {
+    pub props: Vec<VSProp>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VSProp {
+    pub key: String,
+    pub value: VSPropValue,
+}
+
+/// Values of a key, frames, nodes and functions are only described
+#[derive(Debug, Clone, PartialEq)]
+pub enum VSPropValue {
+    Int(Vec<i64>),
+    Float(Vec<f64>),
+    Data(Vec<VSPropData>),
+    VideoNode(Vec<String>),
+    VideoFrame(Vec<String>),
+    AudioNode(usize),
+    AudioFrame(usize),
+    Function(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VSPropData {
+    Utf8(String),
+    /// Total size, with the first bytes
+    Binary {
+        size: usize,
+        preview: Vec<u8>,
+    },
+}
+
+impl VSPropsMap {
+    pub fn from_map(map: &Map) -> Self {
+        let props = map
+            .keys()
+            .filter_map(|key| {
+                let value = VSPropValue::from_map(map, key)?;
+
+                Some(VSProp {
+                    key: key.to_string(),
+                    value,
+                })
+            })
+            .collect();
+
+        Self { props }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&VSPropValue> {
+        self.props.iter().find(|p| p.key == key).map(|p| &p.value)
+    }
+}
+
+impl VSPropValue {
+    fn from_map(map: &Map, key: &str) -> Option<Self> {
+        let count = map.value_count(key).ok()?;
+
+        let value = match map.value_type(key).ok()? {
+            ValueType::Int => Self::Int(map.get_int_array(key).ok()?.to_vec()),
+            ValueType::Float => Self::Float(map.get_float_array(key).ok()?.to_vec()),
+            ValueType::Data => Self::Data(
+                map.get_data_iter(key)
+                    .ok()?
+                    .map(VSPropData::from_bytes)
+                    .collect(),
+            ),
+            ValueType::VideoNode => Self::VideoNode(
+                map.get_video_node_iter(key)
+                    .ok()?
+                    .map(|node| VSNode::from_videoinfo(node.info()).to_string())
+                    .collect(),
+            ),
+            ValueType::VideoFrame => Self::VideoFrame(
+                map.get_video_frame_iter(key)
+                    .ok()?
+                    .map(|frame| {
+                        format!(
+                            "{} {}x{}, {} props",
+                            frame.format().name(),
+                            frame.width(0),
+                            frame.height(0),
+                            frame.props().key_count()
+                        )
+                    })
+                    .collect(),
+            ),
+            ValueType::AudioNode => Self::AudioNode(count),
+            ValueType::AudioFrame => Self::AudioFrame(count),
+            ValueType::Function => Self::Function(count),
+        };
+
+        Some(value)
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Int(_) => "int",
+            Self::Float(_) => "float",
+            Self::Data(data) if data.iter().all(|d| matches!(d, VSPropData::Utf8(_))) => "utf8",
+            Self::Data(_) => "binary",
+            Self::VideoNode(_) => "video node",
+            Self::VideoFrame(_) => "video frame",
+            Self::AudioNode(_) => "audio node",
+            Self::AudioFrame(_) => "audio frame",
+            Self::Function(_) => "function",
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Int(v) => v.len(),
+            Self::Float(v) => v.len(),
+            Self::Data(v) => v.len(),
+            Self::VideoNode(v) | Self::VideoFrame(v) => v.len(),
+            Self::AudioNode(n) | Self::AudioFrame(n) | Self::Function(n) => *n,
+        }
+    }
+
+    /// Each value formatted on its own
+    pub fn items(&self) -> Vec<String> {
+        match self {
+            Self::Int(v) => v.iter().map(ToString::to_string).collect(),
+            Self::Float(v) => v.iter().map(ToString::to_string).collect(),
+            Self::Data(v) => v.iter().map(ToString::to_string).collect(),
+            Self::VideoNode(v) | Self::VideoFrame(v) => v.clone(),
+            Self::AudioNode(n) | Self::AudioFrame(n) | Self::Function(n) => {
+                vec![format!("<{}>", self.type_name()); *n]
+            }
+        }
+    }
+
+    /// Number, for single int and float values
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Int(v) if v.len() == 1 => Some(v[0] as f64),
+            Self::Float(v) if v.len() == 1 => Some(v[0]),
+            _ => None,
+        }
+    }
+}
+
+impl Display for VSPropValue {
+    /// Single values as is, arrays as their length
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.items().as_slice() {
+            [value] => f.write_str(value),
+            items => write!(f, "[{} values]", items.len()),
+        }
+    }
+}
+
+impl VSPropData {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(s) if !s.chars().any(|c| c.is_control() && !c.is_whitespace()) => {
+                Self::Utf8(s.to_string())
+            }
+            _ => Self::Binary {
+                size: bytes.len(),
+                preview: bytes[..bytes.len().min(DATA_PREVIEW_LEN)].to_vec(),
+            },
+        }
+    }
+}
+
+impl Display for VSPropData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Utf8(s) => write!(f, "\"{s}\""),
+            Self::Binary { size, preview } => {
+                write!(f, "{size} bytes:")?;
+                for byte in preview {
+                    write!(f, " {byte:02x}")?;
+                }
+                if *size > preview.len() {
+                    f.write_str(" …")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl VSProp {
+    /// `key = value` lines, arrays indexed
+    pub fn to_text(&self) -> String {
+        let items = self.value.items();
+
+        if items.len() == 1 {
+            return format!("{} = {}", self.key, items[0]);
+        }
+
+        let mut out = String::new();
+        for (i, item) in items.iter().enumerate() {
+            writeln!(out, "{}[{i}] = {item}", self.key).ok();
+        }
+
+        out
+    }
+}
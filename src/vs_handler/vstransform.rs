@@ -1,13 +1,68 @@
 use std::fmt::Display;
 
-#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+use super::VSFrameProps;
+use super::zimg_map::*;
+
+#[derive(
+    Default, Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+#[serde(default)]
 pub struct VSTransformOptions {
     pub resizer: VSResizer,
     pub enable_dithering: bool,
     pub dither_algo: VSDitherAlgo,
+    /// Convert to 16 bit or float RGB instead of RGB24
+    pub high_precision: bool,
+    /// Convert PQ/HLG to linear float RGB, to be tonemapped
+    #[serde(skip)]
+    pub linearize_hdr: bool,
+
+    /// Single plane displayed as greyscale, without RGB conversion
+    #[serde(skip)]
+    pub plane_view: VSPlaneView,
+    /// Point upscale subsampled planes to the clip resolution
+    pub upscale_planes: bool,
+    /// Stretch the viewed plane to its own minimum and maximum
+    pub stretch_planes: bool,
+}
+
+/// Plane of the original clip to view instead of the converted frame
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VSPlaneView {
+    #[default]
+    Off,
+    Plane(usize),
+    Alpha,
 }
 
-#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+/// Per output overrides for the YUV to RGB conversion
+/// `None` uses the frame props
+#[derive(
+    Default, Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+#[serde(default)]
+pub struct VSColorOverrides {
+    pub matrix: Option<VSMatrix>,
+    pub color_range: Option<VSColorRange>,
+    pub chroma_location: Option<VSChromaLocation>,
+    pub primaries: Option<VSPrimaries>,
+}
+
+/// Values actually used to convert a YUV or RGB frame for display
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VSColorConversion {
+    pub matrix: VSMatrix,
+    pub color_range: VSColorRange,
+    pub chroma_location: VSChromaLocation,
+    pub primaries: VSPrimaries,
+    pub transfer: VSTransferCharacteristics,
+    /// Converted to linear light BT.709 primaries for tonemapping
+    pub linear_hdr: bool,
+}
+
+#[derive(
+    Default, Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
 pub enum VSResizer {
     Bilinear,
     Bicubic,
@@ -19,7 +74,9 @@ pub enum VSResizer {
     Spline64,
 }
 
-#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[derive(
+    Default, Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
 pub enum VSDitherAlgo {
     #[default]
     None,
@@ -42,6 +99,136 @@ impl VSResizer {
     }
 }
 
+impl VSPlaneView {
+    /// Cycles through the planes of the format, then the alpha clip
+    pub fn next(self, plane_count: usize, has_alpha: bool) -> Self {
+        let next = match self {
+            Self::Off => Self::Plane(0),
+            Self::Plane(plane) => Self::Plane(plane + 1),
+            Self::Alpha => Self::Off,
+        };
+
+        match next {
+            Self::Plane(plane) if plane >= plane_count => {
+                if has_alpha {
+                    Self::Alpha
+                } else {
+                    Self::Off
+                }
+            }
+            next => next,
+        }
+    }
+
+    /// Name of the plane, for the format's planes
+    pub fn name(self, plane_names: &[&'static str]) -> Option<&'static str> {
+        match self {
+            Self::Off => None,
+            Self::Plane(plane) => plane_names.get(plane).copied(),
+            Self::Alpha => Some("Alpha"),
+        }
+    }
+}
+
+impl VSColorConversion {
+    /// Overrides first, then the frame props, falling back to BT.709 limited range
+    pub fn new(props: &VSFrameProps, overrides: &VSColorOverrides) -> Self {
+        let matrix = overrides.matrix.unwrap_or(match props.matrix {
+            VSMatrix::Rgb | VSMatrix::Unspecified | VSMatrix::Reserved3 => VSMatrix::BT709,
+            matrix => matrix,
+        });
+
+        let color_range = overrides.color_range.unwrap_or(match props.color_range {
+            VSColorRange::Unspecfied => VSColorRange::Limited,
+            range => range,
+        });
+
+        let chroma_location = overrides
+            .chroma_location
+            .unwrap_or(match props.chroma_location {
+                VSChromaLocation::Unspecified => VSChromaLocation::Left,
+                chromaloc => chromaloc,
+            });
+
+        let primaries = overrides.primaries.unwrap_or(match props.primaries {
+            VSPrimaries::BT709
+            | VSPrimaries::BT470m
+            | VSPrimaries::BT470bg
+            | VSPrimaries::ST170M
+            | VSPrimaries::ST240M
+            | VSPrimaries::Film
+            | VSPrimaries::BT2020
+            | VSPrimaries::Xyz
+            | VSPrimaries::DCIP3
+            | VSPrimaries::DCIP3_D65
+            | VSPrimaries::JEDEC_P22 => props.primaries,
+            _ => VSPrimaries::BT709,
+        });
+
+        Self {
+            matrix,
+            color_range,
+            chroma_location,
+            primaries,
+            transfer: props.transfer,
+            linear_hdr: false,
+        }
+    }
+
+    /// RGB sources only use the transfer and primaries, always full range
+    pub fn rgb(props: &VSFrameProps, overrides: &VSColorOverrides) -> Self {
+        Self {
+            matrix: VSMatrix::Rgb,
+            color_range: VSColorRange::Full,
+            ..Self::new(props, overrides)
+        }
+    }
+
+    pub fn is_rgb(&self) -> bool {
+        self.matrix == VSMatrix::Rgb
+    }
+
+    /// zimg derives these matrices from the primaries and transfer
+    pub fn matrix_needs_primaries(&self) -> bool {
+        matches!(
+            self.matrix,
+            VSMatrix::ChromaNcl | VSMatrix::Chromacl | VSMatrix::ICtCp
+        )
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        matches!(
+            self.transfer,
+            VSTransferCharacteristics::ST2084 | VSTransferCharacteristics::STD_B67
+        )
+    }
+
+    /// Gamut is converted to BT.709 only for SDR gamma transfers,
+    /// otherwise the primaries can't be linearized
+    pub fn converts_gamut(&self) -> bool {
+        self.primaries != VSPrimaries::BT709
+            && matches!(
+                self.transfer,
+                VSTransferCharacteristics::BT709
+                    | VSTransferCharacteristics::BT470m
+                    | VSTransferCharacteristics::BT470bg
+                    | VSTransferCharacteristics::BT601
+                    | VSTransferCharacteristics::sRgb
+                    | VSTransferCharacteristics::BT2020_10
+                    | VSTransferCharacteristics::BT2020_12
+            )
+    }
+
+    /// Value for the VS resizer `range_in` argument, the inverse of `_ColorRange`
+    pub fn resize_range(&self) -> i64 {
+        if self.color_range == VSColorRange::Full {
+            1
+        } else {
+            0
+        }
+    }
+}
+
 impl VSDitherAlgo {
     pub const fn as_str(&self) -> &str {
         match self {